@@ -0,0 +1,93 @@
+//! Iteration kernels on single points of different escape behavior and a full view.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use indicatif::ProgressBar;
+use mandelbrot::{
+    calc_mandelbrot, mandelbrot_at_point, mandelbrot_at_vec, mandelbrot_at_vec8, Complex4,
+    Complex8, Offsets, Variant, View, TILE,
+};
+use std::hint::black_box;
+
+const THRESHOLD: f64 = 4.0;
+const ITERS: [usize; 3] = [100, 1000, 10000];
+
+/// (name, real part, imaginary part)
+const POINTS: [(&str, f64, f64); 3] = [
+    ("in-set", -0.1, 0.1),
+    ("escaping", 1.0, 1.0),
+    ("boundary", -0.743_566_9, 0.131_402_3),
+];
+
+fn kernels(c: &mut Criterion) {
+    for (name, cx, cy) in POINTS {
+        let mut group = c.benchmark_group(name);
+        for iters in ITERS {
+            // the fast interior would skip the point of the set at once
+            group.bench_with_input(BenchmarkId::new("point", iters), &iters, |b, &iters| {
+                b.iter(|| {
+                    mandelbrot_at_point(black_box(cx), black_box(cy), iters, THRESHOLD, false)
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("vec4", iters), &iters, |b, &iters| {
+                let c = Complex4::from_row([cx; 4], cy);
+                b.iter(|| mandelbrot_at_vec(black_box(&c), iters, THRESHOLD, false))
+            });
+            group.bench_with_input(BenchmarkId::new("vec8", iters), &iters, |b, &iters| {
+                let c = Complex8::from_row([cx; 8], cy);
+                b.iter(|| mandelbrot_at_vec8(black_box(&c), iters, THRESHOLD, false))
+            });
+        }
+        group.finish();
+    }
+}
+
+fn full_view(c: &mut Criterion) {
+    let (width, height) = (320, 180);
+    let (x_min, x_max, y_min, y_max) =
+        Variant::Mandelbrot.default_coords(width as f64 / height as f64);
+    let view = View {
+        x_min,
+        x_max,
+        y_min,
+        y_max,
+        width,
+        height,
+        rows: (0, height),
+        columns: (0, width),
+        deadline: None,
+        offsets: Offsets::None,
+        rotation: 0.0,
+        rows_per_task: TILE,
+    };
+    let progress = ProgressBar::hidden();
+
+    let mut group = c.benchmark_group("calc_mandelbrot");
+    group.sample_size(20);
+    for iters in ITERS {
+        group.bench_with_input(BenchmarkId::from_parameter(iters), &iters, |b, &iters| {
+            b.iter(|| calc_mandelbrot(iters, THRESHOLD, true, black_box(&view), &progress))
+        });
+    }
+    group.finish();
+
+    // from a task per row up to the default bands, the task overhead shows at few iterations
+    let mut group = c.benchmark_group("rows_per_task");
+    group.sample_size(20);
+    for rows_per_task in [1, 4, 16, TILE] {
+        let view = View {
+            rows_per_task,
+            ..view
+        };
+        group.bench_with_input(
+            BenchmarkId::from_parameter(rows_per_task),
+            &view,
+            |b, view| {
+                b.iter(|| calc_mandelbrot(ITERS[0], THRESHOLD, true, black_box(view), &progress))
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kernels, full_view);
+criterion_main!(benches);
@@ -0,0 +1,177 @@
+//! Escape values of the bands of rows finished so far, saved while calculating so an
+//! interrupted render continues from them.
+//!
+//! The file starts with magic "MBCP", version (u32) and the parameters of the calculation
+//! (u64 length and UTF-8), every finished band follows as its first row (u64) and a raw
+//! dump of its escape values. The band cut short by an interruption is dropped on resume.
+
+use indicatif::ProgressBar;
+use mandelbrot::{calc_escaped, CalcOptions, Escaped, MandelbrotError, RawHeader, View, TILE};
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    mem,
+    path::Path,
+};
+use tracing::info;
+
+const MAGIC: &[u8; 4] = b"MBCP";
+/// Version of the layout of the checkpoint, bumped on every incompatible change
+const VERSION: u32 = 1;
+
+/// Parameters the escape values depend on, a checkpoint resumes with the same ones only
+fn fingerprint(options: &CalcOptions, view: &View) -> String {
+    // neither the deadline nor the split into parallel tasks change the values
+    let view = View {
+        deadline: None,
+        rows_per_task: TILE,
+        ..*view
+    };
+    format!("{:?} {:?}", options, view)
+}
+
+fn invalid(path: &Path, msg: impl Display) -> MandelbrotError {
+    let msg = format!("checkpoint \"{}\": {}", path.display(), msg);
+    io::Error::new(io::ErrorKind::InvalidData, msg).into()
+}
+
+/// Calculates the escape values of the view in bands of `TILE` rows, every finished band
+/// is saved into `path` right away. With `resume` the bands already saved into `path` are
+/// kept and the missing ones calculated, it errors when the parameters of the calculation
+/// aren't the ones the checkpoint was written with
+pub fn calc_resumable(
+    options: &CalcOptions,
+    view: &View,
+    path: &Path,
+    resume: bool,
+    progress: &ProgressBar,
+) -> Result<Escaped, MandelbrotError> {
+    let params = fingerprint(options, view);
+    let width = view.columns.1 - view.columns.0;
+    let bands: Vec<(usize, usize)> = (view.rows.0..view.rows.1)
+        .step_by(TILE)
+        .map(|first| (first, (first + TILE).min(view.rows.1)))
+        .collect();
+    let mut done: Vec<Option<Escaped>> = bands.iter().map(|_| None).collect();
+
+    let mut out = if resume {
+        let (saved, len) = load(path, &params, width, &bands)?;
+        info!(
+            "Resuming \"{}\" with {} of {} bands of rows done",
+            path.display(),
+            saved.len(),
+            bands.len()
+        );
+        for (index, escaped) in saved {
+            done[index] = Some(escaped);
+        }
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(len)?;
+        file.seek(SeekFrom::End(0))?;
+        BufWriter::new(file)
+    } else {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(params.len() as u64).to_le_bytes())?;
+        out.write_all(params.as_bytes())?;
+        out.flush()?;
+        out
+    };
+
+    for (&rows, band) in bands.iter().zip(done.iter_mut()) {
+        if band.is_some() {
+            progress.inc(((rows.1 - rows.0) * options.samples) as u64);
+            continue;
+        }
+        let escaped = calc_escaped(options, &View { rows, ..*view }, progress);
+        let header = RawHeader {
+            width,
+            height: rows.1 - rows.0,
+            iters: options.iters,
+        };
+        out.write_all(&(rows.0 as u64).to_le_bytes())?;
+        escaped.write_raw(&mut out, &header)?;
+        out.flush()?;
+        *band = Some(escaped);
+    }
+
+    let mut bands = done.into_iter().flatten();
+    let mut escaped = bands.next().expect("views have at least a row");
+    for band in bands {
+        escaped.append(band);
+    }
+    Ok(escaped)
+}
+
+/// Saved bands along with their index in `bands`, and the length of the file up to the
+/// last complete one
+fn load(
+    path: &Path,
+    params: &str,
+    width: usize,
+    bands: &[(usize, usize)],
+) -> Result<(Vec<(usize, Escaped)>, u64), MandelbrotError> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut magic = [0; 4];
+    if input.read_exact(&mut magic).is_err() || &magic != MAGIC {
+        return Err(invalid(path, "not a checkpoint of a render"));
+    }
+    let mut word = [0; 4];
+    let mut bytes = [0; 8];
+    input
+        .read_exact(&mut word)
+        .and_then(|_| input.read_exact(&mut bytes))
+        .map_err(|_| invalid(path, "truncated header"))?;
+    let version = u32::from_le_bytes(word);
+    if version != VERSION {
+        let msg = format!("version {} (expected {})", version, VERSION);
+        return Err(invalid(path, msg));
+    }
+    let mut saved = Vec::new();
+    (&mut input)
+        .take(u64::from_le_bytes(bytes))
+        .read_to_end(&mut saved)?;
+    if saved != params.as_bytes() {
+        return Err(invalid(
+            path,
+            "written with other parameters, the size, bounds, iterations and calculation \
+             flags must be the same",
+        ));
+    }
+
+    let mut done = Vec::new();
+    let mut len = input.stream_position()?;
+    let mut kind = None;
+    loop {
+        let Ok((first, (header, escaped))) = input
+            .read_exact(&mut bytes)
+            .map_err(MandelbrotError::from)
+            .and_then(|_| Escaped::read_raw(&mut input))
+            .map(|band| (u64::from_le_bytes(bytes) as usize, band))
+        else {
+            break;
+        };
+        let index = bands.iter().position(|&(row, _)| row == first);
+        let kind = *kind.get_or_insert(mem::discriminant(&escaped));
+        match index {
+            Some(index)
+                if header.width == width
+                    && header.height == bands[index].1 - first
+                    && mem::discriminant(&escaped) == kind =>
+            {
+                done.push((index, escaped))
+            }
+            _ => {
+                return Err(invalid(
+                    path,
+                    format!("band of rows at {} doesn't fit the picture", first),
+                ))
+            }
+        }
+        len = input.stream_position()?;
+    }
+
+    Ok((done, len))
+}
@@ -0,0 +1,406 @@
+//! Render parameters loaded from a TOML file, flags given on the command line win
+//! over the file field by field.
+
+use crate::{
+    parse_aa_threshold, parse_bailout, parse_gamma, parse_hue_scale, parse_light_elevation,
+    parse_palette_offset, parse_pixel_aspect, parse_set_color, parse_size, parse_time_limit,
+    parse_zoom, Args, Command, Location, AXIS_BOUNDS,
+};
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use image::Rgb;
+use mandelbrot::{
+    BitDepth, ColorMode, Dither, Interior, Palette, PaletteSpace, Precision, SamplePattern, Scale,
+    SimdWidth, Trap,
+};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{error::Error, fs, path::Path, path::PathBuf};
+
+/// Same fields as `Args`, every one of them is optional
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    iters: Option<usize>,
+    width: Option<usize>,
+    height: Option<usize>,
+    x_min: Option<f64>,
+    x_max: Option<f64>,
+    y_min: Option<f64>,
+    y_max: Option<f64>,
+    fix_aspect: Option<bool>,
+    pixel_aspect: Option<f64>,
+    center_re: Option<f64>,
+    center_im: Option<f64>,
+    zoom: Option<f64>,
+    rotate: Option<f64>,
+    pan_x: Option<f64>,
+    pan_y: Option<f64>,
+    embed_metadata: Option<bool>,
+    metadata_sidecar: Option<bool>,
+    dump_raw: Option<PathBuf>,
+    export_csv: Option<PathBuf>,
+    legend: Option<PathBuf>,
+    #[serde(default, deserialize_with = "size")]
+    legend_size: Option<(usize, usize)>,
+    tile_height: Option<u32>,
+    time_limit: Option<f64>,
+    #[serde(default, deserialize_with = "sizes")]
+    sizes: Option<Vec<(usize, usize)>>,
+    roi: Option<Vec<usize>>,
+    output: Option<PathBuf>,
+    no_overwrite: Option<bool>,
+    mask: Option<bool>,
+    invert: Option<bool>,
+    #[serde(default, deserialize_with = "value_enum")]
+    dither: Option<Dither>,
+    quality: Option<u8>,
+    #[serde(default, deserialize_with = "value_enum")]
+    bit_depth: Option<BitDepth>,
+    #[serde(default, deserialize_with = "value_enum")]
+    palette: Option<Palette>,
+    palette_image: Option<PathBuf>,
+    random_palette: Option<u64>,
+    #[serde(default, deserialize_with = "value_enum")]
+    palette_space: Option<PaletteSpace>,
+    palette_offset: Option<f32>,
+    #[serde(default, deserialize_with = "set_color")]
+    set_color: Option<Rgb<u8>>,
+    #[serde(default, deserialize_with = "set_color")]
+    degenerate_color: Option<Rgb<u8>>,
+    gamma: Option<f32>,
+    #[serde(default, deserialize_with = "value_enum")]
+    scale: Option<Scale>,
+    color_clamp: Option<Vec<f64>>,
+    smooth: Option<bool>,
+    power: Option<u32>,
+    bailout: Option<f64>,
+    #[serde(default, deserialize_with = "value_enum")]
+    simd_width: Option<SimdWidth>,
+    #[serde(default, deserialize_with = "value_enum")]
+    precision: Option<Precision>,
+    threads: Option<usize>,
+    rows_per_task: Option<u32>,
+    deep: Option<bool>,
+    strict_precision: Option<bool>,
+    no_fast_interior: Option<bool>,
+    samples: Option<u32>,
+    #[serde(default, deserialize_with = "value_enum")]
+    aa_pattern: Option<SamplePattern>,
+    aa_seed: Option<u64>,
+    adaptive_aa: Option<bool>,
+    aa_threshold: Option<f64>,
+    auto_iters: Option<bool>,
+    auto_iters_adaptive: Option<bool>,
+    auto_iters_max: Option<usize>,
+    flip_y: Option<bool>,
+    verbose: Option<bool>,
+    quiet: Option<bool>,
+    progress: Option<bool>,
+    #[serde(default, deserialize_with = "value_enum")]
+    color_mode: Option<ColorMode>,
+    stripe_density: Option<f64>,
+    hue_scale: Option<f32>,
+    light_angle: Option<f64>,
+    light_elevation: Option<f64>,
+    #[serde(default, deserialize_with = "value_enum")]
+    trap: Option<Trap>,
+    #[serde(default, deserialize_with = "value_enum")]
+    interior_color: Option<Interior>,
+    /// Location preset, used when no subcommand is given
+    #[serde(default, deserialize_with = "value_enum")]
+    location: Option<Location>,
+}
+
+/// Accepts the same names as the command line, numeric ones may be written as integers
+fn value_enum<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    let name = match toml::Value::deserialize(de)? {
+        toml::Value::String(s) => s,
+        toml::Value::Integer(n) => n.to_string(),
+        other => {
+            return Err(D::Error::custom(format!(
+                "expected a name, got {}",
+                other.type_str()
+            )))
+        }
+    };
+
+    T::from_str(&name, true).map(Some).map_err(|_| {
+        let names: Vec<_> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        D::Error::custom(format!(
+            "invalid value \"{}\", expected one of {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+/// Same string as the command line, "R,G,B" or a single gray value
+fn set_color<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Rgb<u8>>, D::Error> {
+    let s = String::deserialize(de)?;
+    parse_set_color(&s).map(Some).map_err(D::Error::custom)
+}
+
+/// Same string as the command line, "WxH"
+fn size<'de, D: Deserializer<'de>>(de: D) -> Result<Option<(usize, usize)>, D::Error> {
+    let s = String::deserialize(de)?;
+    parse_size(&s).map(Some).map_err(D::Error::custom)
+}
+
+/// List of "WxH" strings
+fn sizes<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Vec<(usize, usize)>>, D::Error> {
+    let sizes = Vec::<String>::deserialize(de)?;
+    sizes
+        .iter()
+        .map(|s| parse_size(s))
+        .collect::<Result<_, _>>()
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config \"{}\": {}", path.display(), e))?;
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| format!("invalid config \"{}\": {}", path.display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Same checks as the value parsers of the command line
+    fn validate(&self) -> Result<(), String> {
+        let invalid =
+            |key: &str, msg: String| Err(format!("invalid config key `{}`: {}", key, msg));
+
+        if let Some(quality) = self.quality.filter(|&q| q > 100) {
+            return invalid("quality", format!("{} is not in 0..=100", quality));
+        }
+        if let Some(power) = self.power.filter(|&p| p < 2) {
+            return invalid("power", format!("{} is not in 2..", power));
+        }
+        if let Some(samples) = self.samples.filter(|s| !(1..=16).contains(s)) {
+            return invalid("samples", format!("{} is not in 1..=16", samples));
+        }
+        if let Some(Err(e)) = self
+            .aa_threshold
+            .map(|t| parse_aa_threshold(&t.to_string()))
+        {
+            return invalid("aa_threshold", e);
+        }
+        if self.auto_iters_max == Some(0) {
+            return invalid("auto_iters_max", "0 is not in 1..".to_string());
+        }
+        if self.rows_per_task == Some(0) {
+            return invalid("rows_per_task", "0 is not in 1..".to_string());
+        }
+        if self.tile_height == Some(0) {
+            return invalid("tile_height", "0 is not in 1..".to_string());
+        }
+        if let Some(Err(e)) = self.bailout.map(|b| parse_bailout(&b.to_string())) {
+            return invalid("bailout", e);
+        }
+        if let Some(Err(e)) = self.gamma.map(|g| parse_gamma(&g.to_string())) {
+            return invalid("gamma", e);
+        }
+        if let Some(Err(e)) = self.hue_scale.map(|s| parse_hue_scale(&s.to_string())) {
+            return invalid("hue_scale", e);
+        }
+        if let Some(Err(e)) = self
+            .pixel_aspect
+            .map(|a| parse_pixel_aspect(&a.to_string()))
+        {
+            return invalid("pixel_aspect", e);
+        }
+        if let Some(Err(e)) = self
+            .light_elevation
+            .map(|e| parse_light_elevation(&e.to_string()))
+        {
+            return invalid("light_elevation", e);
+        }
+        if let Some(Err(e)) = self
+            .palette_offset
+            .map(|o| parse_palette_offset(&o.to_string()))
+        {
+            return invalid("palette_offset", e);
+        }
+        if let Some(Err(e)) = self.time_limit.map(|t| parse_time_limit(&t.to_string())) {
+            return invalid("time_limit", e);
+        }
+        if let Some(Err(e)) = self.zoom.map(|z| parse_zoom(&z.to_string())) {
+            return invalid("zoom", e);
+        }
+
+        if let Some(roi) = self.roi.as_ref().filter(|roi| roi.len() != 4) {
+            let msg = format!("expected [X, Y, W, H], got {} values", roi.len());
+            return invalid("roi", msg);
+        }
+        if let Some(clamp) = &self.color_clamp {
+            match clamp[..] {
+                [low, high] if low < high && low.is_finite() && high.is_finite() => {}
+                [low, high] => {
+                    let msg = format!("{} {} needs a finite low below high", low, high);
+                    return invalid("color_clamp", msg);
+                }
+                _ => {
+                    let msg = format!("expected [LOW, HIGH], got {} values", clamp.len());
+                    return invalid("color_clamp", msg);
+                }
+            }
+        }
+        if self.palette_image.is_some() && self.random_palette.is_some() {
+            let msg = "palette_image and random_palette are exclusive";
+            return Err(format!("invalid config: {}", msg));
+        }
+
+        let axis = [self.x_min, self.x_max, self.y_min, self.y_max];
+        let center = [self.center_re, self.center_im, self.zoom];
+        if axis.iter().any(Option::is_some) && center.iter().any(Option::is_some) {
+            let msg = "the axis bounds are exclusive with center_re, center_im and zoom";
+            return Err(format!("invalid config: {}", msg));
+        }
+
+        Ok(())
+    }
+
+    /// Fills the fields of `args` not given on the command line
+    pub fn merge(self, args: &mut Args, matches: &ArgMatches) {
+        let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        // the command line bounds of one kind drop the config bounds of the other one
+        let cli_axis = AXIS_BOUNDS.iter().any(|id| explicit(id));
+        let cli_center = ["center_re", "center_im", "zoom", "view"]
+            .iter()
+            .any(|id| explicit(id));
+
+        macro_rules! merge {
+            ($($field:ident),*) => {$(
+                if let Some(value) = self.$field {
+                    if !explicit(stringify!($field)) {
+                        args.$field = value;
+                    }
+                }
+            )*};
+        }
+        macro_rules! merge_bounds {
+            ($skip:expr, $($field:ident),*) => {$(
+                if self.$field.is_some() && !$skip && !explicit(stringify!($field)) {
+                    args.$field = self.$field;
+                }
+            )*};
+        }
+
+        merge!(
+            iters,
+            width,
+            height,
+            fix_aspect,
+            pixel_aspect,
+            legend_size,
+            rotate,
+            pan_x,
+            pan_y,
+            embed_metadata,
+            metadata_sidecar,
+            output,
+            no_overwrite,
+            mask,
+            invert,
+            bit_depth,
+            palette,
+            palette_space,
+            gamma,
+            scale,
+            palette_offset,
+            smooth,
+            power,
+            bailout,
+            simd_width,
+            precision,
+            threads,
+            rows_per_task,
+            deep,
+            strict_precision,
+            no_fast_interior,
+            samples,
+            aa_pattern,
+            aa_seed,
+            adaptive_aa,
+            aa_threshold,
+            auto_iters,
+            auto_iters_adaptive,
+            auto_iters_max,
+            flip_y,
+            verbose,
+            quiet,
+            progress,
+            color_mode,
+            stripe_density,
+            hue_scale,
+            light_angle,
+            light_elevation
+        );
+        if self.trap.is_some() && !explicit("trap") {
+            args.trap = self.trap;
+        }
+        if self.interior_color.is_some() && !explicit("interior_color") {
+            args.interior_color = self.interior_color;
+        }
+        if self.quality.is_some() && !explicit("quality") {
+            args.quality = self.quality;
+        }
+        if self.dither.is_some() && !explicit("dither") {
+            args.dither = self.dither;
+        }
+        if self.set_color.is_some() && !explicit("set_color") {
+            args.set_color = self.set_color;
+        }
+        if self.degenerate_color.is_some() && !explicit("degenerate_color") {
+            args.degenerate_color = self.degenerate_color;
+        }
+        // a palette of the command line drops the other one of the config
+        let cli_palette = explicit("palette_image") || explicit("random_palette");
+        if self.palette_image.is_some() && !cli_palette {
+            args.palette_image = self.palette_image;
+        }
+        if self.random_palette.is_some() && !cli_palette {
+            args.random_palette = self.random_palette;
+        }
+        if self.dump_raw.is_some() && !explicit("dump_raw") {
+            args.dump_raw = self.dump_raw;
+        }
+        if self.legend.is_some() && !explicit("legend") {
+            args.legend = self.legend;
+        }
+        if self.export_csv.is_some() && !explicit("export_csv") {
+            args.export_csv = self.export_csv;
+        }
+        if self.tile_height.is_some() && !explicit("tile_height") {
+            args.tile_height = self.tile_height;
+        }
+        if self.time_limit.is_some() && !explicit("time_limit") {
+            args.time_limit = self.time_limit;
+        }
+        if self.sizes.is_some() && !explicit("sizes") {
+            args.sizes = self.sizes;
+        }
+        if self.roi.is_some() && !explicit("roi") {
+            args.roi = self.roi;
+        }
+        if self.color_clamp.is_some() && !explicit("color_clamp") {
+            args.color_clamp = self.color_clamp;
+        }
+        merge_bounds!(cli_center, x_min, x_max, y_min, y_max);
+        merge_bounds!(cli_axis, center_re, center_im, zoom);
+
+        if args.command.is_none() {
+            args.command = self.location.map(Command::Location);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Keyframes of a zoom animation loaded from a TOML file, and the frames in between.
+
+use serde::Deserialize;
+use std::{error::Error, fs, path::Path};
+
+/// Center, zoom relative to the whole fractal and iterations of one point of the animation
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    pub center_re: f64,
+    pub center_im: f64,
+    pub zoom: f64,
+    pub iters: usize,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct File {
+    keyframe: Vec<Keyframe>,
+}
+
+/// Keyframes of the `[[keyframe]]` tables of the file, in the order of the file
+pub fn load(path: &Path) -> Result<Vec<Keyframe>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read keyframes \"{}\": {}", path.display(), e))?;
+    let file: File = toml::from_str(&text)
+        .map_err(|e| format!("invalid keyframes \"{}\": {}", path.display(), e))?;
+
+    if file.keyframe.len() < 2 {
+        return Err(format!("keyframes \"{}\": at least 2 are needed", path.display()).into());
+    }
+    for (i, key) in file.keyframe.iter().enumerate() {
+        if !(key.zoom > 0.0 && key.zoom.is_finite()) {
+            let msg = format!("zoom must be a positive number, got {}", key.zoom);
+            return Err(format!("keyframe {} of \"{}\": {}", i, path.display(), msg).into());
+        }
+        if key.iters == 0 {
+            let msg = "iters must be at least 1";
+            return Err(format!("keyframe {} of \"{}\": {}", i, path.display(), msg).into());
+        }
+    }
+
+    Ok(file.keyframe)
+}
+
+/// Smoothstep, the motion slows down to a stop at every keyframe
+fn ease(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// State of the animation at `frame` of `frames`, the keyframes split the frames evenly.
+///
+/// Zoom is interpolated geometrically so the apparent zoom speed stays steady, the center
+/// moves with the same exponential curve so the point zoomed into stays under the cursor
+/// instead of drifting out of the shrinking view.
+pub fn interpolate(keys: &[Keyframe], frame: u32, frames: u32) -> Keyframe {
+    assert!(keys.len() >= 2, "interpolation needs at least 2 keyframes");
+    let t = if frames > 1 {
+        frame.min(frames - 1) as f64 / (frames - 1) as f64
+    } else {
+        0.0
+    };
+
+    let segments = keys.len() - 1;
+    let pos = t * segments as f64;
+    let segment = (pos as usize).min(segments - 1);
+    let s = ease(pos - segment as f64);
+    let (a, b) = (&keys[segment], &keys[segment + 1]);
+
+    let ratio = b.zoom / a.zoom;
+    let zoom = a.zoom * ratio.powf(s);
+    // share of the way from a to b, offsets from b shrink with the magnification
+    let along = if (ratio - 1.0).abs() < 1e-12 {
+        s
+    } else {
+        (1.0 - ratio.powf(-s)) / (1.0 - ratio.recip())
+    };
+    let iters = a.iters as f64 + (b.iters as f64 - a.iters as f64) * s;
+
+    Keyframe {
+        center_re: a.center_re + (b.center_re - a.center_re) * along,
+        center_im: a.center_im + (b.center_im - a.center_im) * along,
+        zoom,
+        iters: iters.round() as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(center_re: f64, center_im: f64, zoom: f64, iters: usize) -> Keyframe {
+        Keyframe {
+            center_re,
+            center_im,
+            zoom,
+            iters,
+        }
+    }
+
+    /// Panics unless `key` is `expected` up to the rounding of the centers
+    fn assert_at(key: Keyframe, expected: Keyframe) {
+        assert!(
+            (key.center_re - expected.center_re).abs() < 1e-15,
+            "{:?}",
+            key
+        );
+        assert!(
+            (key.center_im - expected.center_im).abs() < 1e-15,
+            "{:?}",
+            key
+        );
+        assert_eq!((key.zoom, key.iters), (expected.zoom, expected.iters));
+    }
+
+    #[test]
+    fn frames_start_and_end_at_the_keyframes() {
+        let keys = [
+            key(-0.5, 0.0, 1.0, 100),
+            key(-0.75, 0.1, 1e6, 2000),
+            key(0.3, 0.0, 10.0, 50),
+        ];
+        assert_at(interpolate(&keys, 0, 9), keys[0]);
+        // 4 frames in each of the 2 segments
+        assert_at(interpolate(&keys, 4, 9), keys[1]);
+        assert_at(interpolate(&keys, 8, 9), keys[2]);
+        // past the last frame, and a single frame
+        assert_at(interpolate(&keys, 20, 9), keys[2]);
+        assert_at(interpolate(&keys, 0, 1), keys[0]);
+    }
+
+    #[test]
+    fn zoom_is_geometric_and_eased() {
+        let keys = [key(0.0, 0.0, 1.0, 100), key(0.0, 0.0, 1e4, 300)];
+        // halfway through the frames the smoothstep is at a half
+        let middle = interpolate(&keys, 50, 101);
+        assert!((middle.zoom - 100.0).abs() < 1e-9, "{}", middle.zoom);
+        assert_eq!(middle.iters, 200);
+
+        // the steps of the magnification grow up to the middle and shrink after it
+        let steps: Vec<f64> = (0..101)
+            .map(|frame| interpolate(&keys, frame, 101).zoom.ln())
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .collect();
+        assert!(steps.iter().all(|&step| step > 0.0));
+        assert!(steps[..50].windows(2).all(|w| w[0] < w[1]));
+        assert!(steps[50..].windows(2).all(|w| w[0] > w[1]));
+        assert!((steps[49] - steps[50]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn center_keeps_the_target_in_view() {
+        let keys = [key(-0.5, 0.0, 1.0, 100), key(-0.75, 0.1, 1e4, 100)];
+        let (a, b) = (&keys[0], &keys[1]);
+        let mut offset = f64::INFINITY;
+        for frame in 0..=60 {
+            let key = interpolate(&keys, frame, 61);
+            // the center lies on the line from a to b
+            let along = (key.center_re - a.center_re) / (b.center_re - a.center_re);
+            let along_im = (key.center_im - a.center_im) / (b.center_im - a.center_im);
+            assert!((along - along_im).abs() < 1e-9);
+            // the target never moves farther away from the center of the picture, in
+            // widths of the views
+            let target = (b.center_re - key.center_re).hypot(b.center_im - key.center_im);
+            assert!(
+                target * key.zoom <= offset * (1.0 + 1e-12),
+                "frame {}",
+                frame
+            );
+            offset = target * key.zoom;
+        }
+        assert_eq!(offset, 0.0);
+
+        // without a change of the zoom the center moves with the smoothstep alone
+        let flat = [key(0.0, 0.0, 5.0, 100), key(1.0, 2.0, 5.0, 100)];
+        let middle = interpolate(&flat, 5, 11);
+        assert!((middle.center_re - 0.5).abs() < 1e-12);
+        assert!((middle.center_im - 1.0).abs() < 1e-12);
+        assert_eq!(middle.zoom, 5.0);
+    }
+}
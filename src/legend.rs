@@ -0,0 +1,127 @@
+//! Gradient bar of the active palette with the escape counts at its ticks, a key of the
+//! colors of the picture for figures.
+
+use image::{DynamicImage, Rgb, RgbImage};
+use mandelbrot::{color_image, save_image, DrawOptions, Escaped, MandelbrotError};
+use std::path::Path;
+
+/// Glyphs of 3x5 pixels of the digits, a row per element from the top with the leftmost
+/// pixel in the highest of the 3 bits
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+/// Pixels of the legend per pixel of the glyphs
+const SCALE: u32 = 2;
+/// Blank border around the bar and the labels
+const MARGIN: u32 = 4;
+/// Length of the tick marks below the bar
+const TICK: u32 = 4;
+/// Ticks at these shares of the bar, the labels of the inner ones are left out when they
+/// would run into another one
+const TICKS: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+const BACKGROUND: Rgb<u8> = Rgb([255; 3]);
+const INK: Rgb<u8> = Rgb([0; 3]);
+
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0'..='9' => DIGITS[c as usize - '0' as usize],
+        '-' => [0, 0, 0b111, 0, 0],
+        '.' => [0, 0, 0, 0, 0b010],
+        _ => [0; 5],
+    }
+}
+
+/// Escape count of a tick, with a single decimal when it isn't whole
+fn label(count: f64) -> String {
+    if count.fract() == 0.0 {
+        format!("{}", count)
+    } else {
+        format!("{:.1}", count)
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    (text.len() as u32 * (GLYPH_WIDTH + 1) - 1) * SCALE
+}
+
+fn draw_text(img: &mut RgbImage, text: &str, x: u32, y: u32) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i as u32 * (GLYPH_WIDTH + 1) * SCALE;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for col in (0..GLYPH_WIDTH).filter(|col| (bits >> (GLYPH_WIDTH - 1 - col)) & 1 == 1) {
+                for (dx, dy) in (0..SCALE).flat_map(|dx| (0..SCALE).map(move |dy| (dx, dy))) {
+                    let (px, py) = (left + col * SCALE + dx, y + row as u32 * SCALE + dy);
+                    if px < img.width() && py < img.height() {
+                        img.put_pixel(px, py, INK);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Saves the legend of the escape counts 0..iters, or the range of `--color-clamp`, as
+/// colored by `options` into a bar of `width` x `height` pixels: column c shows the count
+/// at c / width of the range and the ticks below are labeled with their counts
+pub fn save(
+    path: &Path,
+    options: DrawOptions,
+    (width, height): (usize, usize),
+    quality: Option<u8>,
+) -> Result<(), MandelbrotError> {
+    let (low, high) = options.clamp.unwrap_or((0.0, options.iters as f64));
+    let count = |share: f64| low + (high - low) * share;
+    let ramp = Escaped::Smooth((0..width).map(|c| count(c as f64 / width as f64)).collect());
+    let ramp = color_image(&ramp, width as u32, 1, options)?.to_rgb8();
+
+    let (width, height) = (width as u32, height as u32);
+    let text_top = MARGIN + height + TICK + SCALE;
+    let mut img = RgbImage::from_pixel(
+        width + 2 * MARGIN,
+        text_top + GLYPH_HEIGHT * SCALE + MARGIN,
+        BACKGROUND,
+    );
+    for (x, y) in (0..width).flat_map(|x| (0..height).map(move |y| (x, y))) {
+        img.put_pixel(MARGIN + x, MARGIN + y, *ramp.get_pixel(x, 0));
+    }
+
+    let column = |share: f64| ((share * width as f64).round() as u32).min(width - 1);
+    let mut placed: Vec<(u32, u32)> = Vec::new();
+    // the labels of the ends first, the inner ones go where there is room left
+    let order = [0, TICKS.len() - 1].into_iter().chain(1..TICKS.len() - 1);
+    for share in order.map(|i| TICKS[i]) {
+        let x = MARGIN + column(share);
+        for y in MARGIN + height..MARGIN + height + TICK {
+            img.put_pixel(x, y, INK);
+        }
+        let text = label(count(share));
+        let text_width = text_width(&text);
+        let left = x
+            .saturating_sub(text_width / 2)
+            .min((width + 2 * MARGIN).saturating_sub(text_width));
+        let right = left + text_width;
+        // a glyph of room between the labels
+        let gap = GLYPH_WIDTH * SCALE;
+        if placed
+            .iter()
+            .all(|&(l, r)| right + gap <= l || r + gap <= left)
+        {
+            draw_text(&mut img, &text, left, text_top);
+            placed.push((left, right));
+        }
+    }
+
+    save_image(DynamicImage::ImageRgb8(img), path, quality)
+}
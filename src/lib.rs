@@ -0,0 +1,4489 @@
+//! Parallel CPU-based Mandelbrot set engine: SIMD escape time kernels (wide crate)
+//! driven row by row with rayon, coloring and saving of the result picture.
+
+use clap::ValueEnum;
+use image::{
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        jpeg::JpegEncoder,
+        pnm::{PnmEncoder, PnmSubtype, SampleEncoding},
+        webp::WebPEncoder,
+    },
+    error::EncodingError,
+    Delay, DynamicImage, Frame, GenericImage, GrayImage, ImageBuffer, ImageError, ImageFormat,
+    Luma, Rgb, RgbImage,
+};
+use indicatif::ProgressBar;
+use num::Complex;
+use rand::Rng;
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+use serde::Serialize;
+use std::{
+    error::Error,
+    fmt, fs,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    ops::{Add, Mul, Neg, Sub},
+    path::Path,
+    sync::OnceLock,
+    time::Instant,
+};
+use wide::{f32x8, f64x4, f64x8, CmpEq, CmpLe, CmpLt};
+
+/// Number of points iterated at once by the Mandelbrot set kernel
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimdWidth {
+    #[value(name = "4")]
+    Four,
+    #[value(name = "8")]
+    Eight,
+}
+
+impl SimdWidth {
+    /// Whether the CPU runs the kernel of this width, 8 lanes need AVX-512 and the CPUs
+    /// without it fall back to 4 lanes
+    pub fn supported(&self) -> bool {
+        match self {
+            SimdWidth::Four => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdWidth::Eight => is_x86_feature_detected!("avx512f"),
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            SimdWidth::Eight => false,
+        }
+    }
+}
+
+/// Floating point type of the Mandelbrot set kernel
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    F32,
+    F64,
+    /// Double-double, pairs of f64 of about 106 bits
+    Dd,
+}
+
+/// Bits per channel of grayscale pictures
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BitDepth {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+}
+
+/// Mapping of escape values into the normalized ratio looked up in the palette
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Escape value divided by the number of iterations
+    Linear,
+    /// Share of the points outside the set escaping not later (histogram equalization),
+    /// stretched from the lowest escape value to the highest one
+    Histogram,
+    /// Estimated distance to the boundary of the set in pixels, crisp at any zoom level
+    /// (Mandelbrot set only)
+    Distance,
+    /// Average of 0.5 + 0.5 sin(s arg z) over the orbit, a shaded look at the cost of
+    /// a sine and an arctangent per iteration (Mandelbrot set only)
+    Stripe,
+    /// Triangle inequality average, the mean position of |z| between the bounds of
+    /// the triangle inequality over the orbit, a soft pastel shading (Mandelbrot set only)
+    Tia,
+    /// Argument of z at the escape around the hue wheel instead of the palette, rainbow
+    /// petals around the set (Mandelbrot set only)
+    Phase,
+    /// Hue from the smooth escape count and value from the argument of z at the escape,
+    /// `--hue-scale` turns of the hue per iteration (Mandelbrot set only)
+    Hsv,
+    /// Palette of the smooth escape count darkened by the Lambertian shading of the normal
+    /// derived from the derivative of z, lit from `--light-angle` and `--light-elevation`,
+    /// an embossed relief (Mandelbrot set only)
+    Lighting,
+}
+
+/// Spreading of the rounding errors of the 8-bit channels that hides the banding
+/// of the smooth gradients, both of them are deterministic
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// 8x8 Bayer threshold matrix, a regular fine pattern
+    Bayer,
+    /// Floyd-Steinberg error diffusion along the rows, a grainy pattern
+    FloydSteinberg,
+}
+
+/// Positions of the samples of the supersampled pixels
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SamplePattern {
+    /// Regular grid of samples x samples points
+    #[default]
+    Grid,
+    /// The same grid turned by atan(1 / samples), no two samples of a pixel share a row or
+    /// a column, straight edges close to vertical or horizontal get more levels of coverage
+    RotatedGrid,
+    /// One random point in every cell of the grid, the regular aliasing turns into noise
+    Random,
+}
+
+/// Settings of mapping escape counts into pixels
+#[derive(Debug, Clone, Copy)]
+pub struct DrawOptions<'a> {
+    pub iters: usize,
+    pub bit_depth: BitDepth,
+    pub palette: Palette,
+    pub color_mode: ColorMode,
+    /// White points of the set and black other ones instead of the palette
+    pub mask: bool,
+    /// The palette gets the escape ratio raised to 1 / gamma
+    pub gamma: f32,
+    /// Curve of the escape ratio, applied before the gamma
+    pub scale: Scale,
+    /// Rotation of the palette, the escape ratio t is looked up at (t + offset) mod 1
+    pub palette_offset: f32,
+    /// Color of the points of the set instead of white in grayscale and black with the
+    /// other palettes
+    pub set_color: Option<Rgb<u8>>,
+    /// Color of the degenerate escape values that aren't finite, such as the NaN of the
+    /// smooth count of an orbit overflowing f64, instead of the color of the last escape
+    /// count
+    pub degenerate_color: Option<Rgb<u8>>,
+    /// Every channel value v of the colored picture becomes max - v
+    pub invert: bool,
+    /// Colors of a palette image replacing the palette
+    pub gradient: Option<&'a Gradient>,
+    /// Dithering of the 8-bit pictures, 16-bit ones are left alone
+    pub dither: Option<Dither>,
+    /// Turns of the hue per iteration of the HSV color mode
+    pub hue_scale: f32,
+    /// Escape counts `(low, high)` stretched over the whole palette by the linear color
+    /// mode instead of `(0, iters)`, the counts outside get the ends of the palette
+    pub clamp: Option<(f64, f64)>,
+}
+
+impl DrawOptions<'_> {
+    /// Function mapping the escape ratio in range [0, 1] into a color of the palette
+    /// or the palette image
+    pub fn color_fn(&self) -> Box<dyn Fn(f32) -> Rgb<u8> + Sync + '_> {
+        if self.color_mode == ColorMode::Phase {
+            return Box::new(hue_wheel);
+        }
+        match self.gradient {
+            Some(gradient) => Box::new(|t| gradient.color(t)),
+            None => Box::new(self.palette.color_fn()),
+        }
+    }
+
+    /// Same as `color_fn` with the channels in range [0, 256) before they get truncated
+    /// into 8 bits
+    pub fn value_fn(&self) -> Box<dyn Fn(f32) -> [f32; 3] + Sync + '_> {
+        if self.color_mode == ColorMode::Phase {
+            return Box::new(hue_wheel_value);
+        }
+        match self.gradient {
+            Some(gradient) => Box::new(|t| gradient.value(t)),
+            None => Box::new(self.palette.value_fn()),
+        }
+    }
+}
+
+/// Named color maps of the escape ratio
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    /// Shades of gray, points of the set are white
+    Grayscale,
+    /// Black to red to yellow to white
+    Fire,
+    /// Deep blue to cyan to white
+    Ocean,
+    /// Full hue wheel from red to violet
+    Rainbow,
+}
+
+impl Palette {
+    /// Function mapping the escape ratio in range [0, 1] into a color
+    pub fn color_fn(&self) -> fn(f32) -> Rgb<u8> {
+        match self {
+            Palette::Grayscale => grayscale,
+            Palette::Fire => fire,
+            Palette::Ocean => ocean,
+            Palette::Rainbow => rainbow,
+        }
+    }
+
+    /// Closure for `color_counts` coloring the escape counts linearly, the points of the set
+    /// are white in grayscale and black with the other palettes
+    pub fn count_color(self) -> impl Fn(u64, usize) -> Rgb<u8> + Sync {
+        let color = self.color_fn();
+        let set_color = match self {
+            Palette::Grayscale => Rgb([255; 3]),
+            _ => Rgb([0; 3]),
+        };
+        move |count, iters| {
+            if count == iters as u64 {
+                set_color
+            } else {
+                color(count as f32 * (1.0 / iters as f32))
+            }
+        }
+    }
+
+    /// Function mapping the escape ratio in range [0, 1] into the channels of the color
+    /// in range [0, 255] before they get truncated into 8 bits
+    pub fn value_fn(&self) -> fn(f32) -> [f32; 3] {
+        match self {
+            Palette::Grayscale => grayscale_value,
+            Palette::Fire => fire_value,
+            Palette::Ocean => ocean_value,
+            Palette::Rainbow => rainbow_value,
+        }
+    }
+}
+
+/// Curves applied to the escape ratio before the palette
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// The ratio as it is
+    Linear,
+    /// Square root of the ratio
+    Sqrt,
+    /// log(1 + k t) / log(1 + k), stretches the low ratios of the fine filaments
+    Log,
+}
+
+/// Steepness k of the logarithmic scale
+pub const LOG_SCALE_STEEPNESS: f32 = 100.0;
+
+impl Scale {
+    /// Function mapping the escape ratio in range [0, 1] into the range [0, 1]
+    pub fn scale_fn(&self) -> fn(f32) -> f32 {
+        match self {
+            Scale::Linear => |t| t,
+            Scale::Sqrt => f32::sqrt,
+            Scale::Log => |t| (t * LOG_SCALE_STEEPNESS).ln_1p() / LOG_SCALE_STEEPNESS.ln_1p(),
+        }
+    }
+}
+
+/// Color space the colors of a `Gradient` are interpolated in
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteSpace {
+    /// Channels of sRGB, the midtones between distant colors get muddy
+    Rgb,
+    /// Perceptually uniform Oklab, clean midtones at the cost of a few cube roots
+    /// per pixel
+    Oklab,
+}
+
+/// Color map taken from the pixels of a 1 x N or N x 1 picture, the first pixel is
+/// the start of the palette
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    colors: Vec<Rgb<u8>>,
+    /// Oklab coordinates of the colors when they are interpolated in Oklab
+    oklab: Option<Vec<[f64; 3]>>,
+}
+
+impl Gradient {
+    pub fn load(path: &Path) -> Result<Gradient, MandelbrotError> {
+        let img = image::open(path)?.to_rgb8();
+        if img.width() != 1 && img.height() != 1 {
+            return Err(MandelbrotError::InvalidPaletteImage(format!(
+                "{}x{} pixels isn't a strip of a single row or column",
+                img.width(),
+                img.height()
+            )));
+        }
+        Ok(Gradient {
+            colors: img.pixels().copied().collect(),
+            oklab: None,
+        })
+    }
+
+    /// Same colors interpolated in `space`
+    pub fn in_space(self, space: PaletteSpace) -> Gradient {
+        let oklab = (space == PaletteSpace::Oklab).then(|| {
+            self.colors
+                .iter()
+                .map(|rgb| srgb_to_oklab(rgb.0.map(|v| v as f64 / 255.0)))
+                .collect()
+        });
+        Gradient { oklab, ..self }
+    }
+
+    /// Ramp of `RANDOM_PALETTE_SIZE` colors from dark to light generated from `seed`, the
+    /// same seed gives the same palette on every platform. Control points of rising
+    /// lightness and random hue and chroma are interpolated linearly in CIELAB, so the
+    /// colors change at a steady perceived pace without jumps
+    pub fn random(seed: u64) -> Gradient {
+        const POINTS: usize = 5;
+        let mut draws = 0;
+        let mut unit = || {
+            draws += 1;
+            (split_mix(seed ^ split_mix(draws)) >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        let points: Vec<[f64; 3]> = (0..POINTS)
+            .map(|k| {
+                // evenly rising lightness with a jitter too small to reverse it
+                let lightness = 12.0 + 78.0 * k as f64 / (POINTS - 1) as f64;
+                let lightness = lightness + 8.0 * (unit() - 0.5);
+                let (hue, chroma) = (std::f64::consts::TAU * unit(), 15.0 + 45.0 * unit());
+                [lightness, chroma * hue.cos(), chroma * hue.sin()]
+            })
+            .collect();
+
+        let colors = (0..RANDOM_PALETTE_SIZE)
+            .map(|i| {
+                let pos = i as f64 / (RANDOM_PALETTE_SIZE - 1) as f64 * (POINTS - 1) as f64;
+                let k = (pos as usize).min(POINTS - 2);
+                let frac = pos - k as f64;
+                let [l, a, b] = std::array::from_fn(|c| {
+                    points[k][c] + (points[k + 1][c] - points[k][c]) * frac
+                });
+                Rgb(lab_to_srgb(l, a, b).map(|v| (v * 255.0 + 0.5) as u8))
+            })
+            .collect();
+        Gradient {
+            colors,
+            oklab: None,
+        }
+    }
+
+    /// Color of the escape ratio in range [0, 1] interpolated between the two closest pixels
+    pub fn color(&self, t: f32) -> Rgb<u8> {
+        Rgb(self.value(t).map(|v| v as u8))
+    }
+
+    /// Same as `color` before the truncation into 8 bits, the channels are shifted by 0.5
+    /// so the truncation rounds the interpolated colors
+    pub fn value(&self, t: f32) -> [f32; 3] {
+        let len = self.colors.len();
+        let pos = t.clamp(0.0, 1.0) * (len - 1) as f32;
+        let i = (pos as usize).min(len - 1);
+        let j = (i + 1).min(len - 1);
+        let frac = pos - i as f32;
+        match &self.oklab {
+            Some(oklab) => {
+                let (a, b) = (oklab[i], oklab[j]);
+                let mixed = std::array::from_fn(|c| a[c] + (b[c] - a[c]) * frac as f64);
+                oklab_to_srgb(mixed).map(|v| (v * 255.0) as f32 + 0.5)
+            }
+            None => {
+                let (a, b) = (self.colors[i], self.colors[j]);
+                std::array::from_fn(|c| a[c] as f32 + (b[c] as f32 - a[c] as f32) * frac + 0.5)
+            }
+        }
+    }
+}
+
+/// Number of colors of `Gradient::random`
+pub const RANDOM_PALETTE_SIZE: usize = 256;
+
+/// sRGB channels in range [0, 1] of the CIELAB color of the D65 white point, the colors
+/// out of the gamut get their channels clamped
+pub fn lab_to_srgb(l: f64, a: f64, b: f64) -> [f64; 3] {
+    const WHITE: [f64; 3] = [0.950_47, 1.0, 1.088_83];
+    let f_inv = |t: f64| {
+        if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            3.0 * (6.0f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        }
+    };
+    let fy = (l + 16.0) / 116.0;
+    let [x, y, z] = [fy + a / 500.0, fy, fy - b / 200.0];
+    let [x, y, z] = [
+        f_inv(x) * WHITE[0],
+        f_inv(y) * WHITE[1],
+        f_inv(z) * WHITE[2],
+    ];
+
+    let linear = [
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    ];
+    linear.map(linear_to_srgb)
+}
+
+/// Linear light of an sRGB channel in range [0, 1]
+fn srgb_to_linear(v: f64) -> f64 {
+    if v <= 0.040_45 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB channel in range [0, 1] of the linear light, clamped into the gamut
+fn linear_to_srgb(v: f64) -> f64 {
+    let v = v.clamp(0.0, 1.0);
+    if v <= 0.003_130_8 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Oklab coordinates (L, a, b) of the sRGB channels in range [0, 1], L runs from 0 for
+/// black to 1 for white
+pub fn srgb_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+    let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+    let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+    [
+        0.210_454_255_3 * l + 0.793_617_785_0 * m - 0.004_072_046_8 * s,
+        1.977_998_495_1 * l - 2.428_592_205_0 * m + 0.450_593_709_9 * s,
+        0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766_0 * s,
+    ]
+}
+
+/// Inverse of `srgb_to_oklab`, the colors out of the gamut get their channels clamped
+pub fn oklab_to_srgb(lab: [f64; 3]) -> [f64; 3] {
+    let [l, a, b] = lab;
+    let l_ = l + 0.396_337_777_4 * a + 0.215_803_757_3 * b;
+    let m_ = l - 0.105_561_345_8 * a - 0.063_854_172_8 * b;
+    let s_ = l - 0.089_484_177_5 * a - 1.291_485_548_0 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+    [
+        4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s,
+        -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s,
+        -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701_0 * s,
+    ]
+    .map(linear_to_srgb)
+}
+
+fn channel(v: f32) -> f32 {
+    v.clamp(0.0, 1.0) * 255.0
+}
+
+fn truncated(value: fn(f32) -> [f32; 3], t: f32) -> Rgb<u8> {
+    Rgb(value(t).map(|v| v as u8))
+}
+
+/// Black to white
+pub fn grayscale(t: f32) -> Rgb<u8> {
+    truncated(grayscale_value, t)
+}
+
+fn grayscale_value(t: f32) -> [f32; 3] {
+    [channel(t); 3]
+}
+
+/// Black to red to yellow to white
+pub fn fire(t: f32) -> Rgb<u8> {
+    truncated(fire_value, t)
+}
+
+fn fire_value(t: f32) -> [f32; 3] {
+    [
+        channel(3.0 * t),
+        channel(3.0 * t - 1.0),
+        channel(3.0 * t - 2.0),
+    ]
+}
+
+/// Deep blue to cyan to white
+pub fn ocean(t: f32) -> Rgb<u8> {
+    truncated(ocean_value, t)
+}
+
+fn ocean_value(t: f32) -> [f32; 3] {
+    [channel(2.0 * t - 1.0), channel(t), channel(t.sqrt())]
+}
+
+/// Red to yellow to green to cyan to blue to violet
+pub fn rainbow(t: f32) -> Rgb<u8> {
+    truncated(rainbow_value, t)
+}
+
+fn rainbow_value(t: f32) -> [f32; 3] {
+    // hue goes up to violet only, so the both ends of the ratio stay distinguishable
+    let h = t.clamp(0.0, 1.0) * 5.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        _ => (x, 0.0, 1.0),
+    };
+    [channel(r), channel(g), channel(b)]
+}
+
+/// Red, green and blue in range [0, 255] of the hue `h` in turns (wrapping around,
+/// 0 is red), the saturation `s` and the value `v` in range [0, 1]
+pub fn hsv(h: f32, s: f32, v: f32) -> [f32; 3] {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    [channel(r + m), channel(g + m), channel(b + m)]
+}
+
+/// Full hue wheel around the turn, both ends are red
+pub fn hue_wheel(t: f32) -> Rgb<u8> {
+    truncated(hue_wheel_value, t)
+}
+
+fn hue_wheel_value(t: f32) -> [f32; 3] {
+    hsv(t, 1.0, 1.0)
+}
+
+/// Thresholds of the 8x8 Bayer matrix
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Quantizes the channels in range [0, 256) of a picture `width` pixels wide into 8 bits,
+/// every channel ends up at its truncation or one above it (the error diffusion may
+/// carry it one further), the integer values stay as they are
+pub fn dither(values: &[f32], width: usize, channels: usize, dither: Dither) -> Vec<u8> {
+    let stride = width * channels;
+    match dither {
+        Dither::Bayer => values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let (x, y) = (i % stride / channels, i / stride);
+                let threshold = (BAYER[y % 8][x % 8] as f32 + 0.5) / 64.0;
+                (v + threshold).min(255.0) as u8
+            })
+            .collect(),
+        Dither::FloydSteinberg => {
+            let mut values = values.to_vec();
+            let mut out = vec![0; values.len()];
+            for i in 0..values.len() {
+                let v = values[i];
+                let q = v.round().clamp(0.0, 255.0);
+                out[i] = q as u8;
+                let err = v - q;
+
+                let x = i % stride / channels;
+                let mut spread = |offset: usize, weight: f32| {
+                    if let Some(next) = values.get_mut(i + offset) {
+                        *next += err * weight;
+                    }
+                };
+                if x + 1 < width {
+                    spread(channels, 7.0 / 16.0);
+                }
+                if x > 0 {
+                    spread(stride - channels, 3.0 / 16.0);
+                }
+                spread(stride, 5.0 / 16.0);
+                if x + 1 < width {
+                    spread(stride + channels, 1.0 / 16.0);
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Shape the closest approach of the orbit to gets measured by the orbit trap coloring
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trap {
+    /// Origin of the complex plane
+    Point,
+    /// Real axis line
+    RealAxis,
+    /// Imaginary axis line
+    ImagAxis,
+}
+
+/// Metric of the orbits of the points of the set colored instead of the flat color
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interior {
+    /// Mean magnitude of the orbit over all the iterations
+    AvgMagnitude,
+    /// Period of the attracting cycle the orbit falls into (`mandelbrot_period_at_point`),
+    /// period p at 1 - 1/p of the palette, the points without a detected period get its end
+    Period,
+}
+
+/// Trap distance mapped onto the end of the palette, the bailout radius can't be less
+pub const TRAP_RANGE: f64 = 2.0;
+
+/// Recurrence of the escape time rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Mandelbrot,
+    /// z = z^d + c with d > 2
+    Multibrot(u32),
+    BurningShip,
+    /// z = conj(z)^2 + c, the Mandelbar
+    Tricorn,
+}
+
+impl Variant {
+    /// Bounds `(x_min, x_max, y_min, y_max)` showing the whole fractal in a picture
+    /// with the given width to height ratio
+    pub fn default_coords(&self, aspect: f64) -> (f64, f64, f64, f64) {
+        match self {
+            Variant::Mandelbrot | Variant::Multibrot(_) => (-2.0, 1.0, -0.84375, 0.84375),
+            Variant::BurningShip => {
+                // the ship sits in the lower half-plane, which is the top of the picture
+                let (y_min, y_max) = (-2.0, 1.0);
+                let dx = (y_max - y_min) * aspect;
+                let cx = -0.5;
+                (cx - dx / 2.0, cx + dx / 2.0, y_min, y_max)
+            }
+            Variant::Tricorn => {
+                // three horns 120 degrees apart reaching 2 from the origin
+                let (y_min, y_max) = (-1.85, 1.85);
+                let dx = (y_max - y_min) * aspect;
+                let cx = -0.5;
+                (cx - dx / 2.0, cx + dx / 2.0, y_min, y_max)
+            }
+        }
+    }
+}
+
+/// Failures of the rendering and saving of a picture
+#[derive(Debug)]
+pub enum MandelbrotError {
+    /// Extension of the output path doesn't match any supported format
+    UnsupportedFormat(String),
+    /// Extension of the animation path isn't gif
+    UnsupportedAnimationFormat(String),
+    /// Number of escape values doesn't match the size of the picture
+    InvalidBufferSize { expected: usize, got: usize },
+    /// File isn't a raw dump of the escape values of a supported version
+    InvalidRawDump(String),
+    /// Palette image isn't a strip of a single row or column
+    InvalidPaletteImage(String),
+    /// Quality given for a format saved lossless only
+    UnsupportedQuality(String),
+    /// Color mode that doesn't apply to the kind of the escape values
+    UnsupportedColorMode(String),
+    /// Failure of creating or writing the output file
+    Io(io::Error),
+    /// Failure of encoding the picture
+    Image(ImageError),
+}
+
+impl fmt::Display for MandelbrotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MandelbrotError::UnsupportedFormat(ext) => write!(
+                f,
+                "Unsupported image format \"{}\" (expected png, jpg, jpeg, bmp, tiff, ppm or webp)",
+                ext
+            ),
+            MandelbrotError::UnsupportedAnimationFormat(ext) => {
+                write!(f, "Unsupported animation format \"{}\" (expected gif)", ext)
+            }
+            MandelbrotError::InvalidBufferSize { expected, got } => write!(
+                f,
+                "Invalid escape buffer size {} (expected {} pixels)",
+                got, expected
+            ),
+            MandelbrotError::InvalidRawDump(msg) => write!(f, "Invalid raw dump: {}", msg),
+            MandelbrotError::InvalidPaletteImage(msg) => {
+                write!(f, "Invalid palette image: {}", msg)
+            }
+            MandelbrotError::UnsupportedQuality(ext) => write!(
+                f,
+                "Format \"{}\" is saved lossless only, quality applies to jpg and jpeg",
+                ext
+            ),
+            MandelbrotError::UnsupportedColorMode(msg) => {
+                write!(f, "Unsupported color mode: {}", msg)
+            }
+            MandelbrotError::Io(e) => write!(f, "{}", e),
+            MandelbrotError::Image(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for MandelbrotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MandelbrotError::Io(e) => Some(e),
+            MandelbrotError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MandelbrotError {
+    fn from(e: io::Error) -> Self {
+        MandelbrotError::Io(e)
+    }
+}
+
+impl From<ImageError> for MandelbrotError {
+    fn from(e: ImageError) -> Self {
+        MandelbrotError::Image(e)
+    }
+}
+
+/// 4 complex numbers in SIMD lanes
+#[derive(Debug, Clone, Copy)]
+pub struct Complex4 {
+    pub real: f64x4,
+    pub imag: f64x4,
+}
+
+impl Add for Complex4 {
+    type Output = Complex4;
+
+    #[inline(always)]
+    fn add(self, other: Complex4) -> Complex4 {
+        Complex4 {
+            real: self.real + other.real,
+            imag: self.imag + other.imag,
+        }
+    }
+}
+
+impl Mul for Complex4 {
+    type Output = Complex4;
+
+    fn mul(self, other: Complex4) -> Complex4 {
+        Complex4 {
+            real: self.real * other.real - self.imag * other.imag,
+            imag: self.real * other.imag + self.imag * other.real,
+        }
+    }
+}
+
+impl Complex4 {
+    /// Points of the same row with the given real parts
+    pub fn from_row(real: [f64; 4], imag: f64) -> Complex4 {
+        Complex4 {
+            real: f64x4::new(real),
+            imag: f64x4::splat(imag),
+        }
+    }
+
+    /// Points with the given real and imaginary parts
+    pub fn from_parts(real: [f64; 4], imag: [f64; 4]) -> Complex4 {
+        Complex4 {
+            real: f64x4::new(real),
+            imag: f64x4::new(imag),
+        }
+    }
+
+    /// Squared magnitudes, compared with the squared bailout radius
+    #[inline(always)]
+    pub fn norm_sqr(&self) -> f64x4 {
+        self.real * self.real + self.imag * self.imag
+    }
+
+    /// Squares with 3 multiplications, the doubled product takes an addition instead
+    #[inline(always)]
+    pub fn square(&self) -> Complex4 {
+        let ri = self.real * self.imag;
+        Complex4 {
+            real: self.real * self.real - self.imag * self.imag,
+            imag: ri + ri,
+        }
+    }
+
+    /// Absolute values of the real and imaginary parts, the folding of the Burning Ship
+    #[inline(always)]
+    pub fn abs(&self) -> Complex4 {
+        Complex4 {
+            real: self.real.abs(),
+            imag: self.imag.abs(),
+        }
+    }
+
+    /// Lanes inside the main cardioid or the period-2 bulb, these points never escape
+    pub fn in_main_body(&self) -> f64x4 {
+        let y2 = self.imag * self.imag;
+        let xq = self.real - f64x4::splat(0.25);
+        let q = xq * xq + y2;
+        let cardioid = (q * (q + xq)).simd_lt(f64x4::splat(0.25) * y2);
+        let x1 = self.real + f64x4::splat(1.0);
+        let bulb = (x1 * x1 + y2).simd_lt(f64x4::splat(0.0625));
+        cardioid | bulb
+    }
+
+    /// Raises to the integer power by repeated squaring
+    pub fn powu(self, mut exp: u32) -> Complex4 {
+        let mut base = self;
+        let mut acc = Complex4 {
+            real: f64x4::splat(1.0),
+            imag: f64x4::splat(0.0),
+        };
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                acc = acc * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+
+        acc
+    }
+}
+
+/// 8 complex numbers in SIMD lanes
+#[derive(Debug, Clone, Copy)]
+pub struct Complex8 {
+    pub real: f64x8,
+    pub imag: f64x8,
+}
+
+impl Complex8 {
+    /// Points of the same row with the given real parts
+    pub fn from_row(real: [f64; 8], imag: f64) -> Complex8 {
+        Complex8 {
+            real: f64x8::new(real),
+            imag: f64x8::splat(imag),
+        }
+    }
+
+    /// Points with the given real and imaginary parts
+    pub fn from_parts(real: [f64; 8], imag: [f64; 8]) -> Complex8 {
+        Complex8 {
+            real: f64x8::new(real),
+            imag: f64x8::new(imag),
+        }
+    }
+
+    /// Lanes inside the main cardioid or the period-2 bulb, these points never escape
+    pub fn in_main_body(&self) -> f64x8 {
+        let y2 = self.imag * self.imag;
+        let xq = self.real - f64x8::splat(0.25);
+        let q = xq * xq + y2;
+        let cardioid = (q * (q + xq)).simd_lt(f64x8::splat(0.25) * y2);
+        let x1 = self.real + f64x8::splat(1.0);
+        let bulb = (x1 * x1 + y2).simd_lt(f64x8::splat(0.0625));
+        cardioid | bulb
+    }
+}
+
+/// 8 single precision complex numbers in SIMD lanes
+#[derive(Debug, Clone, Copy)]
+pub struct Complex8f32 {
+    pub real: f32x8,
+    pub imag: f32x8,
+}
+
+impl Complex8f32 {
+    /// Points of the same row with the given real parts, rounded to single precision
+    pub fn from_row(real: [f64; 8], imag: f64) -> Complex8f32 {
+        Complex8f32 {
+            real: f32x8::new(real.map(|x| x as f32)),
+            imag: f32x8::splat(imag as f32),
+        }
+    }
+
+    /// Points with the given real and imaginary parts, rounded to single precision
+    pub fn from_parts(real: [f64; 8], imag: [f64; 8]) -> Complex8f32 {
+        Complex8f32 {
+            real: f32x8::new(real.map(|x| x as f32)),
+            imag: f32x8::new(imag.map(|x| x as f32)),
+        }
+    }
+
+    /// Lanes inside the main cardioid or the period-2 bulb, these points never escape
+    pub fn in_main_body(&self) -> f32x8 {
+        let y2 = self.imag * self.imag;
+        let xq = self.real - f32x8::splat(0.25);
+        let q = xq * xq + y2;
+        let cardioid = (q * (q + xq)).simd_lt(f32x8::splat(0.25) * y2);
+        let x1 = self.real + f32x8::splat(1.0);
+        let bulb = (x1 * x1 + y2).simd_lt(f32x8::splat(0.0625));
+        cardioid | bulb
+    }
+}
+
+/// Whether the point lies inside the main cardioid or the period-2 bulb of the
+/// Mandelbrot set, which covers most of its area and needs no iterations at all
+pub fn in_main_body(cx: f64, cy: f64) -> bool {
+    let y2 = cy * cy;
+    let xq = cx - 0.25;
+    let q = xq * xq + y2;
+    q * (q + xq) < 0.25 * y2 || (cx + 1.0) * (cx + 1.0) + y2 < 0.0625
+}
+
+/// Shifts of the points of the pixels of a view, in pixels, from the corners of the pixels
+/// that `View::pixel_to_complex` gives
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Offsets {
+    None,
+    /// Pixels of a view supersampled by `n`, the ones of every block of `n` x `n` shift
+    /// onto a grid turned by atan(1 / n)
+    RotatedGrid(usize),
+    /// Shifts below half a pixel either way, hashes of the seed and the unshifted point
+    /// so the same point always gets the same shift
+    Random(u64),
+}
+
+impl Offsets {
+    /// Shift of the pixel `(x, y)` at the point `re + im i`
+    fn at(&self, x: usize, y: usize, re: f64, im: f64) -> (f64, f64) {
+        match *self {
+            Offsets::None => (0.0, 0.0),
+            Offsets::RotatedGrid(n) => {
+                // sample (i, j) of the block moves from i, j to i + (j + 1/2) / n - 1/2,
+                // j + (n - i - 1/2) / n - 1/2, the rows and columns of the grid interleave
+                // and the samples keep their mean
+                let (i, j) = ((x % n) as f64, (y % n) as f64);
+                let n = n as f64;
+                (
+                    (2.0 * j + 1.0 - n) / (2.0 * n),
+                    (n - 2.0 * i - 1.0) / (2.0 * n),
+                )
+            }
+            Offsets::Random(seed) => {
+                let hash = split_mix(seed ^ split_mix(re.to_bits() ^ split_mix(im.to_bits())));
+                // 2^-53 steps of [0, 1) from the upper bits of both halves
+                let unit = |bits: u64| (bits >> 11) as f64 / (1u64 << 53) as f64;
+                (unit(hash) - 0.5, unit(split_mix(hash)) - 0.5)
+            }
+        }
+    }
+}
+
+/// Finalizer of the SplitMix64 generator, a fast well mixed hash of 64 bits
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Size of the pixels in ulps of the coordinates below which `View::beyond_f64` holds
+pub const DEEP_ULPS: f64 = 16.0;
+
+/// Rectangle of the complex plane mapped onto a picture of `width` x `height` pixels
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+    pub width: usize,
+    pub height: usize,
+    /// Rows `rows.0..rows.1` of the picture to calculate, `(0, height)` for all of them
+    pub rows: (usize, usize),
+    /// Columns `columns.0..columns.1` of the picture to calculate, `(0, width)` for all
+    /// of them, the rows of the escape values hold these columns only
+    pub columns: (usize, usize),
+    /// Counterclockwise angle in radians of the sampling grid about the center of the view,
+    /// the picture stays axis-aligned while its contents turn the other way
+    pub rotation: f64,
+    /// Rows not started before this instant are skipped and keep the escape value 0,
+    /// the rows calculated in time are complete
+    pub deadline: Option<Instant>,
+    /// Shifts of the points of the pixels, `Offsets::None` outside of the supersampling
+    pub offsets: Offsets,
+    /// Rows of the bands making up the parallel tasks, at least 1, the bands split further
+    /// into tiles `TILE` pixels wide
+    pub rows_per_task: usize,
+}
+
+impl View {
+    /// Width and height of a pixel in the complex plane
+    pub fn pixel_size(&self) -> (f64, f64) {
+        (
+            (self.x_max - self.x_min) / self.width as f64,
+            (self.y_max - self.y_min) / self.height as f64,
+        )
+    }
+
+    /// Whether the pixels are too small for the f64 coordinates of their points, the
+    /// rounding errors of the iterations blur the neighboring pixels into blocks
+    pub fn beyond_f64(&self) -> bool {
+        let (dx, dy) = self.pixel_size();
+        let scale = [self.x_min, self.x_max, self.y_min, self.y_max]
+            .iter()
+            .fold(0.0, |max: f64, v| max.max(v.abs()));
+        dx.min(dy) < DEEP_ULPS * f64::EPSILON * scale
+    }
+
+    /// Point of the complex plane calculated for the pixel `(x, y)`, row 0 holds
+    /// `y_min`, `None` for the pixels outside of the picture
+    pub fn pixel_to_complex(&self, x: usize, y: usize) -> Option<(f64, f64)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let (dx, dy) = self.pixel_size();
+        Some(self.rotate(
+            self.x_min + (x as f64) * dx,
+            self.y_min + (y as f64) * dy,
+            self.rotation,
+        ))
+    }
+
+    /// Point turned by `angle` about the center of the view, exactly the same point
+    /// for the angle 0
+    pub fn rotate(&self, re: f64, im: f64, angle: f64) -> (f64, f64) {
+        if angle == 0.0 {
+            return (re, im);
+        }
+        let center_re = (self.x_min + self.x_max) / 2.0;
+        let center_im = (self.y_min + self.y_max) / 2.0;
+        let (sin, cos) = angle.sin_cos();
+        let (re, im) = (re - center_re, im - center_im);
+        (
+            center_re + re * cos - im * sin,
+            center_im + re * sin + im * cos,
+        )
+    }
+
+    /// Pixel whose area holds the point `re + im i`, `None` for the points outside
+    /// of the view
+    pub fn complex_to_pixel(&self, re: f64, im: f64) -> Option<(usize, usize)> {
+        let (re, im) = self.rotate(re, im, -self.rotation);
+        let (dx, dy) = self.pixel_size();
+        let x = ((re - self.x_min) / dx).floor();
+        let y = ((im - self.y_min) / dy).floor();
+        let inside = x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64;
+        inside.then_some((x as usize, y as usize))
+    }
+
+    /// View of the calculated columns and rows alone, the bounds of their pixels and
+    /// the size of the region, the same view when all of them are calculated. The turn
+    /// stays about the center of the whole view, so only unrotated regions render again
+    /// from their bounds exactly
+    pub fn crop(&self) -> View {
+        let (dx, dy) = self.pixel_size();
+        let x = |c: usize| match c {
+            c if c == self.width => self.x_max,
+            c => self.x_min + c as f64 * dx,
+        };
+        let y = |r: usize| match r {
+            r if r == self.height => self.y_max,
+            r => self.y_min + r as f64 * dy,
+        };
+        let (width, height) = (self.columns.1 - self.columns.0, self.rows.1 - self.rows.0);
+        View {
+            x_min: x(self.columns.0),
+            x_max: x(self.columns.1),
+            y_min: y(self.rows.0),
+            y_max: y(self.rows.1),
+            width,
+            height,
+            rows: (0, height),
+            columns: (0, width),
+            ..*self
+        }
+    }
+}
+
+/// Escape values of every pixel row by row, points of the set hold `iters`
+/// (except for the distances)
+#[derive(Clone)]
+pub enum Escaped {
+    /// Integer escape counts, exact and the default
+    Counts(Vec<u64>),
+    /// Fractional (normalized) escape counts computed from the final `|z|` with `smooth`
+    /// set, also the averages of the samples
+    Smooth(Vec<f64>),
+    /// Estimated distances to the boundary of the set in pixels, points of the set hold 0
+    Distance(Vec<f64>),
+    /// Closest approaches of the orbits to the trap, the points of the set included
+    Trap(Vec<f64>),
+    /// Stripe or triangle inequality averages or phases in range [0, 1], points of the set
+    /// hold -1
+    Stripe(Vec<f64>),
+    /// Smooth escape counts along with the brightness in range [0, 1] from the argument
+    /// of z at the escape or from the lighting of the normal, points of the set hold `iters`
+    Hsv(Vec<[f64; 2]>),
+}
+
+impl Escaped {
+    /// Escape value of the `i`-th pixel
+    pub fn get(&self, i: usize) -> f64 {
+        match self {
+            Escaped::Counts(buf) => buf[i] as f64,
+            Escaped::Smooth(buf) => buf[i],
+            Escaped::Distance(buf) => buf[i],
+            Escaped::Trap(buf) => buf[i],
+            Escaped::Stripe(buf) => buf[i],
+            Escaped::Hsv(buf) => buf[i][0],
+        }
+    }
+
+    /// Number of pixels
+    pub fn len(&self) -> usize {
+        match self {
+            Escaped::Counts(buf) => buf.len(),
+            Escaped::Smooth(buf) => buf.len(),
+            Escaped::Distance(buf) => buf.len(),
+            Escaped::Trap(buf) => buf.len(),
+            Escaped::Stripe(buf) => buf.len(),
+            Escaped::Hsv(buf) => buf.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 64-bit FNV-1a hash of the little-endian bytes of the values (the bits of the floating
+    /// point ones), the same on every platform and for every number of threads
+    pub fn checksum(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let fnv = |hash: u64, bytes: [u8; 8]| {
+            bytes
+                .iter()
+                .fold(hash, |hash, &b| (hash ^ b as u64).wrapping_mul(PRIME))
+        };
+        match self {
+            Escaped::Counts(buf) => buf
+                .iter()
+                .fold(OFFSET_BASIS, |hash, v| fnv(hash, v.to_le_bytes())),
+            Escaped::Smooth(buf)
+            | Escaped::Distance(buf)
+            | Escaped::Trap(buf)
+            | Escaped::Stripe(buf) => buf
+                .iter()
+                .fold(OFFSET_BASIS, |hash, v| fnv(hash, v.to_bits().to_le_bytes())),
+            Escaped::Hsv(buf) => buf
+                .iter()
+                .flatten()
+                .fold(OFFSET_BASIS, |hash, v| fnv(hash, v.to_bits().to_le_bytes())),
+        }
+    }
+
+    /// Reverses the order of the rows of a picture `width` pixels wide, flipping twice
+    /// gives back the original values
+    pub fn flip_rows(&mut self, width: usize) {
+        fn flip<T>(buf: &mut [T], width: usize) {
+            let height = buf.len() / width;
+            for y in 0..height / 2 {
+                let (top, bottom) = buf.split_at_mut((height - 1 - y) * width);
+                top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+            }
+        }
+
+        match self {
+            Escaped::Counts(buf) => flip(buf, width),
+            Escaped::Smooth(buf) => flip(buf, width),
+            Escaped::Distance(buf) => flip(buf, width),
+            Escaped::Trap(buf) => flip(buf, width),
+            Escaped::Stripe(buf) => flip(buf, width),
+            Escaped::Hsv(buf) => flip(buf, width),
+        }
+    }
+
+    /// Puts the rows of `other` below the rows of `self`, panics when the values are
+    /// not of the same kind
+    pub fn append(&mut self, other: Escaped) {
+        match (self, other) {
+            (Escaped::Counts(buf), Escaped::Counts(rows)) => buf.extend(rows),
+            (Escaped::Smooth(buf), Escaped::Smooth(rows)) => buf.extend(rows),
+            (Escaped::Distance(buf), Escaped::Distance(rows)) => buf.extend(rows),
+            (Escaped::Trap(buf), Escaped::Trap(rows)) => buf.extend(rows),
+            (Escaped::Stripe(buf), Escaped::Stripe(rows)) => buf.extend(rows),
+            (Escaped::Hsv(buf), Escaped::Hsv(rows)) => buf.extend(rows),
+            _ => panic!("appended escape values of another kind"),
+        }
+    }
+
+    /// Puts the rows of `other`, `other_width` pixels wide, right of the rows of `self`,
+    /// `width` pixels wide, both hold the same number of rows, panics when the values are
+    /// not of the same kind
+    pub fn append_columns(&mut self, width: usize, other: Escaped, other_width: usize) {
+        fn join<T: Copy>(buf: &mut Vec<T>, width: usize, rows: Vec<T>, other_width: usize) {
+            let mut joined = Vec::with_capacity(buf.len() + rows.len());
+            for (row, other) in buf.chunks_exact(width).zip(rows.chunks_exact(other_width)) {
+                joined.extend_from_slice(row);
+                joined.extend_from_slice(other);
+            }
+            *buf = joined;
+        }
+
+        match (self, other) {
+            (Escaped::Counts(buf), Escaped::Counts(rows)) => join(buf, width, rows, other_width),
+            (Escaped::Smooth(buf), Escaped::Smooth(rows)) => join(buf, width, rows, other_width),
+            (Escaped::Distance(buf), Escaped::Distance(rows)) => {
+                join(buf, width, rows, other_width)
+            }
+            (Escaped::Trap(buf), Escaped::Trap(rows)) => join(buf, width, rows, other_width),
+            (Escaped::Stripe(buf), Escaped::Stripe(rows)) => join(buf, width, rows, other_width),
+            (Escaped::Hsv(buf), Escaped::Hsv(rows)) => join(buf, width, rows, other_width),
+            _ => panic!("appended escape values of another kind"),
+        }
+    }
+
+    /// Averages every `samples` x `samples` block of a picture `width * samples` pixels
+    /// wide into a single pixel, the block gets `iters` only when every sample does
+    pub fn downsample(&self, width: usize, samples: usize) -> Vec<f64> {
+        let fine_width = width * samples;
+        let mut buf = vec![0.0; self.len() / (samples * samples)];
+
+        buf.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let mut sum = 0.0;
+                for sy in 0..samples {
+                    let offset = (y * samples + sy) * fine_width + x * samples;
+                    sum += (offset..offset + samples).map(|i| self.get(i)).sum::<f64>();
+                }
+                *pixel = sum / (samples * samples) as f64;
+            }
+        });
+
+        buf
+    }
+}
+
+/// Distribution of the escape values of a picture
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Stats {
+    /// Smallest escape value of the points outside the set
+    pub min: f64,
+    /// Largest escape value of the points outside the set, far below `iters`
+    /// means too many iterations
+    pub max: f64,
+    /// Mean escape value of the points outside the set
+    pub mean: f64,
+    /// Share of the points of the set among all pixels
+    pub in_set: f64,
+}
+
+impl Escaped {
+    /// Distribution of the escape values in a single parallel pass, points of the set hold
+    /// `iters` (or 0 for the distances), the trap distances count as points outside the set
+    pub fn stats(&self, iters: usize) -> Stats {
+        let in_set = |x: f64| match self {
+            Escaped::Distance(_) => x == 0.0,
+            Escaped::Trap(_) => false,
+            Escaped::Stripe(_) => x == -1.0,
+            _ => x == iters as f64,
+        };
+
+        // (min, max, sum, number of points outside the set)
+        let (min, max, sum, outside) = (0..self.len())
+            .into_par_iter()
+            .map(|i| self.get(i))
+            .filter(|&x| !in_set(x))
+            .fold(
+                || (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0usize),
+                |(min, max, sum, n), x| (min.min(x), max.max(x), sum + x, n + 1),
+            )
+            .reduce(
+                || (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0usize),
+                |a, b| (a.0.min(b.0), a.1.max(b.1), a.2 + b.2, a.3 + b.3),
+            );
+
+        if outside == 0 {
+            return Stats {
+                min: 0.0,
+                max: 0.0,
+                mean: 0.0,
+                in_set: if self.is_empty() { 0.0 } else { 1.0 },
+            };
+        }
+
+        Stats {
+            min,
+            max,
+            mean: sum / outside as f64,
+            in_set: (self.len() - outside) as f64 / self.len() as f64,
+        }
+    }
+}
+
+/// First bytes of a raw dump of the escape values
+const RAW_MAGIC: &[u8; 4] = b"MBRW";
+/// Version of the layout of the raw dump, bumped on every incompatible change
+const RAW_VERSION: u32 = 1;
+
+/// Size of the picture and number of iterations stored along with the raw escape values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawHeader {
+    pub width: usize,
+    pub height: usize,
+    pub iters: usize,
+}
+
+impl Escaped {
+    /// Writes the escape values after a header of little endian fields: magic "MBRW",
+    /// version (u32), kind of the values (u32), width, height and iters (u64 each),
+    /// then every value as a little endian u64 or f64, the two values of each HSV pixel
+    /// one after the other
+    pub fn save_raw(&self, path: &Path, header: &RawHeader) -> Result<(), MandelbrotError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_raw(&mut out, header)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Same layout as `save_raw` into any writer, several dumps may follow each other
+    pub fn write_raw(
+        &self,
+        out: &mut impl Write,
+        header: &RawHeader,
+    ) -> Result<(), MandelbrotError> {
+        let expected = header.width * header.height;
+        if self.len() != expected {
+            return Err(MandelbrotError::InvalidBufferSize {
+                expected,
+                got: self.len(),
+            });
+        }
+
+        let kind: u32 = match self {
+            Escaped::Counts(_) => 0,
+            Escaped::Smooth(_) => 1,
+            Escaped::Distance(_) => 2,
+            Escaped::Trap(_) => 3,
+            Escaped::Stripe(_) => 4,
+            Escaped::Hsv(_) => 5,
+        };
+
+        out.write_all(RAW_MAGIC)?;
+        out.write_all(&RAW_VERSION.to_le_bytes())?;
+        out.write_all(&kind.to_le_bytes())?;
+        for field in [header.width, header.height, header.iters] {
+            out.write_all(&(field as u64).to_le_bytes())?;
+        }
+        match self {
+            Escaped::Counts(buf) => {
+                for count in buf {
+                    out.write_all(&count.to_le_bytes())?;
+                }
+            }
+            Escaped::Smooth(buf)
+            | Escaped::Distance(buf)
+            | Escaped::Trap(buf)
+            | Escaped::Stripe(buf) => {
+                for value in buf {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+            Escaped::Hsv(buf) => {
+                for value in buf.iter().flatten() {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the escape values written by `save_raw`
+    pub fn load_raw(path: &Path) -> Result<(RawHeader, Escaped), MandelbrotError> {
+        let invalid = |msg: String| MandelbrotError::InvalidRawDump(msg);
+
+        let mut input = BufReader::new(File::open(path)?);
+        let (kind, header, count) = Escaped::read_raw_header(&mut input)?;
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        if data.len() % 8 != 0 || data.len() / 8 != count {
+            return Err(invalid(format!(
+                "{}x{} picture needs {} values ({} bytes), the file holds {} bytes",
+                header.width,
+                header.height,
+                count,
+                count * 8,
+                data.len()
+            )));
+        }
+
+        Ok((header, Escaped::raw_values(kind, &data)?))
+    }
+
+    /// Reads one dump written by `write_raw`, leaving the reader right after its values
+    pub fn read_raw(input: &mut impl Read) -> Result<(RawHeader, Escaped), MandelbrotError> {
+        let (kind, header, count) = Escaped::read_raw_header(input)?;
+        let mut data = Vec::new();
+        input.take(count as u64 * 8).read_to_end(&mut data)?;
+        if data.len() != count * 8 {
+            return Err(MandelbrotError::InvalidRawDump(format!(
+                "{}x{} picture needs {} values, the values are truncated",
+                header.width, header.height, count
+            )));
+        }
+
+        Ok((header, Escaped::raw_values(kind, &data)?))
+    }
+
+    /// Kind of the values, header and number of values following it
+    fn read_raw_header(input: &mut impl Read) -> Result<(u32, RawHeader, usize), MandelbrotError> {
+        let invalid = |msg: String| MandelbrotError::InvalidRawDump(msg);
+
+        let mut magic = [0; 4];
+        input
+            .read_exact(&mut magic)
+            .map_err(|_| invalid("missing header".to_string()))?;
+        if &magic != RAW_MAGIC {
+            return Err(invalid("not a raw dump of the escape values".to_string()));
+        }
+
+        let mut word = [0; 4];
+        let mut read_u32 = |input: &mut dyn Read| {
+            input
+                .read_exact(&mut word)
+                .map(|_| u32::from_le_bytes(word))
+                .map_err(|_| invalid("truncated header".to_string()))
+        };
+        let version = read_u32(input)?;
+        if version != RAW_VERSION {
+            return Err(invalid(format!(
+                "version {} (expected {})",
+                version, RAW_VERSION
+            )));
+        }
+        let kind = read_u32(input)?;
+        let per_pixel = if kind == 5 { 2 } else { 1 };
+
+        let mut fields = [0; 3];
+        for field in fields.iter_mut() {
+            let mut bytes = [0; 8];
+            input
+                .read_exact(&mut bytes)
+                .map_err(|_| invalid("truncated header".to_string()))?;
+            *field = u64::from_le_bytes(bytes) as usize;
+        }
+        let [width, height, iters] = fields;
+        let header = RawHeader {
+            width,
+            height,
+            iters,
+        };
+
+        let expected = width.checked_mul(height).ok_or_else(|| {
+            invalid(format!(
+                "picture of {}x{} pixels is too large",
+                width, height
+            ))
+        })?;
+
+        Ok((kind, header, expected * per_pixel))
+    }
+
+    /// Escape values of `kind` out of little endian words
+    fn raw_values(kind: u32, data: &[u8]) -> Result<Escaped, MandelbrotError> {
+        let words = data
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().expect("chunks of 8 bytes")));
+        let values = || words.clone().map(f64::from_bits).collect();
+        let escaped = match kind {
+            0 => Escaped::Counts(words.clone().collect()),
+            1 => Escaped::Smooth(values()),
+            2 => Escaped::Distance(values()),
+            3 => Escaped::Trap(values()),
+            4 => Escaped::Stripe(values()),
+            5 => {
+                let values: Vec<f64> = values();
+                Escaped::Hsv(values.chunks_exact(2).map(|v| [v[0], v[1]]).collect())
+            }
+            _ => {
+                return Err(MandelbrotError::InvalidRawDump(format!(
+                    "unknown kind of values {}",
+                    kind
+                )))
+            }
+        };
+
+        Ok(escaped)
+    }
+
+    /// Writes the escape values as CSV of `view.height` lines of `view.width` values each,
+    /// after a comment line starting with '#' holding the size, the bounds and `iters`,
+    /// the values go through a buffer straight into the file
+    pub fn save_csv(&self, path: &Path, view: &View, iters: usize) -> Result<(), MandelbrotError> {
+        let expected = view.width * view.height;
+        if self.len() != expected {
+            return Err(MandelbrotError::InvalidBufferSize {
+                expected,
+                got: self.len(),
+            });
+        }
+
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(
+            out,
+            "# width={} height={} iters={} x_min={} x_max={} y_min={} y_max={} rotate={}",
+            view.width,
+            view.height,
+            iters,
+            view.x_min,
+            view.x_max,
+            view.y_min,
+            view.y_max,
+            view.rotation.to_degrees()
+        )?;
+        for y in 0..view.height {
+            for x in 0..view.width {
+                let i = y * view.width + x;
+                let sep = if x + 1 < view.width { "," } else { "\n" };
+                match self {
+                    Escaped::Counts(buf) => write!(out, "{}{}", buf[i], sep)?,
+                    _ => write!(out, "{}{}", self.get(i), sep)?,
+                }
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Recurrence of an escape time fractal, the escape and count loops of `fractal_at_vec`
+/// and `fractal_norm_at_vec` iterate it on 4 points at once
+pub trait Fractal: Sync {
+    /// Value of z before the first iteration for the points `c` of the pixels
+    fn initial_z(&self, c: Complex4) -> Complex4 {
+        c
+    }
+
+    /// Next value of z of the points `c`
+    fn step(&self, z: Complex4, c: Complex4) -> Complex4;
+
+    /// Lanes known to belong to the set without iterating them
+    fn known_inside(&self, _c: &Complex4) -> f64x4 {
+        f64x4::splat(0.0)
+    }
+
+    /// Degree of the recurrence, the base of the logarithm of the smooth counts
+    fn power(&self) -> u32 {
+        2
+    }
+
+    /// Set is symmetric about the real axis, so the views centered on the axis get
+    /// their rows mirrored
+    fn symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// z = z^2 + c
+#[derive(Debug, Clone, Copy)]
+pub struct Mandelbrot;
+
+impl Fractal for Mandelbrot {
+    #[inline(always)]
+    fn step(&self, z: Complex4, c: Complex4) -> Complex4 {
+        z.square() + c
+    }
+
+    #[inline(always)]
+    fn known_inside(&self, c: &Complex4) -> f64x4 {
+        c.in_main_body()
+    }
+}
+
+/// z = z^power + c
+#[derive(Debug, Clone, Copy)]
+pub struct Multibrot(pub u32);
+
+impl Fractal for Multibrot {
+    #[inline(always)]
+    fn step(&self, z: Complex4, c: Complex4) -> Complex4 {
+        z.powu(self.0) + c
+    }
+
+    fn power(&self) -> u32 {
+        self.0
+    }
+}
+
+/// z = (|Re z| + i|Im z|)^2 + c
+#[derive(Debug, Clone, Copy)]
+pub struct BurningShip;
+
+impl Fractal for BurningShip {
+    #[inline(always)]
+    fn step(&self, z: Complex4, c: Complex4) -> Complex4 {
+        z.abs().square() + c
+    }
+
+    fn symmetric(&self) -> bool {
+        false
+    }
+}
+
+/// z = conj(z)^2 + c, conjugating only flips the sign of the doubled product
+#[derive(Debug, Clone, Copy)]
+pub struct Tricorn;
+
+impl Fractal for Tricorn {
+    #[inline(always)]
+    fn step(&self, z: Complex4, c: Complex4) -> Complex4 {
+        let ri = z.real * z.imag;
+        Complex4 {
+            real: z.real * z.real - z.imag * z.imag + c.real,
+            imag: c.imag - ri - ri,
+        }
+    }
+}
+
+/// Minimal squared escape threshold of the smooth coloring, the `log(log(|z|))` term
+/// gets well behaved only when `|z|` is far enough from the escape boundary
+pub const SMOOTH_THRESHOLD: f64 = 256.0;
+
+/// Same as the supersampling of `calc_escaped` in the pixels differing by more than
+/// `threshold` from one of their neighbors only, a first pass takes a single sample of every
+/// pixel and a second one replaces the differing ones with the averages of their samples
+fn calc_escaped_adaptive(
+    options: &CalcOptions,
+    threshold: f64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Escaped {
+    let samples = options.samples;
+    let coarse_options = CalcOptions {
+        samples: 1,
+        adaptive: None,
+        ..*options
+    };
+    let coarse = calc_escaped(&coarse_options, view, progress);
+
+    let width = view.columns.1 - view.columns.0;
+    let height = coarse.len() / width;
+    let hidden = ProgressBar::hidden();
+    // the neighbors just outside of a band of rows or a region, so its edges compare
+    // with the same pixels as in the whole picture
+    let (rows, columns) = (view.rows, view.columns);
+    let halo = |rows, columns| {
+        calc_escaped(
+            &coarse_options,
+            &View {
+                rows,
+                columns,
+                ..*view
+            },
+            &hidden,
+        )
+    };
+    let above = (rows.0 > 0).then(|| halo((rows.0 - 1, rows.0), columns));
+    let below = (rows.1 < view.height).then(|| halo((rows.1, rows.1 + 1), columns));
+    let left = (columns.0 > 0).then(|| halo(rows, (columns.0 - 1, columns.0)));
+    let right = (columns.1 < view.width).then(|| halo(rows, (columns.1, columns.1 + 1)));
+
+    let value = |x: usize, y: usize| coarse.get(y * width + x);
+    let differs = |x: usize, y: usize| {
+        let v = value(x, y);
+        let outside = |halo: &Option<Escaped>, i: usize| halo.as_ref().map(|h| h.get(i));
+        let neighbors = [
+            if x > 0 {
+                Some(value(x - 1, y))
+            } else {
+                outside(&left, y)
+            },
+            if x + 1 < width {
+                Some(value(x + 1, y))
+            } else {
+                outside(&right, y)
+            },
+            if y > 0 {
+                Some(value(x, y - 1))
+            } else {
+                outside(&above, x)
+            },
+            if y + 1 < height {
+                Some(value(x, y + 1))
+            } else {
+                outside(&below, x)
+            },
+        ];
+        neighbors
+            .into_iter()
+            .flatten()
+            .any(|n| (n - v).abs() > threshold)
+    };
+
+    let (dx, dy) = view.pixel_size();
+    let pixel_options = CalcOptions {
+        adaptive: None,
+        ..*options
+    };
+    // the single pixel supersampled, centered on the point its center turns into
+    // so the samples land where the ones of the whole fine view do
+    let resample = |x: usize, y: usize| {
+        let (cx, cy) = view.rotate(
+            view.x_min + ((view.columns.0 + x) as f64 + 0.5) * dx,
+            view.y_min + ((view.rows.0 + y) as f64 + 0.5) * dy,
+            view.rotation,
+        );
+        let pixel_view = View {
+            x_min: cx - dx / 2.0,
+            x_max: cx + dx / 2.0,
+            y_min: cy - dy / 2.0,
+            y_max: cy + dy / 2.0,
+            width: 1,
+            height: 1,
+            rows: (0, 1),
+            columns: (0, 1),
+            ..*view
+        };
+        calc_escaped(&pixel_options, &pixel_view, &hidden).get(0)
+    };
+
+    let mut values: Vec<f64> = (0..coarse.len()).map(|i| coarse.get(i)).collect();
+    values
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                if differs(x, y) {
+                    *pixel = resample(x, y);
+                }
+            }
+            progress.inc((samples - 1) as u64);
+        });
+
+    match coarse {
+        Escaped::Distance(_) => Escaped::Distance(values),
+        Escaped::Trap(_) => Escaped::Trap(values),
+        Escaped::Stripe(_) => Escaped::Stripe(values),
+        _ => Escaped::Smooth(values),
+    }
+}
+
+/// Width of the tiles of pixels making up one parallel task and their default height, rows
+/// crossing the deep interior take far longer than the escaping ones, small tiles spread
+/// them over the threads
+pub const TILE: usize = 64;
+
+/// Calls `at_vec` for every `N` sequential pixels of the rows `view.rows` and the columns
+/// `view.columns`, it gets real parts
+/// and imaginary parts of the points, the fractal `symmetric` about the real axis gets
+/// the rows of the unrotated view centered on the axis mirrored
+fn calc_rows<T, const N: usize, V>(
+    view: &View,
+    symmetric: bool,
+    progress: &ProgressBar,
+    at_vec: V,
+) -> Vec<T>
+where
+    T: Copy + Default + Send,
+    V: Fn([f64; N], [f64; N]) -> [T; N] + Sync,
+{
+    let (left_column, end_column) = view.columns;
+    let width = end_column - left_column;
+    let height = view.height;
+    let (first, end) = view.rows;
+    let mut buf: Vec<T> = vec![T::default(); width * (end - first)];
+
+    let (dx, dy) = view.pixel_size();
+
+    // rows y and height - y lie on the opposite sides of the axis, the first row
+    // and the one on the axis have no pair, the rows below the axis show the values
+    // of their pairs and copy them when the pair is calculated as well
+    let mirror = symmetric
+        && view.y_min == -view.y_max
+        && view.rotation == 0.0
+        && view.offsets == Offsets::None;
+    let source = |y: usize| {
+        if mirror && y >= 1 && 2 * y < height {
+            height - y
+        } else {
+            y
+        }
+    };
+    let copied = |y: usize| source(y) != y && source(y) < end;
+
+    // the pixels `row` of the row `y` starting at the column `left`
+    let calc_row = |y: usize, left: usize, row: &mut [T]| {
+        let cy = view.y_min + (source(y) as f64) * dy;
+        // points of the pixels of the columns, shifted by the offsets and turned about
+        // the center of the view
+        let points = |columns: [usize; N]| {
+            let mut re = [0.0; N];
+            let mut im = [0.0; N];
+            for (i, x) in columns.into_iter().enumerate() {
+                let cx = view.x_min + (x as f64) * dx;
+                let (ox, oy) = view.offsets.at(x, y, cx, cy);
+                (re[i], im[i]) = view.rotate(cx + ox * dx, cy + oy * dy, view.rotation);
+            }
+            (re, im)
+        };
+
+        let mut chunks = row.chunks_exact_mut(N);
+        let mut x = left;
+
+        for chunk in chunks.by_ref() {
+            let (cx, cy) = points(std::array::from_fn(|i| x + i));
+            chunk.copy_from_slice(&at_vec(cx, cy));
+
+            x += N;
+        }
+
+        // unused lanes repeat the last pixel, so they escape together with it and
+        // never keep the loop running longer
+        let rest = chunks.into_remainder();
+        if !rest.is_empty() {
+            let last = x + rest.len() - 1;
+            let (cx, cy) = points(std::array::from_fn(|i| (x + i).min(last)));
+            rest.copy_from_slice(&at_vec(cx, cy)[..rest.len()]);
+        }
+    };
+
+    // bands of tile rows write into their slices of the buffer, the tiles of a band
+    // are calculated into buffers of their own and copied into the band
+    buf.par_chunks_mut(width * view.rows_per_task)
+        .enumerate()
+        .for_each(|(band, rows)| {
+            let top = first + band * view.rows_per_task;
+            let band_height = rows.len() / width;
+            // the first tile reaching a row checks the deadline for all of them, so the
+            // row is calculated in every tile or in none
+            let started: Vec<OnceLock<bool>> = (0..band_height).map(|_| OnceLock::new()).collect();
+            let tiles: Vec<Vec<T>> = (0..width.div_ceil(TILE))
+                .into_par_iter()
+                .map(|tx| {
+                    let left = tx * TILE;
+                    let tile_width = TILE.min(width - left);
+                    let mut tile = vec![T::default(); tile_width * band_height];
+                    for (j, row) in tile.chunks_exact_mut(tile_width).enumerate() {
+                        let in_time = started[j].get_or_init(|| {
+                            view.deadline
+                                .is_none_or(|deadline| Instant::now() < deadline)
+                        });
+                        if !in_time {
+                            break;
+                        }
+                        if !copied(top + j) {
+                            calc_row(top + j, left_column + left, row);
+                        }
+                    }
+                    tile
+                })
+                .collect();
+
+            for (tx, tile) in tiles.iter().enumerate() {
+                let left = tx * TILE;
+                let tile_width = tile.len() / band_height;
+                for (j, row) in tile.chunks_exact(tile_width).enumerate() {
+                    rows[j * width + left..j * width + left + tile_width].copy_from_slice(row);
+                }
+            }
+            progress.inc((top..top + band_height).filter(|&y| !copied(y)).count() as u64);
+        });
+
+    for y in (first..end).filter(|&y| copied(y)) {
+        let offset = (source(y) - first) * width;
+        buf.copy_within(offset..offset + width, (y - first) * width);
+        progress.inc(1);
+    }
+
+    buf
+}
+
+/// Settings of the escape time iterations
+#[derive(Debug, Clone, Copy)]
+pub struct CalcOptions {
+    pub variant: Variant,
+    pub iters: usize,
+    /// Squared bailout radius, smooth values use at least `SMOOTH_THRESHOLD`
+    pub threshold: f64,
+    pub smooth: bool,
+    pub simd_width: SimdWidth,
+    pub precision: Precision,
+    /// Skip the iterations of the points inside the main cardioid and the period-2 bulb
+    /// of the Mandelbrot set
+    pub fast_interior: bool,
+    /// Side of the grid of samples averaged into every pixel, 1 takes a single sample
+    pub samples: usize,
+    /// Positions of the `samples` in the pixels
+    pub pattern: SamplePattern,
+    /// Seed of the positions of `SamplePattern::Random`
+    pub seed: u64,
+    /// Distance estimation of the Mandelbrot set instead of the escape counts,
+    /// the variant and `smooth` are ignored
+    pub distance: bool,
+    /// Orbit trap distances of the Mandelbrot set instead of the escape counts,
+    /// the variant and `smooth` are ignored
+    pub trap: Option<Trap>,
+    /// Stripe averages of the Mandelbrot set of this stripe density instead of the escape
+    /// counts, the variant and `smooth` are ignored
+    pub stripe: Option<f64>,
+    /// Triangle inequality averages of the Mandelbrot set instead of the escape counts,
+    /// the variant and `smooth` are ignored
+    pub tia: bool,
+    /// Arguments of z at the escape of the Mandelbrot set instead of the escape counts,
+    /// the variant and `smooth` are ignored
+    pub phase: bool,
+    /// Smooth escape counts and brightnesses of the Mandelbrot set for the HSV coloring,
+    /// the variant and `smooth` are ignored and `adaptive` takes every sample
+    pub hsv: bool,
+    /// Smooth escape counts and brightnesses of the Mandelbrot set lit from this azimuth
+    /// and elevation in radians (`calc_mandelbrot_lighting`), the variant and `smooth` are
+    /// ignored and `adaptive` takes every sample
+    pub lighting: Option<(f64, f64)>,
+    /// Perturbation of the Mandelbrot set around the center of the view, for the views
+    /// `View::beyond_f64`, escape counts and `smooth` only
+    pub deep: bool,
+    /// Take the `samples` only in the pixels whose single sample differs by more than this
+    /// from one of its 4 neighbors, the other pixels keep their single sample
+    pub adaptive: Option<f64>,
+    /// Escape counts of the Mandelbrot set from `mandelbrot_at_point` pixel by pixel instead
+    /// of the SIMD kernels, a slow reference the kernels must match exactly
+    pub scalar: bool,
+}
+
+/// Escape values of every pixel of the view with the kernel picked by the options,
+/// `progress` gets incremented once per finished row of samples (`samples` per pixel row)
+pub fn calc_escaped(options: &CalcOptions, view: &View, progress: &ProgressBar) -> Escaped {
+    if let Some(threshold) = options
+        .adaptive
+        .filter(|_| options.samples > 1 && !options.hsv && options.lighting.is_none())
+    {
+        return calc_escaped_adaptive(options, threshold, view, progress);
+    }
+    if options.samples > 1 {
+        let samples = options.samples;
+        let fine_view = View {
+            width: view.width * samples,
+            height: view.height * samples,
+            rows: (view.rows.0 * samples, view.rows.1 * samples),
+            columns: (view.columns.0 * samples, view.columns.1 * samples),
+            offsets: match options.pattern {
+                SamplePattern::Grid => Offsets::None,
+                SamplePattern::RotatedGrid => Offsets::RotatedGrid(samples),
+                SamplePattern::Random => Offsets::Random(options.seed),
+            },
+            ..*view
+        };
+        let fine_options = CalcOptions {
+            samples: 1,
+            ..*options
+        };
+        let fine = calc_escaped(&fine_options, &fine_view, progress);
+        let width = view.columns.1 - view.columns.0;
+        let values = fine.downsample(width, samples);
+        return match fine {
+            // back from the pixels of the fine view into the whole ones
+            Escaped::Distance(_) => {
+                Escaped::Distance(values.into_iter().map(|d| d / samples as f64).collect())
+            }
+            Escaped::Trap(_) => Escaped::Trap(values),
+            Escaped::Stripe(_) => Escaped::Stripe(values),
+            Escaped::Hsv(buf) => {
+                let shades = Escaped::Smooth(buf.iter().map(|v| v[1]).collect());
+                let shades = shades.downsample(width, samples);
+                Escaped::Hsv(
+                    values
+                        .into_iter()
+                        .zip(shades)
+                        .map(|(c, s)| [c, s])
+                        .collect(),
+                )
+            }
+            _ => Escaped::Smooth(values),
+        };
+    }
+    if options.deep {
+        let threshold = if options.smooth {
+            options.threshold.max(SMOOTH_THRESHOLD)
+        } else {
+            options.threshold
+        };
+        return calc_mandelbrot_deep(
+            options.iters,
+            threshold,
+            options.smooth,
+            options.fast_interior,
+            view,
+            progress,
+        );
+    }
+    if let Some(trap) = options.trap {
+        return Escaped::Trap(calc_mandelbrot_trap(
+            options.iters,
+            options.threshold,
+            trap,
+            view,
+            progress,
+        ));
+    }
+    if let Some(density) = options.stripe {
+        return Escaped::Stripe(calc_mandelbrot_stripe(
+            options.iters,
+            options.threshold.max(SMOOTH_THRESHOLD),
+            density,
+            options.fast_interior,
+            view,
+            progress,
+        ));
+    }
+    if options.tia {
+        return Escaped::Stripe(calc_mandelbrot_tia(
+            options.iters,
+            options.threshold.max(SMOOTH_THRESHOLD),
+            options.fast_interior,
+            view,
+            progress,
+        ));
+    }
+    if options.hsv {
+        return Escaped::Hsv(calc_mandelbrot_hsv(
+            options.iters,
+            options.threshold.max(SMOOTH_THRESHOLD),
+            options.fast_interior,
+            view,
+            progress,
+        ));
+    }
+    if let Some((azimuth, elevation)) = options.lighting {
+        return Escaped::Hsv(calc_mandelbrot_lighting(
+            options.iters,
+            options.threshold.max(SMOOTH_THRESHOLD),
+            azimuth,
+            elevation,
+            options.fast_interior,
+            view,
+            progress,
+        ));
+    }
+    if options.phase {
+        return Escaped::Stripe(calc_mandelbrot_phase(
+            options.iters,
+            options.threshold,
+            options.fast_interior,
+            view,
+            progress,
+        ));
+    }
+    if options.distance {
+        return Escaped::Distance(calc_mandelbrot_distance(
+            options.iters,
+            options.threshold.max(SMOOTH_THRESHOLD),
+            options.fast_interior,
+            view,
+            progress,
+        ));
+    }
+
+    let iters = options.iters;
+    let threshold = options.threshold;
+    let smooth_threshold = threshold.max(SMOOTH_THRESHOLD);
+    let fast = options.fast_interior;
+
+    match (options.variant, options.smooth) {
+        (Variant::Mandelbrot, smooth) if options.precision == Precision::Dd => {
+            let threshold = if smooth { smooth_threshold } else { threshold };
+            calc_mandelbrot_dd(iters, threshold, smooth, fast, view, progress)
+        }
+        (Variant::Mandelbrot, false) => match (options.precision, options.simd_width) {
+            _ if options.scalar => Escaped::Counts(calc_mandelbrot_scalar(
+                iters, threshold, fast, view, progress,
+            )),
+            (Precision::F32, _) => {
+                Escaped::Counts(calc_mandelbrot_f32(iters, threshold, fast, view, progress))
+            }
+            (Precision::F64 | Precision::Dd, SimdWidth::Four) => {
+                Escaped::Counts(calc_mandelbrot(iters, threshold, fast, view, progress))
+            }
+            (Precision::F64 | Precision::Dd, SimdWidth::Eight) => {
+                Escaped::Counts(calc_mandelbrot8(iters, threshold, fast, view, progress))
+            }
+        },
+        (Variant::Mandelbrot, true) => Escaped::Smooth(calc_mandelbrot_smooth(
+            iters,
+            smooth_threshold,
+            fast,
+            view,
+            progress,
+        )),
+        (Variant::Multibrot(power), false) => {
+            Escaped::Counts(calc_multibrot(iters, power, threshold, view, progress))
+        }
+        (Variant::Multibrot(power), true) => Escaped::Smooth(calc_multibrot_smooth(
+            iters,
+            power,
+            smooth_threshold,
+            view,
+            progress,
+        )),
+        (Variant::BurningShip, false) => {
+            Escaped::Counts(calc_burning_ship(iters, threshold, view, progress))
+        }
+        (Variant::BurningShip, true) => Escaped::Smooth(calc_burning_ship_smooth(
+            iters,
+            smooth_threshold,
+            view,
+            progress,
+        )),
+        (Variant::Tricorn, false) => {
+            Escaped::Counts(calc_tricorn(iters, threshold, view, progress))
+        }
+        (Variant::Tricorn, true) => {
+            Escaped::Smooth(calc_tricorn_smooth(iters, smooth_threshold, view, progress))
+        }
+    }
+}
+
+/// Escape counts of every pixel of the view, `threshold` is the squared bailout radius,
+/// `fast_interior` skips the points known to belong to the set
+pub fn calc_fractal<F: Fractal>(
+    fractal: &F,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_rows(view, fractal.symmetric(), progress, |cx, cy| {
+        let c = Complex4::from_parts(cx, cy);
+        fractal_at_vec(fractal, &c, iters, threshold, fast_interior)
+    })
+}
+
+/// Same as `calc_fractal` but with fractional escape counts `n + 1 - log(log(|z|)) / log(power)`
+pub fn calc_fractal_smooth<F: Fractal>(
+    fractal: &F,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    let power = fractal.power();
+    calc_rows(view, fractal.symmetric(), progress, |cx, cy| {
+        let c = Complex4::from_parts(cx, cy);
+        let (counts, norms) = fractal_norm_at_vec(fractal, &c, iters, threshold, fast_interior);
+        std::array::from_fn(|i| smooth_count(counts[i], norms[i], iters, power))
+    })
+}
+
+/// Escape counts of the Mandelbrot set, `fast_interior` skips the points inside the main
+/// cardioid and the period-2 bulb
+pub fn calc_mandelbrot(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_rows(view, true, progress, |cx, cy| {
+        mandelbrot_at_vec(
+            &Complex4::from_parts(cx, cy),
+            iters,
+            threshold,
+            fast_interior,
+        )
+    })
+}
+
+/// Same as `calc_mandelbrot` but a pixel at a time with `mandelbrot_at_point`, without
+/// any SIMD lanes
+pub fn calc_mandelbrot_scalar(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_rows(view, true, progress, |[cx], [cy]| {
+        [mandelbrot_at_point(cx, cy, iters, threshold, fast_interior)]
+    })
+}
+
+/// Same as `calc_mandelbrot` but iterates 8 pixels at once in single precision
+pub fn calc_mandelbrot_f32(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_rows(view, true, progress, |cx, cy| {
+        let c = Complex8f32::from_parts(cx, cy);
+        mandelbrot_at_vec_f32(&c, iters, threshold as f32, fast_interior)
+    })
+}
+
+/// Same as `calc_mandelbrot` but iterates 8 pixels at once
+pub fn calc_mandelbrot8(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_rows(view, true, progress, |cx, cy| {
+        mandelbrot_at_vec8(
+            &Complex8::from_parts(cx, cy),
+            iters,
+            threshold,
+            fast_interior,
+        )
+    })
+}
+
+/// Same as `calc_mandelbrot` but with fractional escape counts `n + 1 - log2(log(|z|))`
+pub fn calc_mandelbrot_smooth(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    calc_rows(view, true, progress, |cx, cy| {
+        let c = Complex4::from_parts(cx, cy);
+        let (counts, norms) = mandelbrot_norm_at_vec(&c, iters, threshold, fast_interior);
+        std::array::from_fn(|i| smooth_count(counts[i], norms[i], iters, 2))
+    })
+}
+
+/// Estimated distances of the pixels of the view to the boundary of the Mandelbrot set
+/// in pixels, 0 for the points of the set
+pub fn calc_mandelbrot_distance(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    let pixel = (view.x_max - view.x_min) / view.width as f64;
+
+    calc_rows(view, true, progress, |cx, cy| {
+        let c = Complex4::from_parts(cx, cy);
+        let (counts, norms, dz_norms) =
+            mandelbrot_derivative_at_vec(&c, iters, threshold, fast_interior);
+        std::array::from_fn(|i| {
+            if counts[i] == iters as u64 {
+                0.0
+            } else {
+                distance_estimate(norms[i], dz_norms[i]) / pixel
+            }
+        })
+    })
+}
+
+/// Closest approaches of the orbits of the pixels of the view to the trap
+pub fn calc_mandelbrot_trap(
+    iters: usize,
+    threshold: f64,
+    trap: Trap,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    calc_rows(view, true, progress, |cx, cy| {
+        mandelbrot_trap_at_vec(&Complex4::from_parts(cx, cy), iters, threshold, trap).1
+    })
+}
+
+/// Stripe averages of the orbits of the pixels of the view, a single point at a time
+/// since the sine and the arctangent have no SIMD counterparts in `wide`
+pub fn calc_mandelbrot_stripe(
+    iters: usize,
+    threshold: f64,
+    density: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    // the conjugate points get 1 - t, the mirrored rows would get t
+    calc_rows(view, false, progress, |cx: [f64; 4], cy: [f64; 4]| {
+        std::array::from_fn(|i| {
+            mandelbrot_stripe_at_point(cx[i], cy[i], iters, threshold, density, fast_interior)
+        })
+    })
+}
+
+/// Triangle inequality averages of the orbits of the pixels of the view, a single point
+/// at a time like the stripe averages
+pub fn calc_mandelbrot_tia(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    calc_rows(view, true, progress, |cx: [f64; 4], cy: [f64; 4]| {
+        std::array::from_fn(|i| {
+            mandelbrot_tia_at_point(cx[i], cy[i], iters, threshold, fast_interior)
+        })
+    })
+}
+
+/// Arguments of z at the escape of the pixels of the view in turns, in range [0, 1), -1 for
+/// the points of the set
+pub fn calc_mandelbrot_phase(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    // the conjugate points get 1 - p, the mirrored rows would get p
+    calc_rows(view, false, progress, |cx: [f64; 4], cy: [f64; 4]| {
+        std::array::from_fn(|i| {
+            let (count, z) = mandelbrot_z_at_point(cx[i], cy[i], iters, threshold, fast_interior);
+            if count == iters as u64 {
+                -1.0
+            } else {
+                (z.arg() / std::f64::consts::TAU).rem_euclid(1.0)
+            }
+        })
+    })
+}
+
+/// Darkening of the HSV coloring at the arguments of z opposite to the positive real axis
+const HSV_SHADING: f64 = 0.5;
+
+/// Smooth escape counts of the Mandelbrot set along with the brightness from the argument
+/// of z at the escape, 1 - HSV_SHADING * (1 - cos(arg z)) / 2 goes around the turn without
+/// a seam, points of the set get `iters` and full brightness
+pub fn calc_mandelbrot_hsv(
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<[f64; 2]> {
+    calc_rows(view, true, progress, |cx: [f64; 4], cy: [f64; 4]| {
+        std::array::from_fn(|i| {
+            let (count, z) = mandelbrot_z_at_point(cx[i], cy[i], iters, threshold, fast_interior);
+            let shade = 1.0 - HSV_SHADING * (0.5 - 0.5 * z.arg().cos());
+            [smooth_count(count, z.norm_sqr(), iters, 2), shade]
+        })
+    })
+}
+
+/// Smooth escape counts of the Mandelbrot set along with the Lambertian brightness of the
+/// relief with the set on top: the normal leans by 45 degrees along z / dz, the slope of
+/// the potential away from the set, and the light comes from `azimuth` (from the positive
+/// real axis toward the positive imaginary one) and `elevation` over the plane in radians,
+/// points of the set get `iters` and full brightness
+pub fn calc_mandelbrot_lighting(
+    iters: usize,
+    threshold: f64,
+    azimuth: f64,
+    elevation: f64,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<[f64; 2]> {
+    let light = Complex::from_polar(elevation.cos(), azimuth);
+    let height = elevation.sin();
+
+    // the mirrored rows would get the light mirrored too
+    calc_rows(view, false, progress, |cx: [f64; 4], cy: [f64; 4]| {
+        std::array::from_fn(|i| {
+            let (count, z, dz) =
+                mandelbrot_dz_at_point(cx[i], cy[i], iters, threshold, fast_interior);
+            if count == iters as u64 {
+                return [iters as f64, 1.0];
+            }
+            let normal = z / dz;
+            let normal = normal / normal.norm();
+            // (normal, 1) / sqrt(2) against the unit vector toward the light
+            let shade = (normal.re * light.re + normal.im * light.im + height)
+                * std::f64::consts::FRAC_1_SQRT_2;
+            let shade = if shade.is_finite() {
+                shade.max(0.0)
+            } else {
+                1.0
+            };
+            [smooth_count(count, z.norm_sqr(), iters, 2), shade]
+        })
+    })
+}
+
+/// Exterior distance estimate `|z| * log(|z|) / |dz|` from the squared magnitudes
+/// of `z` and of its derivative `dz` at the moment of escape
+pub fn distance_estimate(norm_sqr: f64, dz_norm_sqr: f64) -> f64 {
+    // log(|z|) = log(|z|^2) / 2
+    (norm_sqr / dz_norm_sqr).sqrt() * 0.5 * norm_sqr.ln()
+}
+
+/// Escape counts of the Mandelbrot set by perturbation, fractional ones with `smooth`:
+/// the orbit of the center of the view is iterated once in double-double precision and
+/// every pixel only follows its f64 difference from it, so the pixels stay apart far below
+/// the precision of their f64 coordinates
+pub fn calc_mandelbrot_deep(
+    iters: usize,
+    threshold: f64,
+    smooth: bool,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Escaped {
+    let center_re = (view.x_min + view.x_max) / 2.0;
+    let center_im = (view.y_min + view.y_max) / 2.0;
+    let reference = reference_orbit(center_re, center_im, iters, threshold);
+
+    let buf = calc_rows_offsets(view, progress, |dc| {
+        if fast_interior && in_main_body(center_re + dc.re, center_im + dc.im) {
+            (iters as u64, 0.0)
+        } else {
+            mandelbrot_delta_at_point(&reference, dc, iters, threshold)
+        }
+    });
+    counts_or_smooth(buf, iters, smooth)
+}
+
+/// Escape counts of the Mandelbrot set iterated in double-double precision, fractional ones
+/// with `smooth`, the center of the view and the offsets of the pixels from it add up
+/// to the points of the pixels without rounding
+pub fn calc_mandelbrot_dd(
+    iters: usize,
+    threshold: f64,
+    smooth: bool,
+    fast_interior: bool,
+    view: &View,
+    progress: &ProgressBar,
+) -> Escaped {
+    let half = |sum: DoubleDouble| DoubleDouble {
+        hi: sum.hi / 2.0,
+        lo: sum.lo / 2.0,
+    };
+    let center_re = half(DoubleDouble::new(view.x_min) + DoubleDouble::new(view.x_max));
+    let center_im = half(DoubleDouble::new(view.y_min) + DoubleDouble::new(view.y_max));
+
+    let buf = calc_rows_offsets(view, progress, |dc| {
+        mandelbrot_dd_at_point(
+            center_re + DoubleDouble::new(dc.re),
+            center_im + DoubleDouble::new(dc.im),
+            iters,
+            threshold,
+            fast_interior,
+        )
+    });
+    counts_or_smooth(buf, iters, smooth)
+}
+
+/// Calls `at_point` for every pixel of the rows `view.rows` and the columns `view.columns`
+/// with the offset of its point
+/// from the center of the view, a single pixel at a time, the offsets keep the pixels
+/// apart far below the precision of the f64 coordinates of their points
+fn calc_rows_offsets<T, P>(view: &View, progress: &ProgressBar, at_point: P) -> Vec<T>
+where
+    T: Copy + Default + Send,
+    P: Fn(Complex<f64>) -> T + Sync,
+{
+    let (dx, dy) = view.pixel_size();
+    let (sin, cos) = view.rotation.sin_cos();
+    let (first, end) = view.rows;
+
+    let width = view.columns.1 - view.columns.0;
+    let mut buf = vec![T::default(); width * (end - first)];
+    buf.par_chunks_mut(width * view.rows_per_task)
+        .enumerate()
+        .for_each(|(band, rows)| {
+            for (j, row) in rows.chunks_exact_mut(width).enumerate() {
+                if view
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return;
+                }
+                let y = first + band * view.rows_per_task + j;
+                for (x, pixel) in (view.columns.0..).zip(row.iter_mut()) {
+                    let (re, im) = (view.x_min + x as f64 * dx, view.y_min + y as f64 * dy);
+                    let (ox, oy) = view.offsets.at(x, y, re, im);
+                    let du = (x as f64 + ox - view.width as f64 / 2.0) * dx;
+                    let dv = (y as f64 + oy - view.height as f64 / 2.0) * dy;
+                    *pixel = at_point(Complex::new(du * cos - dv * sin, du * sin + dv * cos));
+                }
+                progress.inc(1);
+            }
+        });
+
+    buf
+}
+
+/// Escape counts of the pairs of counts and final squared magnitudes, smooth ones with
+/// `smooth`
+fn counts_or_smooth(buf: Vec<(u64, f64)>, iters: usize, smooth: bool) -> Escaped {
+    if smooth {
+        Escaped::Smooth(
+            buf.into_iter()
+                .map(|(count, norm)| smooth_count(count, norm, iters, 2))
+                .collect(),
+        )
+    } else {
+        Escaped::Counts(buf.into_iter().map(|(count, _)| count).collect())
+    }
+}
+
+/// Escape counts of the recurrence z = z^power + c
+pub fn calc_multibrot(
+    iters: usize,
+    power: u32,
+    threshold: f64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_fractal(&Multibrot(power), iters, threshold, false, view, progress)
+}
+
+/// Same as `calc_multibrot` but with fractional escape counts
+pub fn calc_multibrot_smooth(
+    iters: usize,
+    power: u32,
+    threshold: f64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    calc_fractal_smooth(&Multibrot(power), iters, threshold, false, view, progress)
+}
+
+/// Escape counts of the recurrence z = (|Re z| + i|Im z|)^2 + c
+pub fn calc_burning_ship(
+    iters: usize,
+    threshold: f64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u64> {
+    calc_fractal(&BurningShip, iters, threshold, false, view, progress)
+}
+
+/// Same as `calc_burning_ship` but with fractional escape counts
+pub fn calc_burning_ship_smooth(
+    iters: usize,
+    threshold: f64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    calc_fractal_smooth(&BurningShip, iters, threshold, false, view, progress)
+}
+
+/// Escape counts of the recurrence z = conj(z)^2 + c
+pub fn calc_tricorn(iters: usize, threshold: f64, view: &View, progress: &ProgressBar) -> Vec<u64> {
+    calc_fractal(&Tricorn, iters, threshold, false, view, progress)
+}
+
+/// Same as `calc_tricorn` but with fractional escape counts
+pub fn calc_tricorn_smooth(
+    iters: usize,
+    threshold: f64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<f64> {
+    calc_fractal_smooth(&Tricorn, iters, threshold, false, view, progress)
+}
+
+/// Half of the side of the square the starting points of the Buddhabrot orbits are
+/// sampled from, every point outside of it escapes at once
+const BUDDHABROT_RANGE: f64 = 2.0;
+
+/// Number of random points sampled by a single task of the Buddhabrot and of the area
+/// estimation
+pub const RANDOM_CHUNK: u64 = 10_000;
+
+/// Counts the visits of the orbits of `samples` random points escaping within `iters`
+/// to every pixel of the view (Buddhabrot), the points are random, so the result is
+/// a statistical estimate differing a bit from run to run, the `progress` advances
+/// by a chunk of `RANDOM_CHUNK` samples
+pub fn calc_buddhabrot(
+    iters: usize,
+    threshold: f64,
+    samples: u64,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<u32> {
+    let (width, height) = (view.width, view.height);
+    let chunks = samples.div_ceil(RANDOM_CHUNK);
+
+    // every task accumulates into the bins of its thread, they get summed up at the end
+    let (bins, _) = (0..chunks)
+        .into_par_iter()
+        .fold(
+            || (vec![0u32; width * height], Vec::with_capacity(iters)),
+            |(mut bins, mut orbit), chunk| {
+                let mut rng = rand::rng();
+                let count = RANDOM_CHUNK.min(samples - chunk * RANDOM_CHUNK);
+
+                for _ in 0..count {
+                    let cx = rng.random_range(-BUDDHABROT_RANGE..BUDDHABROT_RANGE);
+                    let cy = rng.random_range(-BUDDHABROT_RANGE..BUDDHABROT_RANGE);
+                    // the points of the main body never escape
+                    if in_main_body(cx, cy) {
+                        continue;
+                    }
+
+                    orbit.clear();
+                    let c = Complex::new(cx, cy);
+                    let mut z = c;
+                    while orbit.len() < iters && z.norm_sqr() <= threshold {
+                        orbit.push(z);
+                        z = z * z + c;
+                    }
+                    if orbit.len() == iters {
+                        continue;
+                    }
+
+                    for z in &orbit {
+                        if let Some((x, y)) = view.complex_to_pixel(z.re, z.im) {
+                            let bin = &mut bins[y * width + x];
+                            *bin = bin.saturating_add(1);
+                        }
+                    }
+                }
+
+                progress.inc(1);
+                (bins, orbit)
+            },
+        )
+        .reduce(
+            || (vec![0u32; width * height], Vec::new()),
+            |(mut a, orbit), (b, _)| {
+                for (a, b) in a.iter_mut().zip(b) {
+                    *a = a.saturating_add(b);
+                }
+                (a, orbit)
+            },
+        );
+
+    bins
+}
+
+/// Number of the uniformly random points of the view out of `samples` that stay bounded
+/// for `iters` iterations of the Mandelbrot recurrence, a statistical estimate of the share
+/// of the set in the view, the `progress` advances by a chunk of `RANDOM_CHUNK` samples
+pub fn count_random_in_set(
+    iters: usize,
+    threshold: f64,
+    samples: u64,
+    view: &View,
+    progress: &ProgressBar,
+) -> u64 {
+    (0..samples.div_ceil(RANDOM_CHUNK))
+        .into_par_iter()
+        .map(|chunk| {
+            let mut rng = rand::rng();
+            let count = RANDOM_CHUNK.min(samples - chunk * RANDOM_CHUNK);
+            let inside = (0..count)
+                .filter(|_| {
+                    let (cx, cy) = view.rotate(
+                        rng.random_range(view.x_min..view.x_max),
+                        rng.random_range(view.y_min..view.y_max),
+                        view.rotation,
+                    );
+                    mandelbrot_at_point(cx, cy, iters, threshold, true) == iters as u64
+                })
+                .count();
+            progress.inc(1);
+            inside as u64
+        })
+        .sum()
+}
+
+/// Roots of z^3 - 1 the Newton iterations converge to, the cube roots of unity
+pub const NEWTON_ROOTS: [Complex<f64>; 3] = [
+    Complex::new(1.0, 0.0),
+    Complex::new(-0.5, 0.866_025_403_784_438_6),
+    Complex::new(-0.5, -0.866_025_403_784_438_6),
+];
+
+/// Squared distance to a root of the points counted as converged to it
+const NEWTON_TOLERANCE: f64 = 1e-12;
+
+/// Bounds `(x_min, x_max, y_min, y_max)` of the Newton fractal centered on 0 showing
+/// the three basins in a picture with the given width to height ratio
+pub fn newton_default_coords(aspect: f64) -> (f64, f64, f64, f64) {
+    let dy = 3.0 / aspect.max(1.0);
+    (-dy * aspect / 2.0, dy * aspect / 2.0, -dy / 2.0, dy / 2.0)
+}
+
+/// Index into `NEWTON_ROOTS` of the root the Newton iterations z = z - (z^3 - 1) / (3z^2)
+/// started at c converge to and the number of steps it took, `None` when they don't
+/// within `iters` steps or hit the critical point 0
+pub fn newton_at_point(cx: f64, cy: f64, iters: usize) -> Option<(usize, usize)> {
+    let mut z = Complex::new(cx, cy);
+    for step in 0..=iters {
+        if let Some(root) = NEWTON_ROOTS
+            .iter()
+            .position(|root| (z - root).norm_sqr() < NEWTON_TOLERANCE)
+        {
+            return Some((root, step));
+        }
+        let z2 = z * z;
+        if z2.norm_sqr() == 0.0 {
+            return None;
+        }
+        z -= (z2 * z - 1.0) / (3.0 * z2);
+    }
+    None
+}
+
+/// Roots and steps of `newton_at_point` of every pixel of the view, the `progress`
+/// advances by a row
+pub fn calc_newton(
+    iters: usize,
+    view: &View,
+    progress: &ProgressBar,
+) -> Vec<Option<(usize, usize)>> {
+    let mut buf = vec![None; view.width * view.height];
+    buf.par_chunks_mut(view.width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, point) in row.iter_mut().enumerate() {
+                *point = view
+                    .pixel_to_complex(x, y)
+                    .and_then(|(cx, cy)| newton_at_point(cx, cy, iters));
+            }
+            progress.inc(1);
+        });
+    buf
+}
+
+fn smooth_count(count: u64, norm_sqr: f64, iters: usize, power: u32) -> f64 {
+    if count == iters as u64 {
+        return iters as f64;
+    }
+
+    // log(|z|) = log(|z|^2) / 2
+    let nu = count as f64 + 1.0 - (0.5 * norm_sqr.ln()).ln() / (power as f64).ln();
+    nu.clamp(0.0, (iters - 1) as f64)
+}
+
+/// Escape counts of 4 points of the `fractal`, `iters` for the points of the set,
+/// `fast_interior` skips the iterations of the points known to belong to the set and
+/// stops the lanes on periodic orbits
+#[inline(always)]
+pub fn fractal_at_vec<F: Fractal>(
+    fractal: &F,
+    c: &Complex4,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> [u64; 4] {
+    let c = *c;
+    let mut z = fractal.initial_z(c);
+    // points known to be in the set are pushed far beyond the threshold to leave the loop
+    // at once and get their count restored afterwards
+    let mut inside = if fast_interior {
+        let inside = fractal.known_inside(&c);
+        z.real = inside.blend(f64x4::splat(f64::INFINITY), z.real);
+        inside
+    } else {
+        f64x4::splat(0.0)
+    };
+    let mut count = f64x4::splat(0.0);
+    let threshold = f64x4::splat(threshold);
+    let epsilon = f64x4::splat(PERIODICITY_EPSILON);
+    // periodicity check of `mandelbrot_at_point`, shared by the lanes since they step together
+    let mut reference = z;
+    let mut interval = PERIODICITY_INTERVAL;
+    let mut steps = 0;
+
+    for _ in 0..iters {
+        let mask = z.norm_sqr().simd_le(threshold);
+
+        if !mask.any() {
+            break;
+        }
+
+        count += mask.blend(f64x4::splat(1.0), f64x4::splat(0.0));
+
+        z = fractal.step(z, c);
+
+        if fast_interior {
+            // lanes back at the reference are done as points of the set and leave
+            // the loop like the known ones, the escaped lanes are left alone
+            let dr = z.real - reference.real;
+            let di = z.imag - reference.imag;
+            let periodic = (dr * dr + di * di).simd_lt(epsilon) & mask;
+            if periodic.any() {
+                inside |= periodic;
+                z.real = periodic.blend(f64x4::splat(f64::INFINITY), z.real);
+            }
+            steps += 1;
+            if steps == interval {
+                reference = z;
+                steps = 0;
+                interval *= 2;
+            }
+        }
+    }
+
+    count = inside.blend(f64x4::splat(iters as f64), count);
+
+    let arr: [f64; 4] = count.into();
+    [arr[0] as u64, arr[1] as u64, arr[2] as u64, arr[3] as u64]
+}
+
+/// Same as `fractal_at_vec` but also returns squared magnitudes of `z` at the moment
+/// of escape, lives apart since the tracking costs about 20% of the plain loop
+#[inline(always)]
+pub fn fractal_norm_at_vec<F: Fractal>(
+    fractal: &F,
+    c: &Complex4,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> ([u64; 4], [f64; 4]) {
+    let c = *c;
+    let mut z = fractal.initial_z(c);
+    let mut inside = if fast_interior {
+        let inside = fractal.known_inside(&c);
+        z.real = inside.blend(f64x4::splat(f64::INFINITY), z.real);
+        inside
+    } else {
+        f64x4::splat(0.0)
+    };
+    let mut count = f64x4::splat(0.0);
+    let mut norm = f64x4::splat(0.0);
+    let threshold = f64x4::splat(threshold);
+    let epsilon = f64x4::splat(PERIODICITY_EPSILON);
+    // all lanes set, escaped lane never comes back below the threshold
+    let mut alive = count.simd_eq(count);
+    let mut reference = z;
+    let mut interval = PERIODICITY_INTERVAL;
+    let mut steps = 0;
+
+    for _ in 0..iters {
+        let norm_sqr = z.norm_sqr();
+        let mask = norm_sqr.simd_le(threshold);
+        norm = (alive & !mask).blend(norm_sqr, norm);
+        alive = mask;
+
+        if !mask.any() {
+            break;
+        }
+
+        count += mask.blend(f64x4::splat(1.0), f64x4::splat(0.0));
+
+        z = fractal.step(z, c);
+
+        if fast_interior {
+            let dr = z.real - reference.real;
+            let di = z.imag - reference.imag;
+            let periodic = (dr * dr + di * di).simd_lt(epsilon) & mask;
+            if periodic.any() {
+                inside |= periodic;
+                z.real = periodic.blend(f64x4::splat(f64::INFINITY), z.real);
+            }
+            steps += 1;
+            if steps == interval {
+                reference = z;
+                steps = 0;
+                interval *= 2;
+            }
+        }
+    }
+
+    count = inside.blend(f64x4::splat(iters as f64), count);
+
+    let arr: [f64; 4] = count.into();
+    (
+        [arr[0] as u64, arr[1] as u64, arr[2] as u64, arr[3] as u64],
+        norm.into(),
+    )
+}
+
+/// Escape counts of 4 points of the Mandelbrot set, `fast_interior` skips the iterations
+/// of the points inside the main cardioid and the period-2 bulb
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_at_vec(
+    c: &Complex4,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> [u64; 4] {
+    fractal_at_vec(&Mandelbrot, c, iters, threshold, fast_interior)
+}
+
+/// Same as `mandelbrot_at_vec` but for 8 lanes, a single register with AVX-512
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_at_vec8(
+    c: &Complex8,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> [u64; 8] {
+    let mut z = *c;
+    // points of the main body are pushed far beyond the threshold to leave the loop
+    // at once and get their count restored afterwards
+    let inside = if fast_interior {
+        let inside = c.in_main_body();
+        z.real = inside.blend(f64x8::splat(f64::INFINITY), z.real);
+        inside
+    } else {
+        f64x8::splat(0.0)
+    };
+    let mut count = f64x8::splat(0.0);
+    let threshold = f64x8::splat(threshold);
+
+    for _ in 0..iters {
+        let rr = z.real * z.real;
+        let ii = z.imag * z.imag;
+
+        let mask = (rr + ii).simd_le(threshold);
+
+        if !mask.any() {
+            break;
+        }
+
+        count += mask.blend(f64x8::splat(1.0), f64x8::splat(0.0));
+
+        let ri = z.real * z.imag;
+        z.real = rr - ii + c.real;
+        z.imag = ri + ri + c.imag;
+    }
+
+    count = inside.blend(f64x8::splat(iters as f64), count);
+
+    let arr: [f64; 8] = count.into();
+    arr.map(|x| x as u64)
+}
+
+/// Same as `mandelbrot_at_vec8` but in single precision, which fits 8 lanes into AVX2
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_at_vec_f32(
+    c: &Complex8f32,
+    iters: usize,
+    threshold: f32,
+    fast_interior: bool,
+) -> [u64; 8] {
+    let mut z = *c;
+    // points of the main body are pushed far beyond the threshold to leave the loop
+    // at once and get their count restored afterwards
+    let inside = if fast_interior {
+        let inside = c.in_main_body();
+        z.real = inside.blend(f32x8::splat(f32::INFINITY), z.real);
+        inside
+    } else {
+        f32x8::splat(0.0)
+    };
+    let mut count = f32x8::splat(0.0);
+    let threshold = f32x8::splat(threshold);
+
+    for _ in 0..iters {
+        let rr = z.real * z.real;
+        let ii = z.imag * z.imag;
+
+        let mask = (rr + ii).simd_le(threshold);
+
+        if !mask.any() {
+            break;
+        }
+
+        count += mask.blend(f32x8::splat(1.0), f32x8::splat(0.0));
+
+        let ri = z.real * z.imag;
+        z.real = rr - ii + c.real;
+        z.imag = ri + ri + c.imag;
+    }
+
+    count = inside.blend(f32x8::splat(iters as f32), count);
+
+    let arr: [f32; 8] = count.into();
+    arr.map(|x| x as u64)
+}
+
+/// Same as `mandelbrot_at_vec` but also returns squared magnitudes of `z` at the moment
+/// of escape
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_norm_at_vec(
+    c: &Complex4,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> ([u64; 4], [f64; 4]) {
+    fractal_norm_at_vec(&Mandelbrot, c, iters, threshold, fast_interior)
+}
+
+/// Squared distance under which an orbit is considered to have returned to the reference
+/// value, small enough to leave the slowly escaping points near the boundary alone
+pub const PERIODICITY_EPSILON: f64 = 1e-20;
+
+/// Number of iterations before the first update of the reference value of the
+/// periodicity check, the interval doubles after each update to catch long cycles too
+pub const PERIODICITY_INTERVAL: usize = 16;
+
+/// Same as `mandelbrot_norm_at_vec` but also returns squared magnitudes of the derivative
+/// `dz = 2 * z * dz + 1` at the moment of escape, the derivative costs 4 more
+/// multiplications per iteration, about half again as much as the plain loop
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_derivative_at_vec(
+    c: &Complex4,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> ([u64; 4], [f64; 4], [f64; 4]) {
+    let mut z = *c;
+    let mut dz = Complex4 {
+        real: f64x4::splat(1.0),
+        imag: f64x4::splat(0.0),
+    };
+    let inside = if fast_interior {
+        let inside = c.in_main_body();
+        z.real = inside.blend(f64x4::splat(f64::INFINITY), z.real);
+        inside
+    } else {
+        f64x4::splat(0.0)
+    };
+    let mut count = f64x4::splat(0.0);
+    let mut norm = f64x4::splat(0.0);
+    let mut dz_norm = f64x4::splat(0.0);
+    let threshold = f64x4::splat(threshold);
+    let mut alive = count.simd_eq(count);
+
+    for _ in 0..iters {
+        let norm_sqr = z.norm_sqr();
+        let mask = norm_sqr.simd_le(threshold);
+        let escaping = alive & !mask;
+        norm = escaping.blend(norm_sqr, norm);
+        dz_norm = escaping.blend(dz.norm_sqr(), dz_norm);
+        alive = mask;
+
+        if !mask.any() {
+            break;
+        }
+
+        count += mask.blend(f64x4::splat(1.0), f64x4::splat(0.0));
+
+        let dr = z.real * dz.real - z.imag * dz.imag;
+        let di = z.real * dz.imag + z.imag * dz.real;
+        dz.real = dr + dr + f64x4::splat(1.0);
+        dz.imag = di + di;
+
+        z = z.square() + *c;
+    }
+
+    count = inside.blend(f64x4::splat(iters as f64), count);
+
+    let arr: [f64; 4] = count.into();
+    (
+        [arr[0] as u64, arr[1] as u64, arr[2] as u64, arr[3] as u64],
+        norm.into(),
+        dz_norm.into(),
+    )
+}
+
+/// Same as `mandelbrot_at_vec` but also returns the closest approaches of the orbits
+/// to the trap before the escape
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_trap_at_vec(
+    c: &Complex4,
+    iters: usize,
+    threshold: f64,
+    trap: Trap,
+) -> ([u64; 4], [f64; 4]) {
+    // a loop per trap keeps the branch out of the iterations
+    match trap {
+        Trap::Point => trap_at_vec(c, iters, threshold, |z| z.norm_sqr().sqrt()),
+        Trap::RealAxis => trap_at_vec(c, iters, threshold, |z| z.imag.abs()),
+        Trap::ImagAxis => trap_at_vec(c, iters, threshold, |z| z.real.abs()),
+    }
+}
+
+#[inline(always)]
+fn trap_at_vec<D>(c: &Complex4, iters: usize, threshold: f64, distance: D) -> ([u64; 4], [f64; 4])
+where
+    D: Fn(&Complex4) -> f64x4,
+{
+    let mut z = *c;
+    let mut count = f64x4::splat(0.0);
+    let mut closest = f64x4::splat(f64::INFINITY);
+    let threshold = f64x4::splat(threshold);
+
+    for _ in 0..iters {
+        let mask = z.norm_sqr().simd_le(threshold);
+
+        if !mask.any() {
+            break;
+        }
+
+        count += mask.blend(f64x4::splat(1.0), f64x4::splat(0.0));
+        closest = mask.blend(closest.min(distance(&z)), closest);
+
+        z = z.square() + *c;
+    }
+
+    let arr: [f64; 4] = count.into();
+    (
+        [arr[0] as u64, arr[1] as u64, arr[2] as u64, arr[3] as u64],
+        closest.into(),
+    )
+}
+
+/// Unevaluated sum `hi + lo` of two f64 with `lo` below half an ulp of `hi`, about 106 bits
+/// of mantissa from the error free transformations of the f64 sums and products
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    pub fn new(x: f64) -> DoubleDouble {
+        DoubleDouble { hi: x, lo: 0.0 }
+    }
+
+    /// Sum and its rounding error
+    fn two_sum(a: f64, b: f64) -> DoubleDouble {
+        let hi = a + b;
+        let b_part = hi - a;
+        let lo = (a - (hi - b_part)) + (b - b_part);
+        DoubleDouble { hi, lo }
+    }
+
+    /// Same as `two_sum` for |a| >= |b|
+    fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+        let hi = a + b;
+        DoubleDouble {
+            hi,
+            lo: b - (hi - a),
+        }
+    }
+
+    /// Product and its rounding error, exact by the fused multiply-add
+    fn two_prod(a: f64, b: f64) -> DoubleDouble {
+        let hi = a * b;
+        DoubleDouble {
+            hi,
+            lo: a.mul_add(b, -hi),
+        }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, other: DoubleDouble) -> DoubleDouble {
+        let s = DoubleDouble::two_sum(self.hi, other.hi);
+        let t = DoubleDouble::two_sum(self.lo, other.lo);
+        let s = DoubleDouble::quick_two_sum(s.hi, s.lo + t.hi);
+        DoubleDouble::quick_two_sum(s.hi, s.lo + t.lo)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn sub(self, other: DoubleDouble) -> DoubleDouble {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, other: DoubleDouble) -> DoubleDouble {
+        let p = DoubleDouble::two_prod(self.hi, other.hi);
+        let lo = p.lo + (self.hi * other.lo + self.lo * other.hi);
+        DoubleDouble::quick_two_sum(p.hi, lo)
+    }
+}
+
+/// Orbit z_0 = 0, z_1 = c, ... of the point `cx + cy i` iterated in double-double precision
+/// and rounded to f64, up to the first escaping iterate or `iters + 1` iterates
+pub fn reference_orbit(cx: f64, cy: f64, iters: usize, threshold: f64) -> Vec<Complex<f64>> {
+    let (cx, cy) = (DoubleDouble::new(cx), DoubleDouble::new(cy));
+    let (mut re, mut im) = (DoubleDouble::default(), DoubleDouble::default());
+    let mut orbit = vec![Complex::new(0.0, 0.0)];
+
+    for _ in 0..=iters {
+        let re2 = re * re;
+        let im2 = im * im;
+        let reim = re * im;
+        (re, im) = (re2 - im2 + cx, reim + reim + cy);
+        let z = Complex::new(re.hi, im.hi);
+        orbit.push(z);
+        if z.norm_sqr() > threshold {
+            break;
+        }
+    }
+
+    orbit
+}
+
+/// Escape count and final squared magnitude of the point `dc` away from the point of
+/// the `reference` orbit, the same as `mandelbrot_norm_at_point` of the point itself.
+///
+/// The pixel follows z_n = Z_m + d_n, d_(n+1) = (2 Z_m + d_n) d_n + dc of the reference
+/// orbit Z; once |z| drops below |d| or the reference ends, z itself becomes the difference
+/// from Z_0 = 0 (rebasing), which keeps d small next to Z so the glitches of the plain
+/// perturbation don't happen
+pub fn mandelbrot_delta_at_point(
+    reference: &[Complex<f64>],
+    dc: Complex<f64>,
+    iters: usize,
+    threshold: f64,
+) -> (u64, f64) {
+    let last = reference.len() - 1;
+    // z_1 = c is reference[1] + dc
+    let mut m = 1;
+    let mut d = dc;
+
+    for i in 0..iters {
+        let z = reference[m] + d;
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            return (i as u64, norm);
+        }
+        if norm < d.norm_sqr() || m == last {
+            d = z;
+            m = 0;
+        }
+        d = (reference[m] * 2.0 + d) * d + dc;
+        m += 1;
+    }
+
+    let z = reference[m] + d;
+    (iters as u64, z.norm_sqr())
+}
+
+/// Same as `mandelbrot_norm_at_point` in double-double precision, `fast_interior` skips
+/// the main body only: the periodicity check would take the slowly escaping points
+/// near the tiny copies of the set of the deep zooms for points of the set
+pub fn mandelbrot_dd_at_point(
+    cx: DoubleDouble,
+    cy: DoubleDouble,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> (u64, f64) {
+    if fast_interior && in_main_body(cx.hi, cy.hi) {
+        return (iters as u64, 0.0);
+    }
+
+    // z_1 = c, the count is the number of iterates before the escaping one
+    let (mut re, mut im) = (cx, cy);
+    for i in 0..iters {
+        let norm = re.hi * re.hi + im.hi * im.hi;
+        if norm > threshold {
+            return (i as u64, norm);
+        }
+        let re2 = re * re;
+        let im2 = im * im;
+        let reim = re * im;
+        (re, im) = (re2 - im2 + cx, reim + reim + cy);
+    }
+
+    (iters as u64, re.hi * re.hi + im.hi * im.hi)
+}
+
+/// Orbit z_0 = 0, z_1 = c, ... of the point `cx + cy i`, the same iterations as
+/// `mandelbrot_at_point`, up to the first iterate beyond the threshold and at most `iters`
+/// iterates
+pub fn mandelbrot_orbit(cx: f64, cy: f64, iters: usize, threshold: f64) -> Vec<Complex<f64>> {
+    let c = Complex::new(cx, cy);
+    let mut z = Complex::new(0.0, 0.0);
+    let mut orbit = Vec::with_capacity(iters.min(1024));
+
+    while orbit.len() < iters {
+        orbit.push(z);
+        if z.norm_sqr() > threshold {
+            break;
+        }
+        z = z * z + c;
+    }
+
+    orbit
+}
+
+/// Same as `mandelbrot_at_point` along with the final z, the first one beyond the threshold
+/// for the escaping points
+pub fn mandelbrot_z_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> (u64, Complex<f64>) {
+    let c = Complex::new(cx, cy);
+    if fast_interior && in_main_body(cx, cy) {
+        return (iters as u64, c);
+    }
+
+    // z_1 = c, the count is the number of iterates before the escaping one
+    let mut z = c;
+    let mut reference = z;
+    let mut interval = PERIODICITY_INTERVAL;
+    let mut steps = 0;
+
+    for i in 0..iters {
+        if z.norm_sqr() > threshold {
+            return (i as u64, z);
+        }
+        z = z * z + c;
+
+        if fast_interior && (z - reference).norm_sqr() < PERIODICITY_EPSILON {
+            return (iters as u64, z);
+        }
+        steps += 1;
+        if steps == interval {
+            reference = z;
+            steps = 0;
+            interval *= 2;
+        }
+    }
+
+    (iters as u64, z)
+}
+
+/// Same as `mandelbrot_z_at_point` along with the derivative `dz = 2 * z * dz + 1` of the
+/// final z with respect to c
+pub fn mandelbrot_dz_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> (u64, Complex<f64>, Complex<f64>) {
+    let c = Complex::new(cx, cy);
+    let one = Complex::new(1.0, 0.0);
+    if fast_interior && in_main_body(cx, cy) {
+        return (iters as u64, c, one);
+    }
+
+    let mut z = c;
+    let mut dz = one;
+    let mut reference = z;
+    let mut interval = PERIODICITY_INTERVAL;
+    let mut steps = 0;
+
+    for i in 0..iters {
+        if z.norm_sqr() > threshold {
+            return (i as u64, z, dz);
+        }
+        dz = 2.0 * z * dz + one;
+        z = z * z + c;
+
+        if fast_interior && (z - reference).norm_sqr() < PERIODICITY_EPSILON {
+            return (iters as u64, z, dz);
+        }
+        steps += 1;
+        if steps == interval {
+            reference = z;
+            steps = 0;
+            interval *= 2;
+        }
+    }
+
+    (iters as u64, z, dz)
+}
+
+/// Escape count of a single point, `iters` for the points of the set, `fast_interior`
+/// also skips the main body and stops at once on periodic orbits
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> u64 {
+    if !fast_interior {
+        return mandelbrot_norm_at_point(cx, cy, iters, 2, threshold).0;
+    }
+    if in_main_body(cx, cy) {
+        return iters as u64;
+    }
+
+    // z_1 = c, the count is the number of iterates before the escaping one,
+    // the same as in the SIMD kernels
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+    let mut reference = z;
+    let mut interval = PERIODICITY_INTERVAL;
+    let mut steps = 0;
+
+    for i in 0..iters {
+        if z.norm_sqr() > threshold {
+            return i as u64;
+        }
+        z = z * z + c;
+
+        if (z - reference).norm_sqr() < PERIODICITY_EPSILON {
+            return iters as u64;
+        }
+        steps += 1;
+        if steps == interval {
+            reference = z;
+            steps = 0;
+            interval *= 2;
+        }
+    }
+
+    iters as u64
+}
+
+/// Interior metric of a point of the set in the range [0, 1], the orbit runs for all
+/// the `iters` iterations (or until its period is found)
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_interior_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    interior: Interior,
+) -> f64 {
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+
+    match interior {
+        Interior::AvgMagnitude => {
+            let mut sum = 0.0;
+            for _ in 0..iters {
+                if z.norm_sqr() > threshold {
+                    break;
+                }
+                sum += z.norm();
+                z = z * z + c;
+            }
+            // the orbits of the set stay within the radius 2
+            (sum / iters.max(1) as f64 / 2.0).min(1.0)
+        }
+        // period 1 at the start of the palette, the long ones near its end
+        Interior::Period => match mandelbrot_period_at_point(cx, cy, iters) {
+            Some(period) => 1.0 - 1.0 / period as f64,
+            None => 1.0,
+        },
+    }
+}
+
+/// Period of the cycle the orbit of a point of the set settles into, 1 in the main
+/// cardioid and 2 in the period-2 bulb, `None` when no cycle shows within `iters`
+/// iterations. The orbit counts as back on the reference iterate once their squared
+/// distance drops below `PERIODICITY_EPSILON` (a distance of 1e-10), the reference moves
+/// ahead with doubling intervals to catch the long cycles too; the orbits near the boundary
+/// of a bulb converge slowly and run out of iterations first
+pub fn mandelbrot_period_at_point(cx: f64, cy: f64, iters: usize) -> Option<usize> {
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+    let mut reference = z;
+    let mut interval = PERIODICITY_INTERVAL;
+    let mut steps = 0;
+
+    for _ in 0..iters {
+        z = z * z + c;
+        steps += 1;
+        if (z - reference).norm_sqr() < PERIODICITY_EPSILON {
+            return Some(steps);
+        }
+        if steps == interval {
+            reference = z;
+            steps = 0;
+            interval *= 2;
+        }
+    }
+
+    None
+}
+
+/// Returns escape count and squared magnitude of `z` at the moment of escape
+/// of the recurrence z = z^power + c
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_norm_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    power: u32,
+    threshold: f64,
+) -> (u64, f64) {
+    // z_1 = c, the count is the number of iterates before the escaping one,
+    // the same as in the SIMD kernels
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+
+    for i in 0..iters {
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            return (i as u64, norm);
+        }
+        z = if power == 2 { z * z } else { z.powu(power) } + c;
+    }
+
+    (iters as u64, z.norm_sqr())
+}
+
+/// Returns escape count and the closest approach of the orbit to the trap before the escape
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_trap_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    trap: Trap,
+) -> (u64, f64) {
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+    let mut closest = f64::INFINITY;
+
+    for i in 0..iters {
+        if z.norm_sqr() > threshold {
+            return (i as u64, closest);
+        }
+        let distance = match trap {
+            Trap::Point => z.norm(),
+            Trap::RealAxis => z.im.abs(),
+            Trap::ImagAxis => z.re.abs(),
+        };
+        closest = closest.min(distance);
+        z = z * z + c;
+    }
+
+    (iters as u64, closest)
+}
+
+/// Average of 0.5 + 0.5 sin(density * arg z) over the orbit up to the escaping iterate,
+/// blended with the average without the last iterate by the fractional escape count
+/// to get rid of the bands, -1 for the points of the set
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_stripe_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    density: f64,
+    fast_interior: bool,
+) -> f64 {
+    if fast_interior && in_main_body(cx, cy) {
+        return -1.0;
+    }
+
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+    let mut sum = 0.0;
+
+    for i in 0..iters {
+        let stripe = 0.5 + 0.5 * (density * z.arg()).sin();
+        sum += stripe;
+
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            let avg = sum / (i + 1) as f64;
+            if i == 0 {
+                return avg;
+            }
+            let prev = (sum - stripe) / i as f64;
+            // 1 right above the threshold and 0 at its square
+            let frac = 1.0 - (norm.ln() / threshold.ln()).log2();
+            return prev + (avg - prev) * frac.clamp(0.0, 1.0);
+        }
+        z = z * z + c;
+    }
+
+    -1.0
+}
+
+/// Smallest gap between the bounds of the triangle inequality averaged by the TIA coloring
+const TIA_EPSILON: f64 = 1e-12;
+
+/// Average over the orbit of (|z^2 + c| - m) / (M - m), where m = ||z^2| - |c|| and
+/// M = |z^2| + |c| bound |z^2 + c| by the triangle inequality, blended with the average
+/// without the last step by the fractional escape count like the stripe averages,
+/// -1 for the points of the set.
+///
+/// The bounds meet (M - m = 2 min(|z^2|, |c|)) when c or z^2 is close to 0, such steps
+/// are left out of the average, 0 for the orbits escaping before any step counts.
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_tia_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+    fast_interior: bool,
+) -> f64 {
+    if fast_interior && in_main_body(cx, cy) {
+        return -1.0;
+    }
+
+    let c = Complex::new(cx, cy);
+    let c_abs = c.norm();
+    let mut z = c;
+    let mut sum = 0.0;
+    let mut terms = 0usize;
+    // ratio of the last step, None for a left out one
+    let mut last = None;
+
+    for _ in 0..iters {
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            if terms == 0 {
+                return 0.0;
+            }
+            let avg = sum / terms as f64;
+            let prev = match last {
+                Some(ratio) if terms > 1 => (sum - ratio) / (terms - 1) as f64,
+                _ => avg,
+            };
+            // 1 right above the threshold and 0 at its square
+            let frac = 1.0 - (norm.ln() / threshold.ln()).log2();
+            return (prev + (avg - prev) * frac.clamp(0.0, 1.0)).clamp(0.0, 1.0);
+        }
+
+        let z2 = z * z;
+        let z2_abs = z2.norm();
+        z = z2 + c;
+        let (low, high) = ((z2_abs - c_abs).abs(), z2_abs + c_abs);
+        last = (high - low > TIA_EPSILON).then(|| (z.norm() - low) / (high - low));
+        if let Some(ratio) = last {
+            sum += ratio;
+            terms += 1;
+        }
+    }
+
+    -1.0
+}
+
+/// Returns escape count and squared magnitudes of `z` and of its derivative `dz`
+/// at the moment of escape, see `mandelbrot_derivative_at_vec`
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn mandelbrot_derivative_at_point(
+    cx: f64,
+    cy: f64,
+    iters: usize,
+    threshold: f64,
+) -> (u64, f64, f64) {
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+    let mut dz = Complex::new(1.0, 0.0);
+
+    for i in 0..iters {
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            return (i as u64, norm, dz.norm_sqr());
+        }
+        dz = 2.0 * z * dz + 1.0;
+        z = z * z + c;
+    }
+
+    (iters as u64, z.norm_sqr(), dz.norm_sqr())
+}
+
+/// Same as `mandelbrot_norm_at_vec` but for the recurrence z = z^power + c
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn multibrot_at_vec(
+    c: &Complex4,
+    iters: usize,
+    power: u32,
+    threshold: f64,
+) -> ([u64; 4], [f64; 4]) {
+    fractal_norm_at_vec(&Multibrot(power), c, iters, threshold, false)
+}
+
+/// Same as `mandelbrot_norm_at_vec` but for the Burning Ship recurrence
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn burning_ship_at_vec(c: &Complex4, iters: usize, threshold: f64) -> ([u64; 4], [f64; 4]) {
+    fractal_norm_at_vec(&BurningShip, c, iters, threshold, false)
+}
+
+/// Same as `mandelbrot_norm_at_point` but for the Burning Ship recurrence
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn burning_ship_at_point(cx: f64, cy: f64, iters: usize, threshold: f64) -> (u64, f64) {
+    // z_1 = c, the count is the number of iterates before the escaping one,
+    // the same as in the SIMD kernels
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+
+    for i in 0..iters {
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            return (i as u64, norm);
+        }
+        z = Complex::new(z.re.abs(), z.im.abs());
+        z = z * z + c;
+    }
+
+    (iters as u64, z.norm_sqr())
+}
+
+/// Same as `mandelbrot_norm_at_vec` but for the Tricorn recurrence
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn tricorn_at_vec(c: &Complex4, iters: usize, threshold: f64) -> ([u64; 4], [f64; 4]) {
+    fractal_norm_at_vec(&Tricorn, c, iters, threshold, false)
+}
+
+/// Same as `mandelbrot_norm_at_point` but for the Tricorn recurrence
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn tricorn_at_point(cx: f64, cy: f64, iters: usize, threshold: f64) -> (u64, f64) {
+    // z_1 = c, the count is the number of iterates before the escaping one,
+    // the same as in the SIMD kernels
+    let c = Complex::new(cx, cy);
+    let mut z = c;
+
+    for i in 0..iters {
+        let norm = z.norm_sqr();
+        if norm > threshold {
+            return (i as u64, norm);
+        }
+        z = z.conj() * z.conj() + c;
+    }
+
+    (iters as u64, z.norm_sqr())
+}
+
+fn output_format(path: &Path) -> Result<ImageFormat, MandelbrotError> {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "png" => Ok(ImageFormat::Png),
+        "jpg" | "jpeg" => Ok(ImageFormat::Jpeg),
+        "bmp" => Ok(ImageFormat::Bmp),
+        "tif" | "tiff" => Ok(ImageFormat::Tiff),
+        "ppm" => Ok(ImageFormat::Pnm),
+        "webp" => Ok(ImageFormat::WebP),
+        _ => Err(MandelbrotError::UnsupportedFormat(ext)),
+    }
+}
+
+/// Quality of the JPEG pictures saved without one
+pub const JPEG_QUALITY: u8 = 75;
+
+/// Saves the picture in the format inferred from the extension of `path`, `quality` in
+/// range [0, 100] applies to JPEG only (`JPEG_QUALITY` if None), WebP is encoded lossless
+/// only and a quality given for it is an error
+pub fn save_image(
+    img: DynamicImage,
+    path: &Path,
+    quality: Option<u8>,
+) -> Result<(), MandelbrotError> {
+    let format = output_format(path)?;
+    if format == ImageFormat::WebP && quality.is_some() {
+        return Err(MandelbrotError::UnsupportedQuality("webp".to_string()));
+    }
+    if format == ImageFormat::Png {
+        let (width, height) = (img.width(), img.height());
+        return write_png(std::iter::once(Ok(img)), width, height, path, None);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match format {
+        ImageFormat::Jpeg => {
+            let quality = quality.unwrap_or(JPEG_QUALITY);
+            img.write_with_encoder(JpegEncoder::new_with_quality(writer, quality))?
+        }
+        // PPM is the RGB flavour of PNM, so grayscale has to be expanded first
+        ImageFormat::Pnm => DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(
+            PnmEncoder::new(writer).with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary)),
+        )?,
+        // the image crate encodes lossless WebP only
+        ImageFormat::WebP => img.write_with_encoder(WebPEncoder::new_lossless(writer))?,
+        _ => img.write_to(&mut writer, format)?,
+    }
+
+    Ok(())
+}
+
+/// Writes the picture as binary PNM, P5 for grayscale and P6 for the other pictures,
+/// 16-bit ones keep their depth with the samples in big-endian order
+pub fn write_ppm<W: Write>(img: &DynamicImage, writer: W) -> Result<(), MandelbrotError> {
+    let mut writer = BufWriter::new(writer);
+    let (width, height) = (img.width(), img.height());
+    match img {
+        DynamicImage::ImageLuma8(im) => {
+            write!(writer, "P5\n{} {}\n255\n", width, height)?;
+            writer.write_all(im.as_raw())?;
+        }
+        DynamicImage::ImageLuma16(im) => {
+            write!(writer, "P5\n{} {}\n65535\n", width, height)?;
+            for v in im.as_raw() {
+                writer.write_all(&v.to_be_bytes())?;
+            }
+        }
+        _ => {
+            write!(writer, "P6\n{} {}\n255\n", width, height)?;
+            writer.write_all(img.to_rgb8().as_raw())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Saves the picture of `width` x `height` pixels colored band by band, `bands` yields
+/// them from the top one, so only a single band of escape values has to be kept around,
+/// PNG is encoded as the bands come and the other formats get them assembled first,
+/// `text` is the keyword and the text of the PNG text chunk
+pub fn save_image_bands<I>(
+    bands: I,
+    width: u32,
+    height: u32,
+    path: &Path,
+    quality: Option<u8>,
+    text: Option<(&str, &str)>,
+) -> Result<(), MandelbrotError>
+where
+    I: Iterator<Item = Result<DynamicImage, MandelbrotError>>,
+{
+    if output_format(path)? == ImageFormat::Png {
+        return write_png(bands, width, height, path, text);
+    }
+
+    let mut img: Option<DynamicImage> = None;
+    let mut y = 0;
+    for band in bands {
+        let band = band?;
+        let img = img.get_or_insert_with(|| DynamicImage::new(width, height, band.color()));
+        img.copy_from(&band, 0, y)?;
+        y += band.height();
+    }
+    let img = img.ok_or(MandelbrotError::InvalidBufferSize {
+        expected: width as usize * height as usize,
+        got: 0,
+    })?;
+
+    match text {
+        Some((keyword, text)) => save_png_with_text(&img, path, keyword, text),
+        None => save_image(img, path, quality),
+    }
+}
+
+/// Distance to the boundary of the set in pixels mapped onto the end of the palette,
+/// the points closer to the boundary get the darker colors
+pub const DISTANCE_RANGE: f64 = 4.0;
+
+/// Saves the picture as PNG with the text chunk `keyword` holding `text`
+pub fn save_png_with_text(
+    img: &DynamicImage,
+    path: &Path,
+    keyword: &str,
+    text: &str,
+) -> Result<(), MandelbrotError> {
+    let bands = std::iter::once(Ok(img.clone()));
+    write_png(
+        bands,
+        img.width(),
+        img.height(),
+        path,
+        Some((keyword, text)),
+    )
+}
+
+/// Encodes the bands of the picture into PNG one after another, every PNG output goes
+/// through it, so the tiled and the whole pictures give the same files
+fn write_png<I>(
+    mut bands: I,
+    width: u32,
+    height: u32,
+    path: &Path,
+    text: Option<(&str, &str)>,
+) -> Result<(), MandelbrotError>
+where
+    I: Iterator<Item = Result<DynamicImage, MandelbrotError>>,
+{
+    let expected = width as usize * height as usize;
+    let invalid_size = |got| MandelbrotError::InvalidBufferSize { expected, got };
+    let png_error = |e: png::EncodingError| {
+        MandelbrotError::Image(ImageError::Encoding(EncodingError::new(
+            ImageFormat::Png.into(),
+            e,
+        )))
+    };
+
+    let Some(first) = bands.next().transpose()? else {
+        return Err(invalid_size(0));
+    };
+    let (color, depth) = match first {
+        DynamicImage::ImageLuma8(_) => (png::ColorType::Grayscale, png::BitDepth::Eight),
+        DynamicImage::ImageLuma16(_) => (png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        _ => (png::ColorType::Rgb, png::BitDepth::Eight),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    if let Some((keyword, text)) = text {
+        encoder
+            .add_text_chunk(keyword.to_string(), text.to_string())
+            .map_err(png_error)?;
+    }
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    let mut stream = writer.stream_writer().map_err(png_error)?;
+
+    let mut pixels = 0;
+    for band in std::iter::once(Ok(first)).chain(bands) {
+        let band = band?;
+        pixels += band.width() as usize * band.height() as usize;
+        if band.width() != width || pixels > expected {
+            return Err(invalid_size(pixels));
+        }
+        // PNG keeps the samples in big endian order
+        let data = match band {
+            DynamicImage::ImageLuma8(im) => im.into_raw(),
+            DynamicImage::ImageLuma16(im) => im.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            im => im.to_rgb8().into_raw(),
+        };
+        stream.write_all(&data)?;
+    }
+    if pixels != expected {
+        return Err(invalid_size(pixels));
+    }
+    stream.finish().map_err(png_error)
+}
+
+/// Cumulative distribution of escape values over the points outside the set,
+/// indexed by the integer part of the value, stretched from 0 at the lowest value to 1
+/// at the highest one so that every view spans the whole palette
+fn histogram_cdf(values: impl Iterator<Item = f64>, iters: usize) -> Vec<f32> {
+    let mut hist = vec![0u64; iters.max(1)];
+    for x in values.filter(|&x| x != iters as f64 && x.is_finite()) {
+        hist[x as usize] += 1;
+    }
+
+    let total = hist.iter().sum::<u64>();
+    let lowest = hist.iter().copied().find(|&n| n > 0).unwrap_or(0);
+    let span = (total - lowest).max(1) as f32;
+    let mut sum = 0;
+    hist.iter()
+        .map(|&n| {
+            sum += n;
+            sum.saturating_sub(lowest) as f32 / span
+        })
+        .collect()
+}
+
+/// Interior metrics of the points of the set among the escape values of the view, NaN
+/// for the other points, costs all the `iters` iterations of every point of the set
+/// on a single lane, the fast interior skips them in the escape values
+pub fn calc_interior(
+    escaped: &Escaped,
+    view: &View,
+    iters: usize,
+    threshold: f64,
+    interior: Interior,
+) -> Vec<f64> {
+    let in_set = |x: f64| match escaped {
+        Escaped::Distance(_) => x == 0.0,
+        Escaped::Trap(_) => false,
+        _ => x == iters as f64,
+    };
+
+    (0..escaped.len())
+        .into_par_iter()
+        .map(|i| {
+            if !in_set(escaped.get(i)) {
+                return f64::NAN;
+            }
+            let width = view.columns.1 - view.columns.0;
+            let (x, y) = (view.columns.0 + i % width, view.rows.0 + i / width);
+            match view.pixel_to_complex(x, y) {
+                Some((cx, cy)) => mandelbrot_interior_at_point(cx, cy, iters, threshold, interior),
+                None => f64::NAN,
+            }
+        })
+        .collect()
+}
+
+/// Colors the pixels of the picture with the interior metrics of `calc_interior`
+/// taken as the positions in the palette, the NaN ones are left alone, inverted along
+/// with the rest of the picture
+pub fn paint_interior(img: &mut DynamicImage, interior: &[f64], options: DrawOptions) {
+    let color = options.color_fn();
+    let points = interior.iter().enumerate().filter(|(_, t)| !t.is_nan());
+    let invert = |v: u16, max: u16| if options.invert { max - v } else { v };
+
+    match img {
+        DynamicImage::ImageLuma8(im) => {
+            for (i, &t) in points {
+                im.as_mut()[i] = invert((t * 255.0) as u16, 255) as u8;
+            }
+        }
+        DynamicImage::ImageLuma16(im) => {
+            for (i, &t) in points {
+                im.as_mut()[i] = invert((t * 65535.0) as u16, 65535);
+            }
+        }
+        DynamicImage::ImageRgb8(im) => {
+            for (i, &t) in points {
+                let rgb = color(t as f32).0.map(|v| invert(v as u16, 255) as u8);
+                im.as_mut()[3 * i..3 * i + 3].copy_from_slice(&rgb);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Draws the `orbit` over the picture of the view as dots joined by lines, going from blue
+/// at the first point to red at the last one, the parts off the picture are left out
+pub fn draw_orbit(img: &mut RgbImage, view: &View, orbit: &[Complex<f64>]) {
+    let (dx, dy) = view.pixel_size();
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let pixel = |z: &Complex<f64>| {
+        let (re, im) = view.rotate(z.re, z.im, -view.rotation);
+        ((re - view.x_min) / dx, (im - view.y_min) / dy)
+    };
+    let color = |i: usize| {
+        let t = i as f32 / (orbit.len() - 1).max(1) as f32;
+        Rgb(hsv((1.0 - t) * 2.0 / 3.0, 1.0, 1.0).map(|v| v as u8))
+    };
+    let mut plot = |x: f64, y: f64, color: Rgb<u8>| {
+        let (x, y) = (x.round(), y.round());
+        if x >= 0.0 && y >= 0.0 && x < width as f64 && y < height as f64 {
+            img.put_pixel(x as u32, y as u32, color);
+        }
+    };
+
+    for (i, pair) in orbit.windows(2).enumerate() {
+        let ((x0, y0), (x1, y1)) = (pixel(&pair[0]), pixel(&pair[1]));
+        // a step per pixel along the longer axis, the escaping point may lie far away
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil();
+        let steps = steps.min((2 * (width + height)) as f64).max(1.0) as usize;
+        for k in 0..=steps {
+            let t = k as f64 / steps as f64;
+            plot(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t, color(i + 1));
+        }
+    }
+    for (i, z) in orbit.iter().enumerate() {
+        let (x, y) = pixel(z);
+        for (ox, oy) in (-1..=1).flat_map(|ox| (-1..=1).map(move |oy| (ox, oy))) {
+            plot(x + ox as f64, y + oy as f64, color(i));
+        }
+    }
+}
+
+/// Maps the Buddhabrot visits of a `width` x `height` picture onto grayscale, the square
+/// root of the visits relative to the most visited pixel lifts the faint orbits
+pub fn buddhabrot_image(
+    bins: &[u32],
+    width: u32,
+    height: u32,
+    bit_depth: BitDepth,
+) -> Result<DynamicImage, MandelbrotError> {
+    let most = bins.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let level = |n: u32| (n as f64 / most).sqrt();
+
+    let invalid_size = || MandelbrotError::InvalidBufferSize {
+        expected: width as usize * height as usize,
+        got: bins.len(),
+    };
+
+    let img = match bit_depth {
+        BitDepth::Eight => {
+            let raw = bins.iter().map(|&n| (level(n) * 255.0) as u8).collect();
+            DynamicImage::ImageLuma8(
+                GrayImage::from_raw(width, height, raw).ok_or_else(invalid_size)?,
+            )
+        }
+        BitDepth::Sixteen => {
+            let raw = bins.iter().map(|&n| (level(n) * 65535.0) as u16).collect();
+            DynamicImage::ImageLuma16(
+                ImageBuffer::from_raw(width, height, raw).ok_or_else(invalid_size)?,
+            )
+        }
+    };
+
+    Ok(img)
+}
+
+/// Colors of the basins of `NEWTON_ROOTS`
+const NEWTON_COLORS: [Rgb<u8>; 3] = [Rgb([220, 60, 60]), Rgb([60, 200, 90]), Rgb([70, 110, 230])];
+
+/// Share of the brightness a pixel of the Newton fractal keeps per step of its convergence
+const NEWTON_SHADING: f64 = 0.93;
+
+/// Colors the roots of `calc_newton` of a `width` x `height` picture by their basins,
+/// darker the more steps the convergence took, black for the points not converging
+pub fn newton_image(
+    points: &[Option<(usize, usize)>],
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, MandelbrotError> {
+    let raw = points
+        .iter()
+        .flat_map(|point| match *point {
+            Some((root, steps)) => {
+                let shade = NEWTON_SHADING.powi(steps as i32);
+                NEWTON_COLORS[root].0.map(|v| (v as f64 * shade) as u8)
+            }
+            None => [0; 3],
+        })
+        .collect();
+    let img = RgbImage::from_raw(width, height, raw).ok_or(MandelbrotError::InvalidBufferSize {
+        expected: width as usize * height as usize,
+        got: points.len(),
+    })?;
+    Ok(DynamicImage::ImageRgb8(img))
+}
+
+/// Colors the escape values of a `width` x `height` picture and saves it into `path`,
+/// `color_image` and `save_image` in a single call
+pub fn draw_mandelbrot(
+    escaped: Escaped,
+    width: u32,
+    height: u32,
+    options: DrawOptions,
+    path: &Path,
+    quality: Option<u8>,
+) -> Result<(), MandelbrotError> {
+    let img = color_image(&escaped, width, height, options)?;
+    save_image(img, path, quality)
+}
+
+/// Colors the escape counts of a `width` x `height` picture with `color`, it gets the escape
+/// count of a pixel and the number of iterations, the points of the set have the count
+/// equal to the iterations, `Palette::count_color` gives the closures of the palettes
+pub fn color_counts<F>(
+    counts: &[u64],
+    width: u32,
+    height: u32,
+    iters: usize,
+    color: F,
+) -> Result<DynamicImage, MandelbrotError>
+where
+    F: Fn(u64, usize) -> Rgb<u8> + Sync,
+{
+    let mut raw = vec![0; counts.len() * 3];
+    raw.par_chunks_mut(3)
+        .zip(counts)
+        .for_each(|(pixel, &count)| pixel.copy_from_slice(&color(count, iters).0));
+
+    match RgbImage::from_raw(width, height, raw) {
+        Some(im) => Ok(DynamicImage::ImageRgb8(im)),
+        None => Err(MandelbrotError::InvalidBufferSize {
+            expected: width as usize * height as usize,
+            got: counts.len(),
+        }),
+    }
+}
+
+/// Same as `draw_mandelbrot` with the colors of the closure of `color_counts`
+pub fn draw_mandelbrot_with<F>(
+    counts: &[u64],
+    width: u32,
+    height: u32,
+    iters: usize,
+    color: F,
+    path: &Path,
+    quality: Option<u8>,
+) -> Result<(), MandelbrotError>
+where
+    F: Fn(u64, usize) -> Rgb<u8> + Sync,
+{
+    let img = color_counts(counts, width, height, iters, color)?;
+    save_image(img, path, quality)
+}
+
+/// Colors the escape values of a `width` x `height` picture into a grayscale or RGB picture
+/// kept in memory, `save_image` writes it into a file
+pub fn color_image(
+    escaped: &Escaped,
+    width: u32,
+    height: u32,
+    options: DrawOptions,
+) -> Result<DynamicImage, MandelbrotError> {
+    let iters = options.iters;
+    // the degenerate values get the color of the last escape count without a color of
+    // their own
+    let degenerate = |x: f64| !x.is_finite();
+    let last = iters.saturating_sub(1) as f64;
+    let values = || {
+        (0..escaped.len()).map(|i| escaped.get(i)).map(|x| {
+            if degenerate(x) && options.degenerate_color.is_none() {
+                last
+            } else {
+                x
+            }
+        })
+    };
+    let finite = |[count, shade]: [f64; 2]| {
+        if degenerate(count) || degenerate(shade) {
+            [last, 1.0]
+        } else {
+            [count, shade]
+        }
+    };
+    let degenerate_color = options.degenerate_color.unwrap_or(Rgb([0; 3]));
+
+    let phase = options.color_mode == ColorMode::Phase;
+    let in_set = |x: f64| match escaped {
+        Escaped::Distance(_) => x == 0.0,
+        Escaped::Trap(_) => false,
+        Escaped::Stripe(_) => x == -1.0,
+        _ => x == iters as f64,
+    };
+
+    let invalid_size = || MandelbrotError::InvalidBufferSize {
+        expected: width as usize * height as usize,
+        got: escaped.len(),
+    };
+
+    if options.mask {
+        let raw = values().map(|x| if in_set(x) { 255 } else { 0 }).collect();
+        let mask = GrayImage::from_raw(width, height, raw).ok_or_else(invalid_size)?;
+        let mut img = DynamicImage::ImageLuma8(mask);
+        if options.invert {
+            img.invert();
+        }
+        return Ok(img);
+    }
+
+    // the histogram bins escape counts, the distances would index past its end
+    if let (Escaped::Distance(_), ColorMode::Histogram) = (escaped, options.color_mode) {
+        return Err(MandelbrotError::UnsupportedColorMode(
+            "histogram coloring needs escape counts, the values are distances".to_string(),
+        ));
+    }
+
+    if let (Escaped::Hsv(buf), ColorMode::Hsv) = (escaped, options.color_mode) {
+        let set_color = options.set_color.unwrap_or(Rgb([0; 3]));
+        let value = |&[count, shade]: &[f64; 2]| {
+            if in_set(count) {
+                set_color.0.map(f32::from)
+            } else if options.degenerate_color.is_some() && (degenerate(count) || degenerate(shade))
+            {
+                degenerate_color.0.map(f32::from)
+            } else {
+                let [count, shade] = finite([count, shade]);
+                let hue = count as f32 * options.hue_scale + options.palette_offset;
+                hsv(hue, 1.0, shade as f32)
+            }
+        };
+        let raw = match options.dither {
+            Some(kind) => {
+                let values: Vec<f32> = buf.iter().flat_map(value).collect();
+                dither(&values, width as usize, 3, kind)
+            }
+            None => buf.iter().flat_map(|v| value(v).map(|c| c as u8)).collect(),
+        };
+        let mut img = match RgbImage::from_raw(width, height, raw) {
+            Some(im) => DynamicImage::ImageRgb8(im),
+            None => return Err(invalid_size()),
+        };
+        if options.invert {
+            img.invert();
+        }
+        return Ok(img);
+    }
+
+    // maps escape value of a point outside the set into the range [0, max]
+    let level: Box<dyn Fn(f64, f32) -> f32> = match options.color_mode {
+        _ if matches!(escaped, Escaped::Trap(_)) => {
+            Box::new(|x, max| (x / TRAP_RANGE).min(1.0) as f32 * max)
+        }
+        _ if matches!(escaped, Escaped::Stripe(_)) => {
+            Box::new(|x, max| x.clamp(0.0, 1.0) as f32 * max)
+        }
+        // the stripe, tia, phase and hsv modes of the recolored escape counts fall back
+        // to the linear one, so do the smooth counts of the lighting
+        ColorMode::Linear
+        | ColorMode::Stripe
+        | ColorMode::Tia
+        | ColorMode::Phase
+        | ColorMode::Hsv
+        | ColorMode::Lighting => match options.clamp {
+            Some((low, high)) => {
+                Box::new(move |x, max| ((x - low) / (high - low)).clamp(0.0, 1.0) as f32 * max)
+            }
+            None => Box::new(|x, max| (x as f32) * (max / (iters as f32))),
+        },
+        ColorMode::Histogram => {
+            let cdf = histogram_cdf(values(), iters);
+            Box::new(move |x, max| cdf[x as usize] * max)
+        }
+        ColorMode::Distance => Box::new(|x, max| (x / DISTANCE_RANGE).min(1.0) as f32 * max),
+    };
+    let gamma = options.gamma;
+    let scale = options.scale.scale_fn();
+    let level: Box<dyn Fn(f64, f32) -> f32> = if gamma == 1.0 && options.scale == Scale::Linear {
+        level
+    } else {
+        Box::new(move |x, max| scale(level(x, 1.0)).powf(1.0 / gamma) * max)
+    };
+    let offset = options.palette_offset;
+    let level: Box<dyn Fn(f64, f32) -> f32> = if offset == 0.0 {
+        level
+    } else {
+        Box::new(move |x, max| (level(x, 1.0) + offset).rem_euclid(1.0) * max)
+    };
+
+    if let (Escaped::Hsv(buf), ColorMode::Lighting) = (escaped, options.color_mode) {
+        let set_color = options.set_color.unwrap_or(Rgb([0; 3]));
+        let color = options.value_fn();
+        let value = |&[count, shade]: &[f64; 2]| {
+            if in_set(count) {
+                set_color.0.map(f32::from)
+            } else if options.degenerate_color.is_some() && (degenerate(count) || degenerate(shade))
+            {
+                degenerate_color.0.map(f32::from)
+            } else {
+                let [count, shade] = finite([count, shade]);
+                color(level(count, 1.0)).map(|c| c * shade as f32)
+            }
+        };
+        let raw = match options.dither {
+            Some(kind) => {
+                let values: Vec<f32> = buf.iter().flat_map(value).collect();
+                dither(&values, width as usize, 3, kind)
+            }
+            None => buf.iter().flat_map(|v| value(v).map(|c| c as u8)).collect(),
+        };
+        let mut img = match RgbImage::from_raw(width, height, raw) {
+            Some(im) => DynamicImage::ImageRgb8(im),
+            None => return Err(invalid_size()),
+        };
+        if options.invert {
+            img.invert();
+        }
+        return Ok(img);
+    }
+
+    // the palette image and the colored points of the set take the rgb path
+    let gray = |color: Option<Rgb<u8>>| color.map(|Rgb([r, g, b])| (r == g && g == b).then_some(r));
+    let (set_gray, degenerate_gray) = (gray(options.set_color), gray(options.degenerate_color));
+    let gray_palette =
+        options.palette == Palette::Grayscale && options.gradient.is_none() && !phase;
+    let grayscale = gray_palette
+        && set_gray.is_none_or(|gray| gray.is_some())
+        && degenerate_gray.is_none_or(|gray| gray.is_some());
+    let set_gray = set_gray.flatten().unwrap_or(255);
+    let degenerate_gray = degenerate_gray.flatten().unwrap_or(0);
+    // the grayscale points of the set stay white in color, next to a colored degenerate one
+    let set_color = options
+        .set_color
+        .unwrap_or(Rgb([if gray_palette { 255 } else { 0 }; 3]));
+    let mut img = match (grayscale, options.bit_depth) {
+        (true, BitDepth::Eight) => {
+            let value = |x| {
+                if in_set(x) {
+                    set_gray as f32
+                } else if degenerate(x) {
+                    degenerate_gray as f32
+                } else {
+                    level(x, 255.0)
+                }
+            };
+            let raw = match options.dither {
+                Some(kind) => {
+                    let values: Vec<f32> = values().map(value).collect();
+                    dither(&values, width as usize, 1, kind)
+                }
+                None => values().map(|x| value(x) as u8).collect(),
+            };
+
+            match GrayImage::from_raw(width, height, raw) {
+                Some(im) => DynamicImage::ImageLuma8(im),
+                None => return Err(invalid_size()),
+            }
+        }
+        (true, BitDepth::Sixteen) => {
+            let raw = values()
+                .map(|x| {
+                    if in_set(x) {
+                        set_gray as u16 * 257
+                    } else if degenerate(x) {
+                        degenerate_gray as u16 * 257
+                    } else {
+                        level(x, 65535.0) as u16
+                    }
+                })
+                .collect();
+
+            match ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(width, height, raw) {
+                Some(im) => DynamicImage::ImageLuma16(im),
+                None => return Err(invalid_size()),
+            }
+        }
+        _ => {
+            let raw = match options.dither {
+                Some(kind) => {
+                    let color = options.value_fn();
+                    let values: Vec<f32> = values()
+                        .flat_map(|x| {
+                            if in_set(x) {
+                                set_color.0.map(f32::from)
+                            } else if degenerate(x) {
+                                degenerate_color.0.map(f32::from)
+                            } else {
+                                color(level(x, 1.0))
+                            }
+                        })
+                        .collect();
+                    dither(&values, width as usize, 3, kind)
+                }
+                None => {
+                    let color = options.color_fn();
+                    values()
+                        .flat_map(|x| {
+                            if in_set(x) {
+                                set_color.0
+                            } else if degenerate(x) {
+                                degenerate_color.0
+                            } else {
+                                color(level(x, 1.0)).0
+                            }
+                        })
+                        .collect()
+                }
+            };
+
+            match RgbImage::from_raw(width, height, raw) {
+                Some(im) => DynamicImage::ImageRgb8(im),
+                None => return Err(invalid_size()),
+            }
+        }
+    };
+    if options.invert {
+        img.invert();
+    }
+
+    Ok(img)
+}
+
+/// Saves the pictures as frames of an endlessly looping GIF animation
+pub fn save_gif(frames: Vec<DynamicImage>, path: &Path, fps: u32) -> Result<(), MandelbrotError> {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if ext != "gif" {
+        return Err(MandelbrotError::UnsupportedAnimationFormat(ext));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let writer = BufWriter::new(File::create(path)?);
+    // speed 10 of the color quantization is close to the best one at a fraction of the time
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    let delay = Delay::from_numer_denom_ms(1000, fps);
+    for img in frames {
+        encoder.encode_frame(Frame::from_parts(img.to_rgba8(), 0, 0, delay))?;
+    }
+
+    Ok(())
+}
@@ -1,16 +1,42 @@
-use clap::{Parser, Subcommand};
-use image::GrayImage;
-use num::Complex;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use config::Config;
+use image::{imageops, DynamicImage, Rgb};
+use indicatif::{ProgressBar, ProgressStyle};
+use mandelbrot::{
+    buddhabrot_image, calc_buddhabrot, calc_escaped, calc_interior, calc_newton, color_image,
+    count_random_in_set, draw_orbit, mandelbrot_at_point, mandelbrot_orbit, newton_default_coords,
+    newton_image, paint_interior, save_gif, save_image, save_image_bands, save_png_with_text,
+    write_ppm, BitDepth, CalcOptions, ColorMode, Dither, DrawOptions, Escaped, Gradient, Interior,
+    MandelbrotError, Offsets, Palette, PaletteSpace, Precision, RawHeader, SamplePattern, Scale,
+    SimdWidth, Trap, Variant, View, RANDOM_CHUNK, TILE,
+};
+use metadata::{name, Params, Sidecar, PNG_KEYWORD};
 use rayon::{
-    iter::{IndexedParallelIterator, ParallelIterator},
-    slice::ParallelSliceMut,
+    iter::{IntoParallelIterator, ParallelIterator},
+    ThreadPool, ThreadPoolBuilder,
+};
+use std::{
+    error::Error,
+    fmt,
+    hint::black_box,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
-use std::error::Error;
-use wide::{f64x4, CmpLe};
+use tile_cache::TileCache;
+
+use tracing::{debug, error, info, level_filters::LevelFilter, warn};
+
+mod checkpoint;
+mod config;
+mod keyframes;
+mod legend;
+mod metadata;
+mod tile_cache;
 
 /// Parallel CPU-based Mandelbrot set generator (rayon crate).
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, disable_help_flag = true)]
 struct Args {
     /// Number of iterations to check whether a point belongs to a set
     #[arg(short, long, default_value_t = 1000)]
@@ -24,26 +50,780 @@ struct Args {
     #[arg(short, long, default_value_t = 2160)]
     height: usize,
 
-    /// Minimum value of the X-axis for consideration on the complex plane
-    #[arg(long, default_value_t = -2.0)]
-    x_min: f64,
+    /// Minimum value of the X-axis for consideration on the complex plane (-2.0 for the Mandelbrot set)
+    #[arg(long, allow_hyphen_values = true)]
+    x_min: Option<f64>,
+
+    /// Maximum value of the X-axis for consideration on the complex plane (1.0 for the Mandelbrot set)
+    #[arg(long, allow_hyphen_values = true)]
+    x_max: Option<f64>,
+
+    /// Minimum value of the Y-axis for consideration on the complex plane (-0.84375 for the Mandelbrot set)
+    #[arg(long, allow_hyphen_values = true)]
+    y_min: Option<f64>,
+
+    /// Maximum value of the Y-axis for consideration on the complex plane (0.84375 for the Mandelbrot set)
+    #[arg(long, allow_hyphen_values = true)]
+    y_max: Option<f64>,
+
+    /// Expand the axis bounds along the shorter axis, about their center, to the aspect
+    /// ratio of the picture instead of stretching the picture
+    #[arg(long)]
+    fix_aspect: bool,
+
+    /// Width over height of a pixel on the target display, the view spans a region
+    /// width * R / height as wide as tall so that circles stay round there, the picture
+    /// keeps width x height pixels and --fix-aspect fits the bounds to that ratio too
+    #[arg(long, default_value_t = 1.0, value_parser = parse_pixel_aspect)]
+    pixel_aspect: f64,
+
+    /// Real part of the center of the picture, exclusive with the axis bounds
+    #[arg(long, allow_hyphen_values = true, conflicts_with_all = AXIS_BOUNDS)]
+    center_re: Option<f64>,
+
+    /// Imaginary part of the center of the picture, exclusive with the axis bounds
+    #[arg(long, allow_hyphen_values = true, conflicts_with_all = AXIS_BOUNDS)]
+    center_im: Option<f64>,
+
+    /// Magnification around the center, 1.0 shows the whole fractal and each doubling
+    /// halves the visible width, exclusive with the axis bounds
+    #[arg(long, value_parser = parse_zoom, conflicts_with_all = AXIS_BOUNDS)]
+    zoom: Option<f64>,
+
+    /// Center and zoom in a single string RE:IM@ZOOM (-0.7454:0.1130@1e5), the same as
+    /// --center-re RE --center-im IM --zoom ZOOM
+    #[arg(long, allow_hyphen_values = true, value_parser = parse_view, conflicts_with_all = ["x_min", "x_max", "y_min", "y_max", "center_re", "center_im", "zoom"])]
+    view: Option<(f64, f64, f64)>,
+
+    /// Angle in degrees the sampling grid is turned by counterclockwise about the center
+    /// of the view, the fractal turns the other way in the picture
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    rotate: f64,
+
+    /// Shift of the view by this fraction of its width, whatever the bounds come from,
+    /// positive values move it towards the larger real parts (the picture content moves left)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    pan_x: f64,
+
+    /// Shift of the view by this fraction of its height, positive values move it towards
+    /// the larger imaginary parts, which lie at the bottom of the picture (top with --flip-y)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    pan_y: f64,
+
+    /// Color by the closest approach of the orbits to the trap (Mandelbrot set only),
+    /// overrides the color mode
+    #[arg(long, value_enum)]
+    trap: Option<Trap>,
+
+    /// Color the points of the set by a metric of their orbits instead of the flat color
+    /// (Mandelbrot set only), all the iterations of every point of the set run once more
+    #[arg(long, value_enum)]
+    interior_color: Option<Interior>,
+
+    /// Embed the render parameters as JSON into the text chunk "Mandelbrot-Params"
+    /// of the picture (png only)
+    #[arg(long)]
+    embed_metadata: bool,
+
+    /// Save the render parameters, the calculation time and the statistics of the escape
+    /// values into JSON next to the picture (image.png.json for image.png)
+    #[arg(long)]
+    metadata_sidecar: bool,
+
+    /// Save the raw escape values into a binary file, the recolor subcommand turns it
+    /// into pictures of other palettes and color modes without calculating again
+    #[arg(long)]
+    dump_raw: Option<PathBuf>,
+
+    /// Save the escape values as CSV of a line per row of the picture and a column per
+    /// pixel, after a comment line starting with '#' of the size, the bounds and --iters
+    #[arg(long)]
+    export_csv: Option<PathBuf>,
+
+    /// Save the palette as a gradient bar into this picture, labeled with the escape
+    /// counts the colors stand for (0..iters or the range of --color-clamp)
+    #[arg(long)]
+    legend: Option<PathBuf>,
+
+    /// Size of the gradient bar of --legend, the labels go below it
+    #[arg(long, default_value = "256x16", value_parser = parse_size)]
+    legend_size: (usize, usize),
+
+    /// Calculate and color the picture in bands of this many rows, so only a single band
+    /// of escape values is kept in memory (png output is written band by band as well)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    tile_height: Option<u32>,
+
+    /// Stop calculating after this many seconds and save the rows finished so far,
+    /// the rest of the picture is left at the escape value 0
+    #[arg(long, value_parser = parse_time_limit, conflicts_with = "tile_height")]
+    time_limit: Option<f64>,
+
+    /// Save the escape values of every band of rows into this file as soon as it's
+    /// calculated, --resume continues an interrupted render from the file
+    #[arg(long, conflicts_with_all = ["resume", "sizes", "tile_height", "time_limit", "no_save"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue the render saved by --checkpoint into this file, only the bands missing from
+    /// it get calculated (and saved into it), the parameters of the calculation must be the
+    /// ones of the interrupted render
+    #[arg(long, conflicts_with_all = ["sizes", "tile_height", "time_limit", "no_save"])]
+    resume: Option<PathBuf>,
+
+    /// Calculate and save only the region of W x H pixels at column X and row Y of the
+    /// picture (counted from the top left corner), at the pixel size of the whole picture,
+    /// the histogram color mode equalizes the region alone
+    #[arg(long, num_args = 4, value_names = ["X", "Y", "W", "H"], conflicts_with_all = ["sizes", "tile_height"])]
+    roi: Option<Vec<usize>>,
+
+    /// Render the view at every one of these sizes (WxH,WxH,...) instead of the width and
+    /// height, into files with the size appended (image_640x480.png), the sizes keep
+    /// the aspect ratio of the view
+    #[arg(long, value_delimiter = ',', value_parser = parse_size, conflicts_with_all = ["tile_height", "dump_raw", "export_csv"])]
+    sizes: Option<Vec<(usize, usize)>>,
+
+    /// Path of result picture, the format is inferred from the extension
+    #[arg(short, long, default_value = "image.png")]
+    output: PathBuf,
+
+    /// Keep the existing picture and write image_1.png, image_2.png, ... (the first free
+    /// index) instead of image.png
+    #[arg(long)]
+    no_overwrite: bool,
+
+    /// Write the picture to standard output as binary PPM (PGM for grayscale) instead
+    /// of saving it, the messages go to standard error
+    #[arg(long, conflicts_with_all = ["sizes", "tile_height", "embed_metadata", "no_overwrite"])]
+    stdout: bool,
+
+    /// Only calculate the escape values and print the time it took with their checksum,
+    /// nothing gets colored or saved
+    #[arg(long, conflicts_with_all = ["sizes", "tile_height", "dump_raw", "export_csv", "stdout"])]
+    no_save: bool,
+
+    /// Print a checksum of the escape values, the same on every platform and for every
+    /// number of threads, to catch changes of the numerics between versions
+    #[arg(long, conflicts_with = "tile_height")]
+    checksum: bool,
+
+    /// Black and white picture of the points of the set (white) and the other ones, without
+    /// any palette
+    #[arg(long)]
+    mask: bool,
+
+    /// Quality of JPEG pictures from 0 to 100 [default: 75], the other formats are saved
+    /// lossless and WebP ones (whose encoder is lossless only) reject it
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    quality: Option<u8>,
+
+    /// Bits per channel of result picture (16 is supported by png and tiff with grayscale palette only)
+    #[arg(short, long, value_enum, default_value_t = BitDepth::Eight)]
+    bit_depth: BitDepth,
+
+    /// Color palette of result picture
+    #[arg(short, long, value_enum, default_value_t = Palette::Grayscale)]
+    palette: Palette,
+
+    /// Picture of a single row or column of pixels used as the palette, the first pixel
+    /// is the start of the palette and the colors between the pixels are interpolated
+    #[arg(long)]
+    palette_image: Option<PathBuf>,
+
+    /// Generate the palette from this seed instead, a smooth ramp from dark to light,
+    /// the same seed always gives the same colors
+    #[arg(long, conflicts_with = "palette_image")]
+    random_palette: Option<u64>,
+
+    /// Color space the colors of --palette-image and --random-palette are interpolated in
+    #[arg(long, value_enum, default_value_t = PaletteSpace::Rgb)]
+    palette_space: PaletteSpace,
+
+    /// Rotation of the palette in range [0, 1), the escape ratio t gets the color
+    /// of (t + O) mod 1, sweeping it over the frames cycles the colors
+    #[arg(long, default_value_t = 0.0, value_parser = parse_palette_offset)]
+    palette_offset: f32,
+
+    /// Color of the points of the set as R,G,B or a single gray value, white in grayscale
+    /// and black with the other palettes by default
+    #[arg(long, value_parser = parse_set_color)]
+    set_color: Option<Rgb<u8>>,
+
+    /// Color of the pixels whose escape values aren't finite as R,G,B or a single gray
+    /// value, such as the smooth counts of the orbits overflowing f64 with a huge --bailout,
+    /// the color of the last escape count by default
+    #[arg(long, value_parser = parse_set_color)]
+    degenerate_color: Option<Rgb<u8>>,
+
+    /// Invert the colors of the picture, the points of the set included, for dark on light
+    /// prints
+    #[arg(long)]
+    invert: bool,
+
+    /// Dither the 8-bit channels of the picture to hide the banding of the smooth
+    /// gradients, a little noise in the lowest bit instead of visible steps
+    #[arg(long, value_enum)]
+    dither: Option<Dither>,
+
+    /// Colors of --palette-image or --random-palette loaded after the config
+    #[arg(skip)]
+    gradient: Option<Gradient>,
+
+    /// Gamma of the escape ratio, the palette gets the ratio raised to 1 / G, so values
+    /// above 1.0 brighten the midtones
+    #[arg(long, default_value_t = 1.0, value_parser = parse_gamma)]
+    gamma: f32,
+
+    /// Curve of the escape ratio before the palette, the logarithmic one brings out
+    /// the fine filaments
+    #[arg(long, value_enum, default_value_t = Scale::Linear)]
+    scale: Scale,
+
+    /// Stretch the escape counts from LOW to HIGH over the whole palette (linear color
+    /// mode only), the counts below get the start of the palette and the ones above its end
+    #[arg(long, num_args = 2, value_names = ["LOW", "HIGH"], allow_hyphen_values = true)]
+    color_clamp: Option<Vec<f64>>,
+
+    /// Use fractional escape counts to get rid of the contour bands
+    #[arg(short, long)]
+    smooth: bool,
+
+    /// Power d of the recurrence z = z^d + c (Multibrot set), the set has d - 1 fold
+    /// rotational symmetry, so its shape changes with the parity of d: odd powers get
+    /// mirrored about the imaginary axis, even ones stay lopsided like d = 2
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(2..))]
+    power: u32,
+
+    /// Escape radius of the iterations, at least 2.0 (smooth coloring uses at least 16.0)
+    #[arg(long, default_value_t = 2.0, value_parser = parse_bailout)]
+    bailout: f64,
+
+    /// Number of points iterated at once by the Mandelbrot set kernel,
+    /// 8 requires AVX-512 (build with RUSTFLAGS="-C target-cpu=native" to make use of it)
+    #[arg(long, value_enum, default_value_t = SimdWidth::Four)]
+    simd_width: SimdWidth,
+
+    /// Floating point precision of the Mandelbrot set kernel, f32 iterates 8 points at once
+    /// but gets blocky on deep zooms like deep-spiral, dd (double-double) iterates a single
+    /// point at a time many times slower but stays sharp on the zooms too deep for f64
+    /// (other fractals always use f64)
+    #[arg(long, value_enum, default_value_t = Precision::F64)]
+    precision: Precision,
+
+    /// Iterate every pixel of the Mandelbrot set with the scalar reference instead of the
+    /// SIMD kernel, the checksum of the escape counts must stay the same
+    #[arg(long, hide = true)]
+    scalar: bool,
+
+    /// Number of threads of the calculation, 0 uses all cores
+    #[arg(short, long, default_value_t = 0)]
+    threads: usize,
+
+    /// Rows of the picture making up one parallel task (in tiles of 64 columns), fewer
+    /// spread uneven rows over more threads at the cost of scheduling more tasks, the
+    /// default keeps the tiles of 64 x 64 pixels the picture was already split into before
+    /// (1 gives a task per row)
+    #[arg(long, default_value_t = TILE as u32, value_parser = clap::value_parser!(u32).range(1..))]
+    rows_per_task: u32,
+
+    /// Calculate the Mandelbrot set by perturbation around a reference orbit of the center
+    /// of the view, for the deep zooms whose pixels are too small for plain f64 (windows
+    /// down to about 1e-14 wide, where the f64 axis bounds run out of digits themselves)
+    #[arg(long)]
+    deep: bool,
+
+    /// Stop instead of warning when the pixels of the view are too small for plain f64,
+    /// for the renders that would rather fail than blur into blocks
+    #[arg(long)]
+    strict_precision: bool,
+
+    /// Iterate the points inside the main cardioid and the period-2 bulb of the Mandelbrot
+    /// set instead of marking them as points of the set at once
+    #[arg(long)]
+    no_fast_interior: bool,
+
+    /// Side of the grid of samples averaged into every pixel to smooth out the edges
+    /// of the set, 3 takes 9 samples per pixel (the memory use grows the same way)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=16))]
+    samples: u32,
 
-    /// Maximum value of the X-axis for consideration on the complex plane
-    #[arg(long, default_value_t = 1.0)]
-    x_max: f64,
+    /// Positions of the --samples in every pixel
+    #[arg(long, value_enum, default_value_t = SamplePattern::Grid)]
+    aa_pattern: SamplePattern,
 
-    /// Minimum value of the Y-axis for consideration on the complex plane
-    #[arg(long, default_value_t = -0.84375)]
-    y_min: f64,
+    /// Seed of the positions of --aa-pattern random, the same seed renders the same picture
+    #[arg(long, default_value_t = 0)]
+    aa_seed: u64,
 
-    /// Maximum value of the Y-axis for consideration on the complex plane}
-    #[arg(long, default_value_t = 0.84375)]
-    y_max: f64,
+    /// Take the --samples only in the pixels differing from their neighbors, the edges
+    /// of the set, after a first pass of a single sample per pixel
+    #[arg(long)]
+    adaptive_aa: bool,
+
+    /// Difference of the escape values of neighboring pixels above which --adaptive-aa
+    /// takes the samples of both of them
+    #[arg(long, default_value_t = 1.0, value_parser = parse_aa_threshold)]
+    aa_threshold: f64,
+
+    /// Pick the number of iterations from the zoom level of the view instead of --iters,
+    /// deeper views need more of them to tell the set from the slowly escaping points
+    #[arg(long)]
+    auto_iters: bool,
+
+    /// Pick the number of iterations from probes of a small picture of the view instead of
+    /// --iters, doubling them until the unescaped pixels next to the escaped ones hardly
+    /// escape with twice as many
+    #[arg(long, conflicts_with = "auto_iters")]
+    auto_iters_adaptive: bool,
+
+    /// Upper bound of the number of iterations picked by --auto-iters and
+    /// --auto-iters-adaptive
+    #[arg(long, default_value_t = AUTO_ITERS_MAX, value_parser = parse_auto_iters_max)]
+    auto_iters_max: usize,
+
+    /// Put the largest imaginary part at the top row as in the usual plots of the complex
+    /// plane, by default the top row holds the smallest one (y_min)
+    #[arg(long)]
+    flip_y: bool,
+
+    /// Print the resolved view, the number of iterations and threads, the durations and
+    /// the statistics of the escape values, the largest one far below the number of
+    /// iterations means that fewer of them would do
+    #[arg(short, long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Print the errors and the warnings only
+    #[arg(long)]
+    quiet: bool,
+
+    /// Show progress of the calculation
+    #[arg(long)]
+    progress: bool,
+
+    /// Way of mapping escape values into the palette, the points of the set are colored
+    /// by `--interior-color` (`--interior-color period` for the period of their cycle)
+    #[arg(short, long, value_enum, default_value_t = ColorMode::Linear)]
+    color_mode: ColorMode,
+
+    /// Number of stripes per turn around the origin of the stripe color mode
+    #[arg(long, default_value_t = 5.0, allow_hyphen_values = true)]
+    stripe_density: f64,
+
+    /// Turns of the hue per iteration of the hsv color mode
+    #[arg(long, default_value_t = 0.02, value_parser = parse_hue_scale)]
+    hue_scale: f32,
+
+    /// Direction of the light of the lighting color mode in degrees, counterclockwise from
+    /// the positive real axis
+    #[arg(long, default_value_t = 45.0, allow_hyphen_values = true)]
+    light_angle: f64,
+
+    /// Height of the light of the lighting color mode over the plane in degrees, 90 lights
+    /// every slope alike
+    #[arg(long, default_value_t = 45.0, value_parser = parse_light_elevation)]
+    light_elevation: f64,
+
+    /// TOML file with the values of the flags (keys are the flag names with underscores),
+    /// flags given on the command line override it
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print help (-h is the height)
+    #[arg(long, global = true, action = clap::ArgAction::Help)]
+    help: Option<bool>,
 
     #[command(subcommand)]
-    location: Option<Location>,
+    command: Option<Command>,
+}
+impl Args {
+    fn draw_options(&self, iters: usize) -> DrawOptions<'_> {
+        DrawOptions {
+            iters,
+            bit_depth: self.bit_depth,
+            palette: self.palette,
+            color_mode: self.color_mode,
+            mask: self.mask,
+            gamma: self.gamma,
+            scale: self.scale,
+            palette_offset: self.palette_offset,
+            set_color: self.set_color,
+            degenerate_color: self.degenerate_color,
+            invert: self.invert,
+            gradient: self.gradient.as_ref(),
+            dither: self.dither,
+            hue_scale: self.hue_scale,
+            clamp: self.color_clamp.as_deref().map(|c| (c[0], c[1])),
+        }
+    }
+
+    /// Fractal of the subcommand and the power
+    fn variant(&self) -> Variant {
+        match self.command {
+            Some(Command::BurningShip) => Variant::BurningShip,
+            Some(Command::Tricorn) => Variant::Tricorn,
+            _ if self.power > 2 => Variant::Multibrot(self.power),
+            _ => Variant::Mandelbrot,
+        }
+    }
+
+    /// View of the location, the center and zoom or the axis bounds of the flags, panned
+    /// by the pan flags
+    fn view(&self, variant: Variant) -> View {
+        // of the picture on the display, the default bounds and the locations fit it
+        let aspect = self.width as f64 * self.pixel_aspect / self.height as f64;
+
+        let default_coords = match self.command {
+            Some(Command::Newton) => newton_default_coords(aspect),
+            _ => variant.default_coords(aspect),
+        };
+        let (x_min, x_max, y_min, y_max) = if let Some(Command::Location(loc)) = self.command {
+            loc.coords(aspect)
+        } else if self.center_re.is_some() || self.center_im.is_some() || self.zoom.is_some() {
+            let (x_min, x_max, y_min, y_max) = default_coords;
+            let cx = self.center_re.unwrap_or((x_min + x_max) / 2.0);
+            let cy = self.center_im.unwrap_or((y_min + y_max) / 2.0);
+            let dx = (x_max - x_min) / self.zoom.unwrap_or(1.0);
+            let dy = dx / aspect;
+            (cx - dx / 2.0, cx + dx / 2.0, cy - dy / 2.0, cy + dy / 2.0)
+        } else {
+            let (x_min, x_max, y_min, y_max) = default_coords;
+            let bounds = (
+                self.x_min.unwrap_or(x_min),
+                self.x_max.unwrap_or(x_max),
+                self.y_min.unwrap_or(y_min),
+                self.y_max.unwrap_or(y_max),
+            );
+            if [self.x_min, self.x_max, self.y_min, self.y_max]
+                .iter()
+                .any(Option::is_some)
+            {
+                fit_aspect(
+                    bounds,
+                    self.width,
+                    self.height,
+                    self.pixel_aspect,
+                    self.fix_aspect,
+                )
+            } else {
+                bounds
+            }
+        };
+        let pan_x = self.pan_x * (x_max - x_min);
+        let pan_y = self.pan_y * (y_max - y_min);
+        let (x_min, x_max, y_min, y_max) =
+            (x_min + pan_x, x_max + pan_x, y_min + pan_y, y_max + pan_y);
+
+        View {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+            width: self.width,
+            height: self.height,
+            rows: (0, self.height),
+            columns: (0, self.width),
+            rotation: self.rotate.to_radians(),
+            deadline: None,
+            offsets: Offsets::None,
+            rows_per_task: self.rows_per_task as usize,
+        }
+    }
+}
+
+const AXIS_BOUNDS: [&str; 4] = ["x_min", "x_max", "y_min", "y_max"];
+
+/// Radius below 2.0 lets orbits of the points outside the set look bounded
+fn parse_bailout(s: &str) -> Result<f64, String> {
+    let radius: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if radius >= 2.0 {
+        Ok(radius)
+    } else {
+        Err(format!(
+            "bailout radius must be at least 2.0, got {}",
+            radius
+        ))
+    }
+}
+
+fn parse_zoom(s: &str) -> Result<f64, String> {
+    let zoom: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if zoom > 0.0 && zoom.is_finite() {
+        Ok(zoom)
+    } else {
+        Err(format!("zoom must be a positive number, got {}", zoom))
+    }
+}
+
+fn parse_view(s: &str) -> Result<(f64, f64, f64), String> {
+    let malformed = || format!("expected RE:IM@ZOOM like -0.7454:0.1130@1e5, got \"{}\"", s);
+    let (center, zoom) = s.split_once('@').ok_or_else(malformed)?;
+    let (re, im) = center.split_once(':').ok_or_else(malformed)?;
+    let part = |name: &str, v: &str| match v.trim().parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        _ => Err(format!(
+            "{} \"{}\" of \"{}\" is not a finite number",
+            name,
+            v.trim(),
+            s
+        )),
+    };
+    let zoom = parse_zoom(zoom.trim())
+        .map_err(|e| format!("zoom \"{}\" of \"{}\": {}", zoom.trim(), s, e))?;
+    Ok((part("real part", re)?, part("imaginary part", im)?, zoom))
+}
+
+fn parse_gamma(s: &str) -> Result<f32, String> {
+    let gamma: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if gamma > 0.0 && gamma.is_finite() {
+        Ok(gamma)
+    } else {
+        Err(format!("gamma must be a positive number, got {}", gamma))
+    }
+}
+
+fn parse_hue_scale(s: &str) -> Result<f32, String> {
+    let scale: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if scale > 0.0 && scale.is_finite() {
+        Ok(scale)
+    } else {
+        Err(format!(
+            "hue scale must be a positive number, got {}",
+            scale
+        ))
+    }
+}
+
+fn parse_pixel_aspect(s: &str) -> Result<f64, String> {
+    let aspect: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if aspect > 0.0 && aspect.is_finite() {
+        Ok(aspect)
+    } else {
+        Err(format!(
+            "pixel aspect must be a positive number, got {}",
+            aspect
+        ))
+    }
+}
+
+fn parse_light_elevation(s: &str) -> Result<f64, String> {
+    let elevation: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if (0.0..=90.0).contains(&elevation) {
+        Ok(elevation)
+    } else {
+        Err(format!(
+            "elevation must be in 0..=90 degrees, got {}",
+            elevation
+        ))
+    }
+}
+
+fn parse_aa_threshold(s: &str) -> Result<f64, String> {
+    let threshold: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if threshold >= 0.0 && threshold.is_finite() {
+        Ok(threshold)
+    } else {
+        Err(format!(
+            "threshold must be a non-negative number, got {}",
+            threshold
+        ))
+    }
+}
+
+fn parse_auto_iters_max(s: &str) -> Result<usize, String> {
+    let max: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if max > 0 {
+        Ok(max)
+    } else {
+        Err("the upper bound of the iterations must be at least 1".to_string())
+    }
+}
+
+fn parse_time_limit(s: &str) -> Result<f64, String> {
+    let secs: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if secs > 0.0 && secs.is_finite() {
+        Ok(secs)
+    } else {
+        Err(format!(
+            "time limit must be a positive number, got {}",
+            secs
+        ))
+    }
+}
+
+fn parse_palette_offset(s: &str) -> Result<f32, String> {
+    let offset: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if (0.0..1.0).contains(&offset) {
+        Ok(offset)
+    } else {
+        Err(format!("palette offset must be in 0..1, got {}", offset))
+    }
+}
+
+/// "WxH" with both sides positive
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| format!("expected WxH, got \"{}\"", s))?;
+    let side = |v: &str| match v.trim().parse::<usize>() {
+        Ok(side) if side > 0 => Ok(side),
+        _ => Err(format!(
+            "size side \"{}\" is not a positive integer",
+            v.trim()
+        )),
+    };
+    Ok((side(width)?, side(height)?))
+}
+
+/// "R,G,B" or a single gray value, every component in 0..=255
+fn parse_set_color(s: &str) -> Result<Rgb<u8>, String> {
+    let components = s
+        .split(',')
+        .map(|c| {
+            c.trim()
+                .parse::<u8>()
+                .map_err(|_| format!("color component \"{}\" is not in 0..=255", c.trim()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match components[..] {
+        [gray] => Ok(Rgb([gray; 3])),
+        [r, g, b] => Ok(Rgb([r, g, b])),
+        _ => Err(format!(
+            "expected R,G,B or a single gray value, got {} components",
+            components.len()
+        )),
+    }
 }
+
 #[derive(Subcommand, Debug)]
+enum Command {
+    #[command(flatten)]
+    Location(Location),
+    /// Burning Ship fractal, z = (|Re z| + i|Im z|)^2 + c
+    BurningShip,
+    /// Tricorn (Mandelbar) fractal, z = conj(z)^2 + c
+    Tricorn,
+    /// Animated GIF zooming into the center of the view or a location
+    Zoom(ZoomArgs),
+    /// List the locations with their coordinates for the center and zoom flags
+    Locations,
+    /// Buddhabrot, density of the orbits of random points escaping within the iterations,
+    /// a statistical picture differing a bit from run to run
+    Buddhabrot(BuddhabrotArgs),
+    /// Newton fractal of z^3 - 1, colored by the root the points converge to and shaded
+    /// by the steps it took, the iterations cap the steps
+    Newton,
+    /// Estimate the area of the set within the view from the share of its points
+    Area(AreaArgs),
+    /// Print the point of the complex plane calculated for a pixel of the view or the pixel
+    /// holding a point of the complex plane
+    Coord(CoordArgs),
+    /// Picture of the view with the orbit z_0 = 0, z_1 = c, ... of a single point drawn
+    /// over it, from blue at the start to red at the end (Mandelbrot set only)
+    Orbit(OrbitArgs),
+    /// Color the escape values saved by --dump-raw with the palette, color mode and bit
+    /// depth flags instead of calculating them
+    Recolor(RecolorArgs),
+    /// Time the calculation of the escape values of the view without coloring and saving
+    /// the picture
+    Bench(BenchArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    /// Number of untimed calculations before the timed ones
+    #[arg(long, default_value_t = 1)]
+    warmup: u32,
+
+    /// Number of timed calculations
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+}
+
+#[derive(clap::Args, Debug)]
+struct BuddhabrotArgs {
+    /// Number of random points whose orbits are traced, more of them give a smoother picture
+    #[arg(long, default_value_t = 1_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    samples: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct AreaArgs {
+    /// Number of uniformly random points of the Mandelbrot set tested instead of the grid
+    /// of the pixels, the estimate comes with its 95% confidence interval
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    samples: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+struct CoordArgs {
+    /// Column and row of the pixel, counted from the top left corner
+    #[arg(long, num_args = 2, value_names = ["X", "Y"])]
+    pixel: Option<Vec<usize>>,
+
+    /// Real and imaginary part of the point
+    #[arg(long, num_args = 2, value_names = ["RE", "IM"], allow_negative_numbers = true)]
+    complex: Option<Vec<f64>>,
+}
+
+#[derive(clap::Args, Debug)]
+struct OrbitArgs {
+    /// Real and imaginary part of the point c, the iterations cap the plotted points
+    #[arg(long, num_args = 2, value_names = ["RE", "IM"], allow_negative_numbers = true, required = true)]
+    point: Vec<f64>,
+}
+
+#[derive(clap::Args, Debug)]
+struct RecolorArgs {
+    /// Raw dump of the escape values written by --dump-raw
+    input: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct ZoomArgs {
+    /// Location to zoom into instead of the center of the view
+    #[arg(long, value_enum)]
+    location: Option<Location>,
+
+    /// Number of frames of the animation
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+    frames: u32,
+
+    /// Magnification of every frame relative to the previous one
+    #[arg(long, default_value_t = 1.1, value_parser = parse_zoom)]
+    zoom_factor: f64,
+
+    /// Frames per second of the animation
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+    fps: u32,
+
+    /// Extra iterations per doubling of the magnification, deep frames need more of them
+    /// to tell the points of the set from the slowly escaping ones
+    #[arg(long, default_value_t = 0)]
+    iters_per_doubling: usize,
+
+    /// Directory of numbered PNG frames (frame_00000.png, ...) to write instead of the GIF,
+    /// frames already present there are skipped
+    #[arg(long)]
+    frame_sequence: Option<PathBuf>,
+
+    /// TOML file of `[[keyframe]]` tables (center_re, center_im, zoom and iters) to fly
+    /// through instead of zooming into a single point, --frames are spread evenly over them
+    #[arg(long, conflicts_with_all = ["location", "zoom_factor", "iters_per_doubling"])]
+    keyframes: Option<PathBuf>,
+
+    /// Frames calculated at the same time, all of them share the threads of --threads,
+    /// a few jobs keep the threads busy on small frames (every frame at once by default)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    frame_jobs: Option<u32>,
+
+    /// Keeps the tiles of the frames and reuses the ones whose pixels lie at the same points
+    /// with the same iterations in later frames, the holds of the keyframes and the frames
+    /// of --zoom-factor 1 aren't calculated again
+    #[arg(long)]
+    tile_cache: bool,
+}
+
+#[derive(Subcommand, ValueEnum, Clone, Copy, Debug)]
 enum Location {
     /// Seahorse Valley (double spirals)
     Seahorse,
@@ -51,9 +831,37 @@ enum Location {
     DeepSpiral,
     /// Elephant Valley
     Elephant,
+    /// Triple Spiral Valley (spirals with three arms)
+    TripleSpiral,
+    /// Period-3 copy of the set on the real axis
+    MiniMandelbrot,
+    /// Scepter Valley (between the period-2 and period-4 bulbs)
+    ScepterValley,
 }
 
 impl Location {
+    fn all() -> &'static [Location] {
+        Location::value_variants()
+    }
+
+    /// Doc comment of the variant
+    fn description(&self) -> String {
+        self.to_possible_value()
+            .and_then(|v| v.get_help().map(|help| help.to_string()))
+            .unwrap_or_default()
+    }
+
+    /// Center of the location and its zoom relative to the whole Mandelbrot set
+    fn center_zoom(&self) -> (f64, f64, f64) {
+        let (x_min, x_max, y_min, y_max) = self.coords(1.0);
+        let (full_min, full_max, _, _) = Variant::Mandelbrot.default_coords(1.0);
+        (
+            (x_min + x_max) / 2.0,
+            (y_min + y_max) / 2.0,
+            (full_max - full_min) / (x_max - x_min),
+        )
+    }
+
     fn coords(&self, aspect: f64) -> (f64, f64, f64, f64) {
         match self {
             Location::Seahorse => {
@@ -77,164 +885,1890 @@ impl Location {
                 let dy = dx / aspect;
                 (x_min, x_max, cy - dy / 2.0, cy + dy / 2.0)
             }
+            Location::TripleSpiral => {
+                let (x_min, x_max) = (-0.093, -0.083);
+                let dx = x_max - x_min;
+                let cy = 0.653;
+                let dy = dx / aspect;
+                (x_min, x_max, cy - dy / 2.0, cy + dy / 2.0)
+            }
+            Location::MiniMandelbrot => {
+                let (x_min, x_max) = (-1.79, -1.71);
+                let dx = x_max - x_min;
+                let cy = 0.0;
+                let dy = dx / aspect;
+                (x_min, x_max, cy - dy / 2.0, cy + dy / 2.0)
+            }
+            Location::ScepterValley => {
+                let (x_min, x_max) = (-1.28, -1.22);
+                let dx = x_max - x_min;
+                let cy = 0.025;
+                let dy = dx / aspect;
+                (x_min, x_max, cy - dy / 2.0, cy + dy / 2.0)
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Complex4 {
-    real: f64x4,
-    imag: f64x4,
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(v) => write!(f, "{}", v.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
 }
 
-fn calc_mandelbrot(
-    iters: usize,
-    x_min: f64,
-    x_max: f64,
-    y_min: f64,
-    y_max: f64,
-    width: usize,
-    height: usize,
-) -> Vec<u8> {
-    let mut buf: Vec<u8> = vec![0; width * height];
+/// Renders the frames of the zoom in parallel and saves them as GIF animation
+/// or as numbered pictures of the frame sequence directory
+fn zoom(
+    args: &Args,
+    zoom_args: &ZoomArgs,
+    calc_options: &CalcOptions,
+    view: View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<(), Box<dyn Error>> {
+    let aspect = view.width as f64 * args.pixel_aspect / view.height as f64;
+    let (cx, cy) = match zoom_args.location {
+        Some(loc) => {
+            let (x_min, x_max, y_min, y_max) = loc.coords(aspect);
+            ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0)
+        }
+        None => (
+            (view.x_min + view.x_max) / 2.0,
+            (view.y_min + view.y_max) / 2.0,
+        ),
+    };
+    let dx = view.x_max - view.x_min;
+    let keys = match &zoom_args.keyframes {
+        Some(path) => Some(keyframes::load(path)?),
+        None => None,
+    };
+    let (full_min, full_max, _, _) = calc_options.variant.default_coords(aspect);
+    // the frames run as tasks of a pool of --frame-jobs threads, which wait for the
+    // calculations handed over to the shared pool
+    let jobs = match zoom_args.frame_jobs {
+        Some(jobs) => Some(
+            ThreadPoolBuilder::new()
+                .num_threads(jobs as usize)
+                .build()?,
+        ),
+        None => None,
+    };
+    let frame_pool = jobs.as_ref().unwrap_or(pool);
+    let cache = zoom_args.tile_cache.then(TileCache::new);
+    let report_cache = || {
+        if let Some(cache) = &cache {
+            let (hits, misses) = cache.counts();
+            info!("Reused {} of {} tiles", hits, hits + misses);
+        }
+    };
 
-    let dx = (x_max - x_min) / width as f64;
-    let dy = (y_max - y_min) / height as f64;
+    let render = |frame: u32| {
+        let (cx, cy, dx, iters) = match &keys {
+            Some(keys) => {
+                let key = keyframes::interpolate(keys, frame, zoom_args.frames);
+                let dx = (full_max - full_min) / key.zoom;
+                (key.center_re, key.center_im, dx, key.iters)
+            }
+            None => {
+                let magnification = zoom_args.zoom_factor.powi(frame as i32);
+                let doublings = magnification.log2().max(0.0);
+                let iters =
+                    calc_options.iters + (zoom_args.iters_per_doubling as f64 * doublings) as usize;
+                (cx, cy, dx / magnification, iters)
+            }
+        };
+        let dy = dx / aspect;
+        let frame_view = View {
+            x_min: cx - dx / 2.0,
+            x_max: cx + dx / 2.0,
+            y_min: cy - dy / 2.0,
+            y_max: cy + dy / 2.0,
+            ..view
+        };
+        let options = CalcOptions {
+            iters,
+            ..*calc_options
+        };
 
-    let inv_iter = 255.0 / (iters as f32);
+        let draw_options = args.draw_options(iters);
+        let mut escaped = pool.install(|| match &cache {
+            Some(cache) => cache.calc(&options, &frame_view, progress),
+            None => calc_escaped(&options, &frame_view, progress),
+        });
+        if args.flip_y {
+            escaped.flip_rows(view.width);
+        }
+        (draw_options, escaped)
+    };
 
-    buf.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
-        let cy_val = y_min + (y as f64) * dy;
-        let cy4 = f64x4::splat(cy_val);
+    if let Some(dir) = &zoom_args.frame_sequence {
+        // frames rendered before an interruption are kept, so the render resumes
+        let digits = (zoom_args.frames - 1).to_string().len().max(5);
+        let pending: Vec<(u32, PathBuf)> = (0..zoom_args.frames)
+            .map(|frame| (frame, dir.join(format!("frame_{:0digits$}.png", frame))))
+            .filter(|(_, path)| !path.exists())
+            .collect();
+        progress.set_length((pending.len() * view.height * calc_options.samples) as u64);
 
-        let mut chunks = row.chunks_exact_mut(4);
-        let mut x = 0;
+        frame_pool.install(|| {
+            pending.into_par_iter().try_for_each(|(frame, path)| {
+                let (options, escaped) = render(frame);
+                color_image(&escaped, view.width as u32, view.height as u32, options)
+                    .and_then(|img| save_image(img, &path, args.quality))
+                    .map_err(|e| format!("{}: {}", path.display(), e))
+            })
+        })?;
+        progress.finish_and_clear();
+        report_cache();
 
-        let x_offset = f64x4::new([0.0, 1.0, 2.0, 3.0]);
-        let dx4 = f64x4::splat(dx);
-        let x_min4 = f64x4::splat(x_min);
+        return Ok(());
+    }
 
-        for chunk in chunks.by_ref() {
-            let x_base = f64x4::splat(x as f64);
-            let cx4 = x_min4 + (x_base + x_offset) * dx4;
-            let c = Complex4 {
-                real: cx4,
-                imag: cy4,
-            };
-            let results = mandelbrot_at_vec(&c, iters);
-            let arr: [u64; 4] = results.into();
-
-            for i in 0..4 {
-                let iter_count = arr[i];
-                if iter_count == iters as u64 {
-                    chunk[i] = 255;
-                } else {
-                    chunk[i] = ((iter_count as f32) * inv_iter) as u8;
-                }
-            }
+    progress.set_length(zoom_args.frames as u64 * (view.height * calc_options.samples) as u64);
+    let frames: Vec<_> =
+        frame_pool.install(|| (0..zoom_args.frames).into_par_iter().map(render).collect());
+    progress.finish_and_clear();
+    report_cache();
+
+    let mut images = Vec::with_capacity(frames.len());
+    for (options, escaped) in frames {
+        let options = DrawOptions {
+            bit_depth: BitDepth::Eight,
+            ..options
+        };
+        images.push(color_image(
+            &escaped,
+            view.width as u32,
+            view.height as u32,
+            options,
+        )?);
+    }
+
+    save_gif(images, &args.output, zoom_args.fps)?;
+    Ok(())
+}
 
-            x += 4;
+/// Iterations of the view as wide as the whole Mandelbrot set
+const AUTO_ITERS_BASE: usize = 500;
+/// Extra iterations per halving of the width of the view
+const AUTO_ITERS_PER_DOUBLING: usize = 150;
+/// Default upper bound of the automatic number of iterations
+const AUTO_ITERS_MAX: usize = 100_000;
+/// Width of the probe pictures of --auto-iters-adaptive
+const PROBE_WIDTH: usize = 160;
+/// Share of the unescaped pixels at the boundary of the set allowed to escape with twice the
+/// iterations picked by --auto-iters-adaptive
+const PROBE_TOLERANCE: f64 = 0.05;
+
+fn auto_iters(view: &View, max: usize) -> usize {
+    let doublings = (3.0 / (view.x_max - view.x_min)).log2().max(0.0);
+    let iters = AUTO_ITERS_BASE as f64 + AUTO_ITERS_PER_DOUBLING as f64 * doublings;
+    (iters as usize).min(max)
+}
+
+/// Iterations resolving the boundary of the set in the view: probes a small picture of it
+/// from `AUTO_ITERS_BASE` iterations on and doubles them while more than `PROBE_TOLERANCE`
+/// of the unescaped pixels with an escaped neighbor escape with twice as many, or while no
+/// pixel escapes at all
+fn probe_iters(options: &CalcOptions, view: &View, max: usize) -> usize {
+    let width = PROBE_WIDTH.min(view.width);
+    let height = ((width * view.height) as f64 / view.width as f64)
+        .round()
+        .max(1.0) as usize;
+    let probe = View {
+        width,
+        height,
+        rows: (0, height),
+        columns: (0, width),
+        deadline: None,
+        ..*view
+    };
+    let counts = |iters| {
+        let options = CalcOptions {
+            iters,
+            smooth: false,
+            samples: 1,
+            adaptive: None,
+            distance: false,
+            trap: None,
+            stripe: None,
+            tia: false,
+            phase: false,
+            hsv: false,
+            lighting: None,
+            ..*options
+        };
+        let escaped = calc_escaped(&options, &probe, &ProgressBar::hidden());
+        (0..escaped.len())
+            .map(|i| escaped.get(i) < iters as f64)
+            .collect::<Vec<bool>>()
+    };
+
+    let mut iters = AUTO_ITERS_BASE.min(max);
+    let mut escaped = counts(iters);
+    while iters < max {
+        let boundary: Vec<usize> = (0..escaped.len())
+            .filter(|&i| {
+                let (x, y) = (i % width, i / width);
+                !escaped[i]
+                    && ((x > 0 && escaped[i - 1])
+                        || (x + 1 < width && escaped[i + 1])
+                        || (y > 0 && escaped[i - width])
+                        || (y + 1 < height && escaped[i + width]))
+            })
+            .collect();
+        let next_iters = (iters * 2).min(max);
+        let next = counts(next_iters);
+        let escaping = boundary.iter().filter(|&&i| next[i]).count();
+        debug!(
+            "Probe of {} iterations: {} of {} unescaped pixels at the boundary escape with {}",
+            iters,
+            escaping,
+            boundary.len(),
+            next_iters
+        );
+        if escaped.contains(&true) && escaping as f64 <= PROBE_TOLERANCE * boundary.len() as f64 {
+            break;
         }
+        iters = next_iters;
+        escaped = next;
+    }
+    iters
+}
+
+/// Fixed point number without the trailing zeros
+fn decimal(x: f64, precision: usize) -> String {
+    let s = format!("{:.*}", precision, x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    s.to_string()
+}
+
+/// Calculates, colors and saves a single picture of the view into `output`, along with
+/// the raw dump and the sidecar when asked for
+fn render(
+    args: &Args,
+    variant: Variant,
+    calc_options: &CalcOptions,
+    view: &View,
+    output: &Path,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<(), MandelbrotError> {
+    progress.reset();
+    progress.set_length(((view.rows.1 - view.rows.0) * calc_options.samples) as u64);
+    debug!(
+        "Reproduce with: {}",
+        command_line(args, variant, view, output)
+    );
+
+    let start = Instant::now();
+    let view = &View {
+        deadline: args
+            .time_limit
+            .map(|secs| start + Duration::from_secs_f64(secs)),
+        ..*view
+    };
+    let mut escaped = match args.checkpoint.as_ref().or(args.resume.as_ref()) {
+        Some(path) => pool.install(|| {
+            let resume = args.resume.is_some();
+            checkpoint::calc_resumable(calc_options, view, path, resume, progress)
+        })?,
+        None => pool.install(|| calc_escaped(calc_options, view, progress)),
+    };
+    let calc_seconds = start.elapsed().as_secs_f64();
+    debug!("Calculated in {:.3} s", calc_seconds);
+    if args.checksum {
+        info!("Checksum of the escape values: {:016x}", escaped.checksum());
+    }
+    if view
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+    {
+        warn!(
+            "Time limit of {} s reached, the picture may be partially rendered",
+            args.time_limit.unwrap_or_default()
+        );
+    }
+    let mut interior = pool.install(|| interior_of(args, &escaped, view));
+    // the region of --roi alone from here on
+    let view = &view.crop();
+    if args.flip_y {
+        escaped.flip_rows(view.width);
+        interior = flip_interior(interior, view.width);
+    }
+    progress.finish_and_clear();
 
-        for pixel in chunks.into_remainder() {
-            let cx = x_min + (x as f64) * dx;
-            let count = mandelbrot_at_point(cx, cy_val, iters);
+    if let Some(path) = &args.dump_raw {
+        let header = RawHeader {
+            width: view.width,
+            height: view.height,
+            iters: args.iters,
+        };
+        escaped.save_raw(path, &header)?;
+    }
+    if let Some(path) = &args.export_csv {
+        escaped.save_csv(path, view, args.iters)?;
+    }
+
+    let stats = (args.verbose || args.metadata_sidecar).then(|| escaped.stats(args.iters));
+    if let Some(stats) = stats.as_ref().filter(|_| args.verbose) {
+        debug!(
+            "Escape values outside the set: min {}, max {}, mean {:.2}; {:.2}% of the pixels in the set",
+            stats.min,
+            stats.max,
+            stats.mean,
+            stats.in_set * 100.0
+        );
+    }
 
-            if count == iters as u64 {
-                *pixel = 255;
+    if let Some(stats) = stats.filter(|_| args.metadata_sidecar) {
+        let sidecar = Sidecar {
+            params: Params::new(args, variant, view),
+            calc_seconds,
+            stats,
+        };
+        sidecar.save(output)?;
+    }
+
+    let options = args.draw_options(args.iters);
+    let mut img = color_image(&escaped, view.width as u32, view.height as u32, options)?;
+    paint_interior(&mut img, &interior, options);
+    let saved = if args.stdout {
+        write_ppm(&img, io::stdout().lock())
+    } else if args.embed_metadata {
+        let params = Params::new(args, variant, view);
+        save_png_with_text(&img, output, PNG_KEYWORD, &params.to_json())
+    } else {
+        save_image(img, output, args.quality)
+    };
+
+    debug!("Rendered in {:.3} s", start.elapsed().as_secs_f64());
+    saved
+}
+
+/// Rows and columns of the view calculated for --roi, the rows count from the bottom
+/// one with `flip_y` like the rows of the saved picture
+fn region(args: &Args, view: &View) -> View {
+    match args.roi.as_deref() {
+        Some(&[x, y, w, h]) => {
+            let rows = if args.flip_y {
+                (view.height - (y + h), view.height - y)
             } else {
-                *pixel = ((count as f32) * inv_iter) as u8;
+                (y, y + h)
+            };
+            View {
+                rows,
+                columns: (x, x + w),
+                ..*view
             }
-            x += 1;
         }
-    });
+        _ => *view,
+    }
+}
 
-    buf
+/// `s` as a single shell word, quoted unless it is made of the plain characters only
+fn shell_word(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-/+=,:".contains(c))
+    {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
 }
 
-#[unsafe(no_mangle)]
-#[inline(never)]
-fn mandelbrot_at_vec(c: &Complex4, iters: usize) -> [u64; 4] {
-    let mut z = *c;
-    let mut count = f64x4::splat(0.0);
-    let threshold = f64x4::splat(4.0);
+/// Command line rendering the same picture again without the config file, the bounds
+/// of the view are spelled out instead of the location, center, zoom and pan flags,
+/// the other flags are given when they differ from their defaults
+fn command_line(args: &Args, variant: Variant, view: &View, output: &Path) -> String {
+    let defaults = Args::parse_from(["mandelbrot"]);
+    let mut words = vec!["mandelbrot".to_string()];
+    let mut value = |flag: &str, value: String, default: String| {
+        if value != default {
+            words.push(format!("--{}={}", flag, shell_word(&value)));
+        }
+    };
 
-    for _ in 0..iters {
-        let rr = z.real * z.real;
-        let ii = z.imag * z.imag;
+    value("iters", args.iters.to_string(), String::new());
+    value("width", view.width.to_string(), String::new());
+    value("height", view.height.to_string(), String::new());
+    value("x-min", view.x_min.to_string(), String::new());
+    value("x-max", view.x_max.to_string(), String::new());
+    value("y-min", view.y_min.to_string(), String::new());
+    value("y-max", view.y_max.to_string(), String::new());
+    value("output", output.display().to_string(), String::new());
+    value(
+        "rotate",
+        args.rotate.to_string(),
+        defaults.rotate.to_string(),
+    );
+    value(
+        "pixel-aspect",
+        args.pixel_aspect.to_string(),
+        defaults.pixel_aspect.to_string(),
+    );
+    value("power", args.power.to_string(), defaults.power.to_string());
+    value(
+        "bailout",
+        args.bailout.to_string(),
+        defaults.bailout.to_string(),
+    );
+    value(
+        "samples",
+        args.samples.to_string(),
+        defaults.samples.to_string(),
+    );
+    value(
+        "aa-pattern",
+        name(&args.aa_pattern),
+        name(&defaults.aa_pattern),
+    );
+    if args.aa_pattern == SamplePattern::Random {
+        let default = defaults.aa_seed.to_string();
+        value("aa-seed", args.aa_seed.to_string(), default);
+    }
+    if args.adaptive_aa {
+        let default = defaults.aa_threshold.to_string();
+        value("aa-threshold", args.aa_threshold.to_string(), default);
+    }
+    value(
+        "simd-width",
+        name(&args.simd_width),
+        name(&defaults.simd_width),
+    );
+    value(
+        "precision",
+        name(&args.precision),
+        name(&defaults.precision),
+    );
+    value(
+        "color-mode",
+        name(&args.color_mode),
+        name(&defaults.color_mode),
+    );
+    if args.color_mode == ColorMode::Stripe {
+        let default = defaults.stripe_density.to_string();
+        value("stripe-density", args.stripe_density.to_string(), default);
+    }
+    if args.color_mode == ColorMode::Hsv {
+        let default = defaults.hue_scale.to_string();
+        value("hue-scale", args.hue_scale.to_string(), default);
+    }
+    if args.color_mode == ColorMode::Lighting {
+        let default = defaults.light_angle.to_string();
+        value("light-angle", args.light_angle.to_string(), default);
+        let default = defaults.light_elevation.to_string();
+        value("light-elevation", args.light_elevation.to_string(), default);
+    }
+    if let Some(trap) = &args.trap {
+        value("trap", name(trap), String::new());
+    }
+    if let Some(interior) = &args.interior_color {
+        value("interior-color", name(interior), String::new());
+    }
+    value("palette", name(&args.palette), name(&defaults.palette));
+    if let Some(path) = &args.palette_image {
+        value("palette-image", path.display().to_string(), String::new());
+    }
+    if let Some(seed) = args.random_palette {
+        value("random-palette", seed.to_string(), String::new());
+    }
+    value(
+        "palette-space",
+        name(&args.palette_space),
+        name(&defaults.palette_space),
+    );
+    let offset = args.palette_offset.to_string();
+    value(
+        "palette-offset",
+        offset,
+        defaults.palette_offset.to_string(),
+    );
+    if let Some(Rgb([r, g, b])) = args.set_color {
+        value("set-color", format!("{},{},{}", r, g, b), String::new());
+    }
+    if let Some(Rgb([r, g, b])) = args.degenerate_color {
+        value(
+            "degenerate-color",
+            format!("{},{},{}", r, g, b),
+            String::new(),
+        );
+    }
+    value("gamma", args.gamma.to_string(), defaults.gamma.to_string());
+    value("scale", name(&args.scale), name(&defaults.scale));
+    if let Some(dither) = &args.dither {
+        value("dither", name(dither), String::new());
+    }
+    value(
+        "bit-depth",
+        name(&args.bit_depth),
+        name(&defaults.bit_depth),
+    );
+    if let Some(quality) = args.quality {
+        value("quality", quality.to_string(), String::new());
+    }
 
-        let mask = (rr + ii).simd_le(threshold);
+    let switches = [
+        ("smooth", args.smooth),
+        ("adaptive-aa", args.adaptive_aa),
+        ("deep", args.deep),
+        ("no-fast-interior", args.no_fast_interior),
+        ("mask", args.mask),
+        ("invert", args.invert),
+        ("flip-y", args.flip_y),
+        ("embed-metadata", args.embed_metadata),
+    ];
+    for (flag, _) in switches.iter().filter(|(_, on)| *on) {
+        words.push(format!("--{}", flag));
+    }
+    if let Some(roi) = &args.roi {
+        words.push("--roi".to_string());
+        words.extend(roi.iter().map(|v| v.to_string()));
+    }
+    if let Some(clamp) = &args.color_clamp {
+        words.push("--color-clamp".to_string());
+        words.extend(clamp.iter().map(|v| shell_word(&v.to_string())));
+    }
+    if variant == Variant::BurningShip {
+        words.push("burning-ship".to_string());
+    }
+    if variant == Variant::Tricorn {
+        words.push("tricorn".to_string());
+    }
 
-        if !mask.any() {
-            break;
+    words.join(" ")
+}
+
+/// `path` with the size appended to the file stem, "image_640x480.png" for "image.png"
+fn sized_path(path: &Path, width: usize, height: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_{}x{}{}", stem, width, height, ext))
+}
+
+/// Calculates and colors the picture band by band of `tile_height` rows, the bands
+/// come from the bottom ones flipped with --flip-y
+fn tiled(
+    args: &Args,
+    variant: Variant,
+    calc_options: &CalcOptions,
+    view: &View,
+    tile_height: usize,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<(), MandelbrotError> {
+    let options = args.draw_options(args.iters);
+
+    let mut bands: Vec<(usize, usize)> = (0..view.height)
+        .step_by(tile_height)
+        .map(|first| (first, (first + tile_height).min(view.height)))
+        .collect();
+    if args.flip_y {
+        bands.reverse();
+    }
+
+    let colored = bands.into_iter().map(|rows| {
+        let band_view = View { rows, ..*view };
+        let (mut escaped, mut interior) = pool.install(|| {
+            let escaped = calc_escaped(calc_options, &band_view, progress);
+            let interior = interior_of(args, &escaped, &band_view);
+            (escaped, interior)
+        });
+        if args.flip_y {
+            escaped.flip_rows(view.width);
+            interior = flip_interior(interior, view.width);
         }
+        let mut img = color_image(
+            &escaped,
+            view.width as u32,
+            (rows.1 - rows.0) as u32,
+            options,
+        )?;
+        paint_interior(&mut img, &interior, options);
+        Ok(img)
+    });
+
+    let params = Params::new(args, variant, view).to_json();
+    let text = args
+        .embed_metadata
+        .then_some((PNG_KEYWORD, params.as_str()));
+    let saved = save_image_bands(
+        colored,
+        view.width as u32,
+        view.height as u32,
+        &args.output,
+        args.quality,
+        text,
+    );
+    progress.finish_and_clear();
+    saved
+}
+
+/// Interior metrics of the points of the set with --interior-color, empty otherwise
+fn interior_of(args: &Args, escaped: &Escaped, view: &View) -> Vec<f64> {
+    match args.interior_color.filter(|_| !args.mask) {
+        Some(interior) => calc_interior(
+            escaped,
+            view,
+            args.iters,
+            args.bailout * args.bailout,
+            interior,
+        ),
+        None => Vec::new(),
+    }
+}
 
-        count += mask.blend(f64x4::splat(1.0), f64x4::splat(0.0));
+/// Interior metrics in the order of the rows flipped by --flip-y
+fn flip_interior(interior: Vec<f64>, width: usize) -> Vec<f64> {
+    interior.chunks(width).rev().flatten().copied().collect()
+}
+
+/// Mean, median and standard deviation of the timed calculations of the view
+fn bench(
+    bench_args: &BenchArgs,
+    calc_options: &CalcOptions,
+    view: &View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> String {
+    let rows = (view.height * calc_options.samples) as u64;
+    progress.set_length((bench_args.warmup + bench_args.runs) as u64 * rows);
 
-        let ri = z.real * z.imag;
-        z.real = rr - ii + c.real;
-        z.imag = ri + ri + c.imag;
+    let run = || {
+        let start = Instant::now();
+        black_box(pool.install(|| calc_escaped(calc_options, view, progress)));
+        start.elapsed().as_secs_f64()
+    };
+    for _ in 0..bench_args.warmup {
+        run();
     }
+    let mut seconds: Vec<f64> = (0..bench_args.runs).map(|_| run()).collect();
+    progress.finish_and_clear();
+
+    seconds.sort_by(f64::total_cmp);
+    let n = seconds.len();
+    let mean = seconds.iter().sum::<f64>() / n as f64;
+    let median = (seconds[(n - 1) / 2] + seconds[n / 2]) / 2.0;
+    let variance = seconds.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n as f64;
+    let megapixels = (view.width * view.height) as f64 / 1e6;
 
-    let arr: [f64; 4] = count.into();
-    [arr[0] as u64, arr[1] as u64, arr[2] as u64, arr[3] as u64]
+    format!(
+        "{} runs of {}x{} pixels: mean {:.3} s, median {:.3} s, stddev {:.3} s, {:.2} Mpx/s",
+        n,
+        view.width,
+        view.height,
+        mean,
+        median,
+        variance.sqrt(),
+        megapixels / mean
+    )
 }
 
-#[unsafe(no_mangle)]
-#[inline(never)]
-fn mandelbrot_at_point(cx: f64, cy: f64, iters: usize) -> u64 {
-    let mut z = Complex::new(0.0, 0.0);
-    let c = Complex::new(cx, cy);
+/// Duration of a single calculation of the view and the checksum of the escape values,
+/// which keeps the calculation from being optimized away
+fn calc_only(
+    calc_options: &CalcOptions,
+    view: &View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> String {
+    progress.set_length((view.height * calc_options.samples) as u64);
+    let start = Instant::now();
+    let escaped = pool.install(|| calc_escaped(calc_options, view, progress));
+    let seconds = start.elapsed().as_secs_f64();
+    progress.finish_and_clear();
+
+    format!(
+        "Calculated {}x{} pixels in {:.3} s, checksum {:016x}",
+        view.width,
+        view.height,
+        seconds,
+        escaped.checksum()
+    )
+}
+
+/// Area of the view times the share of the pixels of the set or the share of the random
+/// points in it with `--samples`
+fn area(
+    area_args: &AreaArgs,
+    calc_options: &CalcOptions,
+    view: &View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<String, String> {
+    if view.x_min >= view.x_max || view.y_min >= view.y_max {
+        return Err("the view has no area".to_string());
+    }
+    let view_area = (view.x_max - view.x_min) * (view.y_max - view.y_min);
+
+    let Some(samples) = area_args.samples else {
+        let escaped = pool.install(|| calc_escaped(calc_options, view, progress));
+        progress.finish_and_clear();
+        let share = escaped.stats(calc_options.iters).in_set;
+        return Ok(format!(
+            "Area of the set in the view: {} ({} of {} pixels)",
+            share * view_area,
+            (share * escaped.len() as f64).round(),
+            escaped.len()
+        ));
+    };
+
+    progress.set_length(samples.div_ceil(RANDOM_CHUNK));
+    let inside = pool.install(|| {
+        count_random_in_set(
+            calc_options.iters,
+            calc_options.threshold,
+            samples,
+            view,
+            progress,
+        )
+    });
+    progress.finish_and_clear();
+
+    // normal approximation of the binomial share
+    let share = inside as f64 / samples as f64;
+    let margin = 1.96 * (share * (1.0 - share) / samples as f64).sqrt();
+    Ok(format!(
+        "Area of the set in the view: {:.6} ± {:.6} (95% confidence, {} of {} random points)",
+        share * view_area,
+        margin * view_area,
+        inside,
+        samples
+    ))
+}
+
+/// Traces the orbits of the random points and saves their density in grayscale
+fn buddhabrot(
+    args: &Args,
+    buddhabrot_args: &BuddhabrotArgs,
+    threshold: f64,
+    view: &View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<(), MandelbrotError> {
+    let samples = buddhabrot_args.samples;
+    progress.set_length(samples.div_ceil(RANDOM_CHUNK));
+    let mut bins = pool.install(|| calc_buddhabrot(args.iters, threshold, samples, view, progress));
+    progress.finish_and_clear();
+
+    if args.flip_y {
+        bins = bins.chunks(view.width).rev().flatten().copied().collect();
+    }
+    let mut img = buddhabrot_image(&bins, view.width as u32, view.height as u32, args.bit_depth)?;
+    if args.invert {
+        img.invert();
+    }
+    save_image(img, &args.output, args.quality)
+}
 
-    for i in 0..=iters {
-        if z.norm_sqr() > 4.0 {
-            return i as u64;
+/// Finds the roots the Newton iterations of the pixels converge to and saves their basins
+fn newton(
+    args: &Args,
+    view: &View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<(), MandelbrotError> {
+    progress.set_length(view.height as u64);
+    let mut points = pool.install(|| calc_newton(args.iters, view, progress));
+    progress.finish_and_clear();
+
+    if args.flip_y {
+        points = points.chunks(view.width).rev().flatten().copied().collect();
+    }
+    let mut img = newton_image(&points, view.width as u32, view.height as u32)?;
+    if args.invert {
+        img.invert();
+    }
+    save_image(img, &args.output, args.quality)
+}
+
+/// Colors the escape values of the view and draws the orbit of the point over them
+fn orbit(
+    args: &Args,
+    orbit_args: &OrbitArgs,
+    calc_options: &CalcOptions,
+    view: &View,
+    pool: &ThreadPool,
+    progress: &ProgressBar,
+) -> Result<(), MandelbrotError> {
+    progress.set_length((view.height * calc_options.samples) as u64);
+    let escaped = pool.install(|| calc_escaped(calc_options, view, progress));
+    progress.finish_and_clear();
+
+    let [re, im] = orbit_args.point[..] else {
+        unreachable!("clap takes 2 values of --point")
+    };
+    let points = mandelbrot_orbit(re, im, args.iters, calc_options.threshold);
+    match mandelbrot_at_point(re, im, args.iters, calc_options.threshold, false) {
+        count if count == args.iters as u64 => {
+            info!("Orbit stays bounded for {} iterations", args.iters)
         }
-        z = z * z + c;
+        count => info!("Orbit escapes with the escape count {}", count),
     }
 
-    iters as u64
+    let options = args.draw_options(args.iters);
+    let img = color_image(&escaped, view.width as u32, view.height as u32, options)?;
+    let mut img = img.to_rgb8();
+    draw_orbit(&mut img, view, &points);
+    if args.flip_y {
+        imageops::flip_vertical_in_place(&mut img);
+    }
+    save_image(DynamicImage::ImageRgb8(img), &args.output, args.quality)
 }
 
-fn draw_mandelbrot(escaped: Vec<u8>, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
-    let img = match GrayImage::from_raw(width, height, escaped) {
-        Some(im) => im,
-        None => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                format!("Invalid raw_img size"),
-            )))
+/// Converts between the pixels of the view and the points of the complex plane the same way
+/// as the calculation, the rows count from the bottom one with `flip_y`
+fn coord(coord_args: &CoordArgs, view: &View, flip_y: bool) {
+    let flip = |y: usize| if flip_y { view.height - 1 - y } else { y };
+
+    if let Some([x, y]) = coord_args.pixel.as_deref() {
+        let row = (*y < view.height).then(|| flip(*y));
+        match row.and_then(|row| view.pixel_to_complex(*x, row)) {
+            Some((re, im)) => println!("Pixel ({}, {}) is the point {} {:+}i", x, y, re, im),
+            None => error!(
+                "Handled error: pixel ({}, {}) is outside of the {}x{} picture",
+                x, y, view.width, view.height
+            ),
+        }
+    }
+
+    if let Some([re, im]) = coord_args.complex.as_deref() {
+        match view.complex_to_pixel(*re, *im) {
+            Some((x, y)) => println!(
+                "Point {} {:+}i is in the pixel ({}, {})",
+                re,
+                im,
+                x,
+                flip(y)
+            ),
+            None => println!(
+                "Point {} {:+}i is off-screen, the view spans {}..{} and {}..{}i",
+                re, im, view.x_min, view.x_max, view.y_min, view.y_max
+            ),
         }
+    }
+}
+
+/// Colors the escape values of the raw dump, the size and the number of iterations
+/// come from its header
+fn recolor(args: &Args, input: &Path) -> Result<(), MandelbrotError> {
+    let (header, escaped) = Escaped::load_raw(input)?;
+    let options = args.draw_options(header.iters);
+    if let Some(path) = &args.legend {
+        legend::save(path, options, args.legend_size, args.quality)?;
+    }
+    let img = color_image(&escaped, header.width as u32, header.height as u32, options)?;
+    save_image(img, &args.output, args.quality)
+}
+
+/// Axis bounds expanded symmetrically along the shorter axis to the aspect ratio of the
+/// picture on a display of `pixel_aspect` wide pixels with `fix`, otherwise the same bounds
+/// and a warning when they stretch the picture by more than a pixel
+fn fit_aspect(
+    bounds: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    pixel_aspect: f64,
+    fix: bool,
+) -> (f64, f64, f64, f64) {
+    let (x_min, x_max, y_min, y_max) = bounds;
+    let (dx, dy) = (x_max - x_min, y_max - y_min);
+    let aspect = width as f64 * pixel_aspect / height as f64;
+    // above 1 the features get squeezed horizontally, below 1 vertically
+    let ratio = dx / dy / aspect;
+    if !(dx > 0.0 && dy > 0.0) || (ratio - 1.0).abs() * (width.max(height) as f64) < 1.0 {
+        return bounds;
+    }
+
+    if !fix {
+        let (squeeze, axis) = if ratio > 1.0 {
+            (1.0 - ratio.recip(), "horizontally")
+        } else {
+            (1.0 - ratio, "vertically")
+        };
+        warn!(
+            "The axis bounds of aspect ratio {:.4} squeeze the picture of aspect ratio {:.4} \
+             by {:.1}% {}, pass --fix-aspect to expand the bounds instead",
+            dx / dy,
+            aspect,
+            squeeze * 100.0,
+            axis
+        );
+        return bounds;
+    }
+
+    let (cx, cy) = ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0);
+    let (dx, dy) = if ratio > 1.0 {
+        (dx, dx / aspect)
+    } else {
+        (dy * aspect, dy)
     };
+    debug!(
+        "Expanded the axis bounds to x {}..{}, y {}..{}",
+        cx - dx / 2.0,
+        cx + dx / 2.0,
+        cy - dy / 2.0,
+        cy + dy / 2.0
+    );
+    (cx - dx / 2.0, cx + dx / 2.0, cy - dy / 2.0, cy + dy / 2.0)
+}
 
-    img.save("image.png")?;
+/// `path` itself when nothing is there, otherwise the path with the first free index
+/// appended to the file stem, "image_1.png" for "image.png"
+fn free_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
 
-    Ok(())
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|i| path.with_file_name(format!("{}_{}{}", stem, i, ext)))
+        .find(|candidate| !candidate.exists())
+        .expect("some index is free")
+}
+
+/// Sends the log events to stdout (stderr with --stdout) without decorations, --quiet keeps
+/// the errors and the warnings only and --verbose adds the details of the render
+fn init_logging(args: &Args) {
+    let level = if args.quiet {
+        LevelFilter::WARN
+    } else if args.verbose {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+
+    let to_stderr = args.stdout;
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(move || -> Box<dyn io::Write> {
+            if to_stderr {
+                Box::new(io::stderr())
+            } else {
+                Box::new(io::stdout())
+            }
+        })
+        .with_ansi(false)
+        .without_time()
+        .with_level(false)
+        .with_target(false)
+        .init();
+}
+
+fn print_locations() {
+    let width = Location::all()
+        .iter()
+        .map(|loc| loc.to_string().len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+    for loc in Location::all() {
+        let (cx, cy, zoom) = loc.center_zoom();
+        // enough digits to tell apart the neighbouring pixels of a 4K picture
+        let precision = zoom.log10().max(0.0).ceil() as usize + 4;
+        println!("{:<width$}{}", loc.to_string(), loc.description());
+        println!(
+            "{:<width$}--center-re {} --center-im {} --zoom {}",
+            "",
+            decimal(cx, precision),
+            decimal(cy, precision),
+            decimal(zoom, 2)
+        );
+    }
 }
 
 fn main() {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let aspect = args.width as f64 / args.height as f64;
+    if let Some(Command::Locations) = args.command {
+        print_locations();
+        return;
+    }
 
-    let (x_min, x_max, y_min, y_max) = if let Some(loc) = args.location {
-        loc.coords(aspect)
-    } else {
-        (args.x_min, args.x_max, args.y_min, args.y_max)
+    // the config may set the verbosity, so its errors get reported once the logging is set up
+    let config_error = match args.config.as_deref().map(Config::load) {
+        Some(Ok(config)) => {
+            config.merge(&mut args, &matches);
+            None
+        }
+        Some(Err(e)) => Some(e),
+        None => None,
     };
+    if let Some((re, im, zoom)) = args.view {
+        (args.center_re, args.center_im, args.zoom) = (Some(re), Some(im), Some(zoom));
+    }
+    init_logging(&args);
+    if let Some(e) = config_error {
+        error!("Handled error: {}", e);
+        return;
+    }
+
+    if let Some(path) = &args.palette_image {
+        match Gradient::load(path) {
+            Ok(gradient) => args.gradient = Some(gradient),
+            Err(e) => {
+                error!("Handled error: \"{}\": {}", path.display(), e);
+                return;
+            }
+        }
+    }
+    if let Some(seed) = args.random_palette {
+        args.gradient = Some(Gradient::random(seed));
+    }
+    match args.gradient.take() {
+        Some(gradient) => args.gradient = Some(gradient.in_space(args.palette_space)),
+        None if args.palette_space != PaletteSpace::Rgb => {
+            warn!("--palette-space interpolates --palette-image and --random-palette only");
+        }
+        None => {}
+    }
+
+    if args.no_overwrite {
+        args.output = free_path(&args.output);
+    }
+    let webp = args
+        .output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("webp"));
+    if webp && args.quality.is_some() {
+        error!("Handled error: WebP pictures are saved lossless only, --quality applies to JPEG");
+        return;
+    }
+
+    if args.legend.is_some()
+        && (args.mask
+            || args.trap.is_some()
+            || !matches!(args.color_mode, ColorMode::Linear | ColorMode::Lighting))
+    {
+        error!(
+            "Handled error: --legend keys the escape counts of the linear and lighting color \
+             modes only, without --mask and --trap"
+        );
+        return;
+    }
+    if args.legend.is_some()
+        && (args.no_save
+            || matches!(
+                args.command,
+                Some(Command::Zoom(_))
+                    | Some(Command::Buddhabrot(_))
+                    | Some(Command::Newton)
+                    | Some(Command::Orbit(_))
+                    | Some(Command::Area(_))
+                    | Some(Command::Bench(_))
+                    | Some(Command::Coord(_))
+            ))
+    {
+        error!("Handled error: --legend supports single pictures and recolor only");
+        return;
+    }
+
+    if let Some(Command::Recolor(recolor_args)) = &args.command {
+        if args.stdout || args.no_save {
+            error!("Handled error: --stdout and --no-save support calculated pictures only");
+            return;
+        }
+        if args.interior_color.is_some() {
+            error!("Handled error: the raw dump keeps no orbits for interior coloring");
+            return;
+        }
+        match recolor(&args, &recolor_args.input) {
+            Ok(_) => info!("Successed save image as \"{}\"", args.output.display()),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
+
+    let variant = args.variant();
+    let view = args.view(variant);
 
-    let escaped = calc_mandelbrot(
-        args.iters,
-        x_min,
-        x_max,
-        y_min,
-        y_max,
-        args.width,
-        args.height,
+    if let Some(Command::Coord(coord_args)) = &args.command {
+        coord(coord_args, &view, args.flip_y);
+        return;
+    }
+
+    if args.auto_iters && args.auto_iters_adaptive {
+        error!("Handled error: --auto-iters and --auto-iters-adaptive are exclusive");
+        return;
+    }
+    if args.auto_iters_adaptive
+        && matches!(
+            args.command,
+            Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+        )
+    {
+        error!("Handled error: --auto-iters-adaptive probes escape-time fractals only");
+        return;
+    }
+    if args.auto_iters {
+        args.iters = auto_iters(&view, args.auto_iters_max);
+        info!("Using {} iterations", args.iters);
+    }
+    debug!(
+        "View: real {}..{}, imaginary {}..{}, {}x{} pixels",
+        view.x_min, view.x_max, view.y_min, view.y_max, view.width, view.height
     );
+    debug!("Iterations: {}", args.iters);
+    if !args.deep
+        && args.precision != Precision::Dd
+        && view.beyond_f64()
+        && !matches!(
+            args.command,
+            Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+        )
+    {
+        if args.strict_precision {
+            error!(
+                "Handled error: the pixels of the view are too small for f64 coordinates with \
+                 --strict-precision, pass --deep to calculate the Mandelbrot set by \
+                 perturbation (or the slower --precision dd)"
+            );
+            return;
+        }
+        warn!(
+            "The pixels of the view are too small for f64 coordinates and will blur into \
+             blocks, pass --deep to calculate the Mandelbrot set by perturbation (or the slower \
+             --precision dd)"
+        );
+    }
+
+    let threshold = args.bailout * args.bailout;
+    let simd_width = if args.simd_width.supported() {
+        args.simd_width
+    } else {
+        warn!("AVX-512 is not available, falling back to 4 SIMD lanes");
+        SimdWidth::Four
+    };
+
+    let png_output = args
+        .output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    if args.embed_metadata && !png_output {
+        error!("Handled error: embedding of the metadata supports png output only");
+        return;
+    }
+    if args.color_mode == ColorMode::Distance && variant != Variant::Mandelbrot {
+        error!("Handled error: distance coloring supports the Mandelbrot set only");
+        return;
+    }
+    if args.color_mode == ColorMode::Stripe && variant != Variant::Mandelbrot {
+        error!("Handled error: stripe coloring supports the Mandelbrot set only");
+        return;
+    }
+    if args.color_mode == ColorMode::Tia && variant != Variant::Mandelbrot {
+        error!("Handled error: triangle inequality coloring supports the Mandelbrot set only");
+        return;
+    }
+    if args.color_mode == ColorMode::Phase && variant != Variant::Mandelbrot {
+        error!("Handled error: phase coloring supports the Mandelbrot set only");
+        return;
+    }
+    if args.color_mode == ColorMode::Phase && args.samples > 1 {
+        error!(
+            "Handled error: phase coloring can't average the samples across the wrap of the hue"
+        );
+        return;
+    }
+    if args.color_mode == ColorMode::Hsv && variant != Variant::Mandelbrot {
+        error!("Handled error: hsv coloring supports the Mandelbrot set only");
+        return;
+    }
+    if args.color_mode == ColorMode::Hsv && args.adaptive_aa {
+        error!("Handled error: hsv coloring supports the full supersampling only, without --adaptive-aa");
+        return;
+    }
+    if args.color_mode == ColorMode::Lighting && variant != Variant::Mandelbrot {
+        error!("Handled error: lighting supports the Mandelbrot set only");
+        return;
+    }
+    if args.color_mode == ColorMode::Lighting && args.adaptive_aa {
+        error!(
+            "Handled error: lighting supports the full supersampling only, without --adaptive-aa"
+        );
+        return;
+    }
+    if !args.light_angle.is_finite() {
+        error!("Handled error: light angle must be a finite number of degrees");
+        return;
+    }
+    if args.trap.is_some() && variant != Variant::Mandelbrot {
+        error!("Handled error: orbit trap coloring supports the Mandelbrot set only");
+        return;
+    }
+    if args.interior_color.is_some() && (variant != Variant::Mandelbrot || args.trap.is_some()) {
+        error!("Handled error: interior coloring supports the Mandelbrot set without --trap only");
+        return;
+    }
+    if args.deep
+        && (variant != Variant::Mandelbrot
+            || args.precision != Precision::F64
+            || args.trap.is_some()
+            || args.interior_color.is_some()
+            || matches!(
+                args.color_mode,
+                ColorMode::Distance
+                    | ColorMode::Stripe
+                    | ColorMode::Tia
+                    | ColorMode::Phase
+                    | ColorMode::Hsv
+                    | ColorMode::Lighting
+            )
+            || matches!(
+                args.command,
+                Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+            ))
+    {
+        error!(
+            "Handled error: --deep supports the escape counts of the Mandelbrot set in f64 only, \
+             without --trap, --interior-color and the distance, stripe, tia, phase, hsv and \
+             lighting color modes"
+        );
+        return;
+    }
+    if args.interior_color.is_some()
+        && matches!(
+            args.command,
+            Some(Command::Zoom(_)) | Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+        )
+    {
+        error!("Handled error: interior coloring supports single pictures only");
+        return;
+    }
+    if args.time_limit.is_some()
+        && (matches!(
+            args.command,
+            Some(Command::Zoom(_)) | Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+        ) || args.tile_height.is_some())
+    {
+        error!("Handled error: --time-limit supports single pictures without --tile-height only");
+        return;
+    }
+    if (args.checkpoint.is_some() || args.resume.is_some())
+        && matches!(
+            args.command,
+            Some(Command::Zoom(_))
+                | Some(Command::Buddhabrot(_))
+                | Some(Command::Newton)
+                | Some(Command::Orbit(_))
+                | Some(Command::Area(_))
+                | Some(Command::Bench(_))
+        )
+    {
+        error!("Handled error: --checkpoint and --resume support single pictures only");
+        return;
+    }
+    if args.stdout
+        && matches!(
+            args.command,
+            Some(Command::Zoom(_)) | Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+        )
+    {
+        error!("Handled error: --stdout supports single pictures only");
+        return;
+    }
+    if args.no_save
+        && matches!(
+            args.command,
+            Some(Command::Zoom(_))
+                | Some(Command::Buddhabrot(_))
+                | Some(Command::Newton)
+                | Some(Command::Bench(_))
+        )
+    {
+        error!("Handled error: --no-save times the calculation of single pictures only");
+        return;
+    }
+    if matches!(args.command, Some(Command::Orbit(_)))
+        && (variant != Variant::Mandelbrot
+            || args.interior_color.is_some()
+            || args.time_limit.is_some()
+            || args.tile_height.is_some()
+            || args.sizes.is_some()
+            || args.dump_raw.is_some()
+            || args.export_csv.is_some()
+            || args.stdout)
+    {
+        error!(
+            "Handled error: orbit supports single pictures of the Mandelbrot set only, without \
+             --interior-color, --time-limit, --tile-height, --sizes, --dump-raw, --export-csv \
+             and --stdout"
+        );
+        return;
+    }
+    if args.adaptive_aa && args.samples == 1 {
+        error!("Handled error: --adaptive-aa needs --samples above 1");
+        return;
+    }
+    if args.aa_pattern != SamplePattern::Grid && args.samples == 1 {
+        error!("Handled error: --aa-pattern needs --samples above 1");
+        return;
+    }
+    if let Some(&[low, high]) = args.color_clamp.as_deref() {
+        if !(low < high && low.is_finite() && high.is_finite()) {
+            error!(
+                "Handled error: --color-clamp {} {} needs a finite LOW below HIGH",
+                low, high
+            );
+            return;
+        }
+        if args.color_mode != ColorMode::Linear || args.trap.is_some() {
+            error!("Handled error: --color-clamp stretches the escape counts of the linear color mode only");
+            return;
+        }
+    }
+    if args.scalar
+        && (variant != Variant::Mandelbrot
+            || args.smooth
+            || args.deep
+            || args.precision != Precision::F64
+            || args.trap.is_some()
+            || !matches!(args.color_mode, ColorMode::Linear | ColorMode::Histogram))
+    {
+        error!(
+            "Handled error: --scalar replaces the kernel of the escape counts of the Mandelbrot \
+             set only, without --smooth, --deep, --precision, --trap and the color modes of \
+             other escape values"
+        );
+        return;
+    }
+    if args.mask && args.trap.is_some() {
+        error!("Handled error: orbit trap coloring keeps no membership of the set for --mask");
+        return;
+    }
+    if let Some(&[x, y, w, h]) = args.roi.as_deref() {
+        if w == 0 || h == 0 || x + w > args.width || y + h > args.height {
+            error!(
+                "Handled error: --roi {} {} {} {} isn't a region of the {}x{} picture",
+                x, y, w, h, args.width, args.height
+            );
+            return;
+        }
+        if args.rotate != 0.0
+            || matches!(
+                args.command,
+                Some(Command::Zoom(_))
+                    | Some(Command::Buddhabrot(_))
+                    | Some(Command::Newton)
+                    | Some(Command::Orbit(_))
+                    | Some(Command::Area(_))
+                    | Some(Command::Bench(_))
+            )
+            || args.no_save
+        {
+            error!("Handled error: --roi supports single unrotated pictures only");
+            return;
+        }
+    }
+    if let Some(Command::Zoom(zoom_args)) = &args.command {
+        // the adaptive samples look beyond the tiles
+        if zoom_args.tile_cache && args.adaptive_aa {
+            error!(
+                "Handled error: --tile-cache reuses the tiles of frames without --adaptive-aa only"
+            );
+            return;
+        }
+    }
+    if let Some(sizes) = &args.sizes {
+        if matches!(
+            args.command,
+            Some(Command::Zoom(_)) | Some(Command::Buddhabrot(_)) | Some(Command::Newton)
+        ) || args.tile_height.is_some()
+            || args.dump_raw.is_some()
+            || args.export_csv.is_some()
+        {
+            error!(
+                "Handled error: --sizes supports single pictures without --tile-height, \
+                 --dump-raw and --export-csv only"
+            );
+            return;
+        }
+        // a pixel of rounding either way
+        let aspect = view.width as f64 / view.height as f64;
+        if let Some((width, height)) = sizes.iter().find(|&&(width, height)| {
+            (width as f64 - height as f64 * aspect).abs() > aspect.max(1.0)
+        }) {
+            error!(
+                "Handled error: size {}x{} would stretch the view of aspect ratio {:.4}",
+                width, height, aspect
+            );
+            return;
+        }
+    }
+    if args.tile_height.is_some()
+        && (args.color_mode == ColorMode::Histogram
+            || args.verbose
+            || args.metadata_sidecar
+            || args.dump_raw.is_some()
+            || args.export_csv.is_some())
+    {
+        error!(
+            "Handled error: tiled rendering doesn't keep the escape values of the whole picture \
+             needed by histogram coloring, --verbose, --metadata-sidecar, --dump-raw and \
+             --export-csv"
+        );
+        return;
+    }
+
+    let calc_options = CalcOptions {
+        variant,
+        iters: args.iters,
+        threshold,
+        smooth: args.smooth,
+        simd_width,
+        precision: args.precision,
+        fast_interior: !args.no_fast_interior,
+        samples: args.samples as usize,
+        pattern: args.aa_pattern,
+        seed: args.aa_seed,
+        distance: args.color_mode == ColorMode::Distance,
+        trap: args.trap,
+        stripe: (args.color_mode == ColorMode::Stripe).then_some(args.stripe_density),
+        tia: args.color_mode == ColorMode::Tia,
+        phase: args.color_mode == ColorMode::Phase,
+        hsv: args.color_mode == ColorMode::Hsv,
+        lighting: (args.color_mode == ColorMode::Lighting).then(|| {
+            (
+                args.light_angle.to_radians(),
+                args.light_elevation.to_radians(),
+            )
+        }),
+        scalar: args.scalar,
+        deep: args.deep,
+        adaptive: args.adaptive_aa.then_some(args.aa_threshold),
+    };
+
+    let progress = if args.progress {
+        let bar = ProgressBar::new((view.height * calc_options.samples) as u64);
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} rows, ETA {eta}")
+                .expect("valid progress template"),
+        );
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+
+    let pool = match ThreadPoolBuilder::new().num_threads(args.threads).build() {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Handled error: {}", e);
+            return;
+        }
+    };
+    debug!("Threads: {}", pool.current_num_threads());
+
+    let calc_options = if args.auto_iters_adaptive {
+        args.iters = pool.install(|| probe_iters(&calc_options, &view, args.auto_iters_max));
+        info!("Using {} iterations", args.iters);
+        CalcOptions {
+            iters: args.iters,
+            ..calc_options
+        }
+    } else {
+        calc_options
+    };
+
+    if let Some(Command::Zoom(zoom_args)) = &args.command {
+        let target = zoom_args.frame_sequence.as_ref().unwrap_or(&args.output);
+        match zoom(&args, zoom_args, &calc_options, view, &pool, &progress) {
+            Ok(_) => info!("Successed save animation as \"{}\"", target.display()),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
+
+    if let Some(Command::Area(area_args)) = &args.command {
+        if area_args.samples.is_some() && variant != Variant::Mandelbrot {
+            error!("Handled error: random sampling of the area supports the Mandelbrot set only");
+            return;
+        }
+        match area(area_args, &calc_options, &view, &pool, &progress) {
+            Ok(msg) => info!("{}", msg),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
+
+    if let Some(Command::Bench(bench_args)) = &args.command {
+        info!(
+            "{}",
+            bench(bench_args, &calc_options, &view, &pool, &progress)
+        );
+        return;
+    }
+
+    if args.no_save {
+        info!("{}", calc_only(&calc_options, &view, &pool, &progress));
+        return;
+    }
+
+    if let Some(Command::Buddhabrot(buddhabrot_args)) = &args.command {
+        match buddhabrot(&args, buddhabrot_args, threshold, &view, &pool, &progress) {
+            Ok(_) => info!("Successed save image as \"{}\"", args.output.display()),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
 
-    let _ = match draw_mandelbrot(escaped, args.width as u32, args.height as u32) {
-        Ok(_) => println!("Successed save image as \"image.png\""),
-        Err(e) => println!("Handled error: {}", e),
+    if let Some(Command::Newton) = args.command {
+        match newton(&args, &view, &pool, &progress) {
+            Ok(_) => info!("Successed save image as \"{}\"", args.output.display()),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
+
+    if let Some(Command::Orbit(orbit_args)) = &args.command {
+        match orbit(&args, orbit_args, &calc_options, &view, &pool, &progress) {
+            Ok(_) => info!("Successed save image as \"{}\"", args.output.display()),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
+
+    if let Some(path) = &args.legend {
+        let options = args.draw_options(args.iters);
+        if let Err(e) = legend::save(path, options, args.legend_size, args.quality) {
+            error!("Handled error: {}", e);
+            return;
+        }
+    }
+
+    if let Some(tile_height) = args.tile_height.map(|h| h as usize) {
+        match tiled(
+            &args,
+            variant,
+            &calc_options,
+            &view,
+            tile_height,
+            &pool,
+            &progress,
+        ) {
+            Ok(_) => info!("Successed save image as \"{}\"", args.output.display()),
+            Err(e) => error!("Handled error: {}", e),
+        };
+        return;
+    }
+
+    let targets = match &args.sizes {
+        Some(sizes) => sizes
+            .iter()
+            .map(|&(width, height)| {
+                let sized = View {
+                    width,
+                    height,
+                    rows: (0, height),
+                    columns: (0, width),
+                    ..view
+                };
+                (sized, sized_path(&args.output, width, height))
+            })
+            .collect(),
+        None => vec![(region(&args, &view), args.output.clone())],
     };
+
+    for (view, output) in targets {
+        match render(
+            &args,
+            variant,
+            &calc_options,
+            &view,
+            &output,
+            &pool,
+            &progress,
+        ) {
+            Ok(_) if args.stdout => {}
+            Ok(_) => info!("Successed save image as \"{}\"", output.display()),
+            Err(e) => {
+                error!("Handled error: {}", e);
+                return;
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Words of a command line joined from `shell_word`s
+    fn split_words(line: &str) -> Vec<String> {
+        let mut words = vec![];
+        let mut word: Option<String> = None;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' => words.extend(word.take()),
+                '\'' => {
+                    let word = word.get_or_insert_with(String::new);
+                    word.extend(chars.by_ref().take_while(|&c| c != '\''));
+                }
+                '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+                c => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+        words.extend(word);
+        words
+    }
+
+    /// Arguments parsed from the command line of the arguments `argv` and the view and the
+    /// fractal parsed from it
+    fn reproduced(argv: &[&str]) -> (Args, Args) {
+        let args = Args::try_parse_from(argv).unwrap();
+        let variant = args.variant();
+        let line = command_line(&args, variant, &args.view(variant), &args.output);
+        let again = Args::try_parse_from(split_words(&line)).unwrap();
+        (args, again)
+    }
+
+    #[test]
+    fn args_are_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn command_line_reproduces_the_view_and_the_options() {
+        let (args, again) = reproduced(&[
+            "mandelbrot",
+            "-i",
+            "500",
+            "-w",
+            "320",
+            "-h",
+            "180",
+            "--center-re=-0.75",
+            "--center-im",
+            "0.1",
+            "--zoom",
+            "4",
+            "--pan-x",
+            "0.1",
+            "--rotate",
+            "30",
+            "--smooth",
+            "--samples",
+            "2",
+            "--aa-pattern",
+            "random",
+            "--aa-seed",
+            "7",
+            "-p",
+            "fire",
+            "--gamma",
+            "2.2",
+            "-q",
+            "90",
+            "--color-clamp",
+            "-10",
+            "400",
+            "-o",
+            "out dir/it's.jpg",
+            "burning-ship",
+        ]);
+
+        assert_eq!(again.variant(), Variant::BurningShip);
+        let (view, view_again) = (args.view(args.variant()), again.view(again.variant()));
+        assert_eq!(
+            (view.x_min, view.x_max, view.y_min, view.y_max),
+            (
+                view_again.x_min,
+                view_again.x_max,
+                view_again.y_min,
+                view_again.y_max
+            )
+        );
+        assert_eq!((view_again.width, view_again.height), (320, 180));
+        assert_eq!(view_again.rotation, view.rotation);
+        assert_eq!(again.iters, 500);
+        assert!(again.smooth);
+        assert_eq!(again.samples, 2);
+        assert_eq!(again.aa_pattern, SamplePattern::Random);
+        assert_eq!(again.aa_seed, 7);
+        assert_eq!(again.palette, Palette::Fire);
+        assert_eq!(again.gamma, 2.2);
+        assert_eq!(again.quality, Some(90));
+        assert_eq!(again.color_clamp, Some(vec![-10.0, 400.0]));
+        assert_eq!(again.output, Path::new("out dir/it's.jpg"));
+    }
+
+    #[test]
+    fn command_line_spells_out_the_bounds_of_a_location() {
+        let (args, again) = reproduced(&["mandelbrot", "-w", "200", "-h", "100", "seahorse"]);
+
+        // the bounds reproduce the location without the subcommand
+        assert!(again.command.is_none());
+        let (view, view_again) = (args.view(args.variant()), again.view(again.variant()));
+        assert_eq!(
+            (view.x_min, view.x_max, view.y_min, view.y_max),
+            (
+                view_again.x_min,
+                view_again.x_max,
+                view_again.y_min,
+                view_again.y_max
+            )
+        );
+        assert_eq!(again.iters, args.iters);
+        assert_eq!(again.palette, args.palette);
+        assert_eq!(again.quality, None);
+    }
+
+    #[test]
+    fn free_path_takes_the_first_free_index() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-free-path-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.png");
+        assert_eq!(free_path(&path), path);
+
+        // a gap at 2 after 1, and files of other names and extensions
+        for name in [
+            "image.png",
+            "image_1.png",
+            "image_3.png",
+            "image_2.jpg",
+            "other_2.png",
+        ] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(free_path(&path), dir.join("image_2.png"));
+        fs::write(dir.join("image_2.png"), b"").unwrap();
+        assert_eq!(free_path(&path), dir.join("image_4.png"));
+
+        fs::write(dir.join("picture"), b"").unwrap();
+        assert_eq!(free_path(&dir.join("picture")), dir.join("picture_1"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Width and height in pixels of the period-2 bulb of the set, a disk, in the view of
+    /// the arguments `argv`
+    fn bulb_pixels(argv: &[&str]) -> (usize, usize) {
+        let args = Args::try_parse_from(argv).unwrap();
+        let view = args.view(args.variant());
+        let (mut columns, mut rows) = ((usize::MAX, 0), (usize::MAX, 0));
+        for y in 0..view.height {
+            for x in 0..view.width {
+                let (cx, cy) = view.pixel_to_complex(x, y).unwrap();
+                // the disk of radius 1/4 about -1 without the bulbs on it
+                let near = (cx + 1.0).hypot(cy) < 0.26;
+                if near && mandelbrot::mandelbrot_at_point(cx, cy, 500, 4.0, true) == 500 {
+                    columns = (columns.0.min(x), columns.1.max(x));
+                    rows = (rows.0.min(y), rows.1.max(y));
+                }
+            }
+        }
+        (columns.1 - columns.0 + 1, rows.1 - rows.0 + 1)
+    }
+
+    #[test]
+    fn fix_aspect_keeps_the_features_round() {
+        let argv = [
+            "mandelbrot",
+            "-w",
+            "200",
+            "-h",
+            "100",
+            "--x-min=-1.3",
+            "--x-max=-0.7",
+            "--y-min=-0.5",
+            "--y-max",
+            "0.5",
+        ];
+        // bounds 0.6 wide and 1 tall stretch the disk across the picture twice as wide
+        let (width, height) = bulb_pixels(&argv);
+        assert!(width > 3 * height, "{} x {}", width, height);
+
+        let fixed = [&argv[..], &["--fix-aspect"]].concat();
+        let (width, height) = bulb_pixels(&fixed);
+        assert!(width.abs_diff(height) <= 1, "{} x {}", width, height);
+        let args = Args::try_parse_from(&fixed).unwrap();
+        let view = args.view(args.variant());
+        assert_eq!(view.pixel_size().0, view.pixel_size().1);
+        // the taller axis and the center are kept
+        assert_eq!((view.y_min, view.y_max), (-0.5, 0.5));
+        assert!(((view.x_min + view.x_max) / 2.0 + 1.0).abs() < 1e-12);
+
+        // pixels twice as wide as tall on the display span twice the width
+        let wide = [&fixed[..], &["--pixel-aspect", "2"]].concat();
+        let args = Args::try_parse_from(&wide).unwrap();
+        let (dx, dy) = args.view(args.variant()).pixel_size();
+        assert!((dx / dy - 2.0).abs() < 1e-12, "{} x {}", dx, dy);
+    }
+
+    #[test]
+    fn parse_view_reads_the_center_and_the_zoom() {
+        for (s, view) in [
+            ("-0.7454:0.1130@1e5", (-0.7454, 0.113, 1e5)),
+            ("0:0@1", (0.0, 0.0, 1.0)),
+            ("-1.5e-3 : -2E-1 @ 2.5", (-1.5e-3, -0.2, 2.5)),
+            ("0.25:-0.5@3e14", (0.25, -0.5, 3e14)),
+        ] {
+            assert_eq!(parse_view(s), Ok(view), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_view_rejects_malformed_strings() {
+        for (s, error) in [
+            ("-0.7454:0.1130", "expected RE:IM@ZOOM"),
+            ("-0.7454@1e5", "expected RE:IM@ZOOM"),
+            ("", "expected RE:IM@ZOOM"),
+            ("x:0.1@10", "real part \"x\""),
+            ("0.1:@10", "imaginary part \"\""),
+            ("inf:0@10", "real part \"inf\""),
+            ("0:NaN@10", "imaginary part \"NaN\""),
+            ("0:0@0", "zoom \"0\""),
+            ("0:0@-5", "zoom \"-5\""),
+            ("0:0@fast", "zoom \"fast\""),
+            ("0:0:0@10", "imaginary part \"0:0\""),
+        ] {
+            let result = parse_view(s);
+            assert!(
+                matches!(&result, Err(e) if e.contains(error)),
+                "{}: {:?}",
+                s,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn locations_keep_the_aspect_of_the_picture() {
+        for location in Location::all() {
+            for aspect in [1.0, 16.0 / 9.0, 0.5, 3.0] {
+                let (x_min, x_max, y_min, y_max) = location.coords(aspect);
+                assert!(x_min < x_max && y_min < y_max, "{}", location);
+                let ratio = (x_max - x_min) / (y_max - y_min);
+                assert!(
+                    (ratio - aspect).abs() < 1e-9 * aspect,
+                    "{} at {}",
+                    location,
+                    aspect
+                );
+            }
+
+            let name = location.to_string();
+            let args = Args::try_parse_from(["mandelbrot", "-w", "300", "-h", "100", &name]);
+            let view = args.unwrap().view(Variant::Mandelbrot);
+            let ratio = (view.x_max - view.x_min) / (view.y_max - view.y_min);
+            assert!(
+                (ratio - 3.0).abs() < 1e-8,
+                "{} of 300 x 100 pixels",
+                location
+            );
+        }
+    }
 }
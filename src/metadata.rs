@@ -0,0 +1,200 @@
+//! Render parameters saved along with the picture, enough to render it again.
+
+use crate::Args;
+use clap::ValueEnum;
+use mandelbrot::{SamplePattern, Stats, Variant, View};
+use serde::Serialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Keyword of the PNG text chunk holding the parameters
+pub const PNG_KEYWORD: &str = "Mandelbrot-Params";
+
+#[derive(Serialize, Debug)]
+pub struct Params {
+    variant: &'static str,
+    power: u32,
+    iters: usize,
+    width: usize,
+    height: usize,
+    pixel_aspect: f64,
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+    rotate: f64,
+    bailout: f64,
+    smooth: bool,
+    deep: bool,
+    samples: u32,
+    aa_pattern: String,
+    aa_seed: Option<u64>,
+    adaptive_aa: Option<f64>,
+    palette: String,
+    palette_image: Option<PathBuf>,
+    random_palette: Option<u64>,
+    palette_space: String,
+    palette_offset: f32,
+    set_color: Option<[u8; 3]>,
+    degenerate_color: Option<[u8; 3]>,
+    invert: bool,
+    dither: Option<String>,
+    gamma: f32,
+    scale: String,
+    color_clamp: Option<[f64; 2]>,
+    color_mode: String,
+    stripe_density: f64,
+    hue_scale: f32,
+    light_angle: f64,
+    light_elevation: f64,
+    bit_depth: String,
+}
+
+/// Name of the value on the command line
+pub fn name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+impl Params {
+    pub fn new(args: &Args, variant: Variant, view: &View) -> Params {
+        let (variant, power) = match variant {
+            Variant::Mandelbrot => ("mandelbrot", 2),
+            Variant::Multibrot(power) => ("multibrot", power),
+            Variant::BurningShip => ("burning-ship", 2),
+            Variant::Tricorn => ("tricorn", 2),
+        };
+
+        Params {
+            variant,
+            power,
+            iters: args.iters,
+            width: view.width,
+            height: view.height,
+            pixel_aspect: args.pixel_aspect,
+            x_min: view.x_min,
+            x_max: view.x_max,
+            y_min: view.y_min,
+            y_max: view.y_max,
+            rotate: view.rotation.to_degrees(),
+            bailout: args.bailout,
+            smooth: args.smooth,
+            deep: args.deep,
+            samples: args.samples,
+            aa_pattern: name(&args.aa_pattern),
+            aa_seed: (args.aa_pattern == SamplePattern::Random).then_some(args.aa_seed),
+            adaptive_aa: args.adaptive_aa.then_some(args.aa_threshold),
+            palette: name(&args.palette),
+            palette_image: args.palette_image.clone(),
+            random_palette: args.random_palette,
+            palette_space: name(&args.palette_space),
+            palette_offset: args.palette_offset,
+            set_color: args.set_color.map(|color| color.0),
+            degenerate_color: args.degenerate_color.map(|color| color.0),
+            invert: args.invert,
+            dither: args.dither.as_ref().map(name),
+            gamma: args.gamma,
+            scale: name(&args.scale),
+            color_clamp: args.color_clamp.as_deref().map(|c| [c[0], c[1]]),
+            color_mode: name(&args.color_mode),
+            stripe_density: args.stripe_density,
+            hue_scale: args.hue_scale,
+            light_angle: args.light_angle,
+            light_elevation: args.light_elevation,
+            bit_depth: name(&args.bit_depth),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("plain struct serializes into JSON")
+    }
+}
+
+/// Contents of the JSON file saved next to the picture
+#[derive(Serialize, Debug)]
+pub struct Sidecar {
+    #[serde(flatten)]
+    pub params: Params,
+    /// Duration of the calculation of the escape values, without coloring and saving
+    pub calc_seconds: f64,
+    pub stats: Stats,
+}
+
+impl Sidecar {
+    /// Path of the picture with ".json" appended, "image.png.json" for "image.png"
+    pub fn path(output: &Path) -> PathBuf {
+        let mut path = output.as_os_str().to_owned();
+        path.push(".json");
+        PathBuf::from(path)
+    }
+
+    pub fn save(&self, output: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("plain struct serializes into JSON");
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(Sidecar::path(output), json + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use image::{DynamicImage, GrayImage};
+    use mandelbrot::save_png_with_text;
+    use std::{fs::File, io::BufReader, process};
+
+    #[test]
+    fn png_text_chunk_reads_back_as_the_params() {
+        let args = Args::try_parse_from([
+            "mandelbrot",
+            "-i",
+            "800",
+            "-w",
+            "64",
+            "-h",
+            "36",
+            "--x-min=-1.5",
+            "--smooth",
+            "tricorn",
+        ])
+        .unwrap();
+        let variant = args.variant();
+        let view = args.view(variant);
+        let json = Params::new(&args, variant, &view).to_json();
+
+        let dir = std::env::temp_dir().join(format!("mandelbrot-metadata-{}", process::id()));
+        let path = dir.join("set.png");
+        let img = DynamicImage::ImageLuma8(GrayImage::new(64, 36));
+        save_png_with_text(&img, &path, PNG_KEYWORD, &json).unwrap();
+
+        let decoder = png::Decoder::new(BufReader::new(File::open(&path).unwrap()));
+        let reader = decoder.read_info().unwrap();
+        let chunk = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == PNG_KEYWORD)
+            .expect("the picture carries the params");
+        let params: serde_json::Value = serde_json::from_str(&chunk.text).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(params["variant"], "tricorn");
+        assert_eq!(params["iters"], 800);
+        assert_eq!(
+            (params["width"].as_u64(), params["height"].as_u64()),
+            (Some(64), Some(36))
+        );
+        assert_eq!(params["x_min"].as_f64(), Some(view.x_min));
+        assert_eq!(params["x_max"].as_f64(), Some(view.x_max));
+        assert_eq!(params["y_min"].as_f64(), Some(view.y_min));
+        assert_eq!(params["y_max"].as_f64(), Some(view.y_max));
+        assert_eq!(params["smooth"], true);
+        assert_eq!(params["palette"], "grayscale");
+    }
+}
@@ -0,0 +1,290 @@
+//! Tiles of escape values kept between the frames of an animation, the tiles of a frame
+//! whose pixels lie at the same points with the same iterations aren't calculated again.
+
+use indicatif::ProgressBar;
+use mandelbrot::{calc_escaped, CalcOptions, Escaped, Precision, View};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// Width and height of the tiles in pixels
+const TILE: usize = 64;
+/// Tiles kept at most, the tiles calculated later aren't kept
+const CAPACITY: usize = 4096;
+
+/// Bits of everything the escape values of the pixels of a tile depend on besides the
+/// options shared by the frames
+#[derive(PartialEq, Eq, Hash)]
+struct Key {
+    iters: usize,
+    pixel_size: (u64, u64),
+    /// Real parts of the points of the columns
+    columns: Vec<u64>,
+    /// Imaginary parts of the points of the rows along with the ones of the rows they are
+    /// mirrored from about the real axis
+    rows: Vec<(u64, u64)>,
+    /// Placement of the tile in its frame, for the points lying about the center of the
+    /// frame
+    frame: Option<Frame>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct Frame {
+    center: (u64, u64),
+    rotation: u64,
+    size: (usize, usize),
+    /// First row and column of the tile
+    corner: (usize, usize),
+}
+
+impl Key {
+    /// Points of the samples of the rows `rows` and the columns `columns` as met by the
+    /// kernels in the view of the samples
+    fn new(
+        options: &CalcOptions,
+        view: &View,
+        rows: (usize, usize),
+        columns: (usize, usize),
+    ) -> Key {
+        let samples = options.samples;
+        let (width, height) = (view.width * samples, view.height * samples);
+        let dx = (view.x_max - view.x_min) / width as f64;
+        let dy = (view.y_max - view.y_min) / height as f64;
+        let source = |y: usize| {
+            if view.y_min == -view.y_max && y >= 1 && 2 * y < height {
+                height - y
+            } else {
+                y
+            }
+        };
+        let im = |y: usize| (view.y_min + y as f64 * dy).to_bits();
+        // deep and double-double pixels are offsets from the center of the frame, the
+        // reference orbit of a deep frame is the orbit of its center and the turned
+        // pixels turn about it
+        let about_center =
+            options.deep || options.precision == Precision::Dd || view.rotation != 0.0;
+        let frame = Frame {
+            center: (
+                ((view.x_min + view.x_max) / 2.0).to_bits(),
+                ((view.y_min + view.y_max) / 2.0).to_bits(),
+            ),
+            rotation: view.rotation.to_bits(),
+            size: (view.width, view.height),
+            corner: (rows.0, columns.0),
+        };
+
+        Key {
+            iters: options.iters,
+            pixel_size: (dx.to_bits(), dy.to_bits()),
+            columns: (columns.0 * samples..columns.1 * samples)
+                .map(|x| (view.x_min + x as f64 * dx).to_bits())
+                .collect(),
+            rows: (rows.0 * samples..rows.1 * samples)
+                .map(|y| (im(y), im(source(y))))
+                .collect(),
+            frame: about_center.then_some(frame),
+        }
+    }
+}
+
+/// Tiles of the frames calculated so far, shared by the frames calculated at the same time
+pub struct TileCache {
+    tiles: Mutex<HashMap<Key, Escaped>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl TileCache {
+    pub fn new() -> TileCache {
+        TileCache {
+            tiles: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Escape values of the view put together from tiles, the same as `calc_escaped` of
+    /// the whole view: every tile is calculated in the view of the frame
+    pub fn calc(&self, options: &CalcOptions, view: &View, progress: &ProgressBar) -> Escaped {
+        let mut escaped: Option<Escaped> = None;
+        for top in (0..view.height).step_by(TILE) {
+            let rows = (top, (top + TILE).min(view.height));
+            let tiles: Vec<_> = (0..view.width)
+                .step_by(TILE)
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|left| {
+                    let columns = (left, (left + TILE).min(view.width));
+                    (columns, self.tile(options, view, rows, columns))
+                })
+                .collect();
+
+            let mut band: Option<Escaped> = None;
+            for ((left, right), tile) in tiles {
+                match &mut band {
+                    Some(band) => band.append_columns(left, tile, right - left),
+                    None => band = Some(tile),
+                }
+            }
+            let band = band.expect("views hold a column at least");
+            match &mut escaped {
+                Some(escaped) => escaped.append(band),
+                None => escaped = Some(band),
+            }
+            progress.inc(((rows.1 - rows.0) * options.samples) as u64);
+        }
+        escaped.expect("views hold a row at least")
+    }
+
+    fn tile(
+        &self,
+        options: &CalcOptions,
+        view: &View,
+        rows: (usize, usize),
+        columns: (usize, usize),
+    ) -> Escaped {
+        let key = Key::new(options, view, rows, columns);
+        if let Some(tile) = self.tiles.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return tile.clone();
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let tile_view = View {
+            rows,
+            columns,
+            ..*view
+        };
+        let tile = calc_escaped(options, &tile_view, &ProgressBar::hidden());
+        let mut tiles = self.tiles.lock().unwrap();
+        if tiles.len() < CAPACITY {
+            tiles.insert(key, tile.clone());
+        }
+        tile
+    }
+
+    /// Tiles reused and tiles calculated
+    pub fn counts(&self) -> (usize, usize) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mandelbrot::{Offsets, SamplePattern, SimdWidth, Variant};
+
+    fn options(iters: usize) -> CalcOptions {
+        CalcOptions {
+            variant: Variant::Mandelbrot,
+            iters,
+            threshold: 4.0,
+            smooth: false,
+            simd_width: SimdWidth::Four,
+            precision: Precision::F64,
+            fast_interior: true,
+            samples: 1,
+            pattern: SamplePattern::Grid,
+            seed: 0,
+            distance: false,
+            trap: None,
+            stripe: None,
+            tia: false,
+            phase: false,
+            hsv: false,
+            lighting: None,
+            deep: false,
+            adaptive: None,
+            scalar: false,
+        }
+    }
+
+    /// Frame of 4 x 2 tiles centered on the real axis, its points exact in f64 for pixels
+    /// of powers of 2
+    fn frame(x_min: f64, pixel: f64) -> View {
+        View {
+            x_min,
+            x_max: x_min + 256.0 * pixel,
+            y_min: -64.0 * pixel,
+            y_max: 64.0 * pixel,
+            width: 256,
+            height: 128,
+            rows: (0, 128),
+            columns: (0, 256),
+            deadline: None,
+            offsets: Offsets::None,
+            rotation: 0.0,
+            rows_per_task: TILE,
+        }
+    }
+
+    /// Tiles reused by the frames, every frame put together from the tiles must match
+    /// the frame calculated without the cache
+    fn reused(options: &CalcOptions, frames: &[View]) -> usize {
+        let cache = TileCache::new();
+        let progress = ProgressBar::hidden();
+        for view in frames {
+            let cached = cache.calc(options, view, &progress);
+            let fresh = calc_escaped(options, view, &progress);
+            assert_eq!(cached.len(), fresh.len());
+            assert_eq!(cached.checksum(), fresh.checksum());
+        }
+        cache.counts().0
+    }
+
+    const PIXEL: f64 = 1.0 / 128.0;
+    /// Pan by a tile, 3 of its columns of tiles are shared with the first frame
+    const PAN: [f64; 2] = [-2.0, -2.0 + 64.0 * PIXEL];
+
+    #[test]
+    fn pans_reuse_the_shared_tiles() {
+        let frames = PAN.map(|x_min| frame(x_min, PIXEL));
+        assert_eq!(reused(&options(100), &frames), 6);
+
+        let supersampled = CalcOptions {
+            smooth: true,
+            samples: 2,
+            pattern: SamplePattern::RotatedGrid,
+            ..options(100)
+        };
+        assert_eq!(reused(&supersampled, &frames), 6);
+    }
+
+    #[test]
+    fn holds_reuse_every_tile() {
+        let frames = [frame(-2.0, PIXEL); 3];
+        assert_eq!(reused(&options(100), &frames), 16);
+    }
+
+    #[test]
+    fn zooms_and_other_iterations_reuse_nothing() {
+        let zoom = [frame(-2.0, PIXEL), frame(-1.5, PIXEL / 2.0)];
+        assert_eq!(reused(&options(100), &zoom), 0);
+
+        let cache = TileCache::new();
+        let view = frame(-2.0, PIXEL);
+        cache.calc(&options(100), &view, &ProgressBar::hidden());
+        cache.calc(&options(200), &view, &ProgressBar::hidden());
+        assert_eq!(cache.counts(), (0, 16));
+    }
+
+    #[test]
+    fn deep_pans_reuse_nothing() {
+        // the same points of the tiles, but the reference orbits of other centers
+        let frames = PAN.map(|x_min| frame(x_min, PIXEL));
+        let deep = CalcOptions {
+            deep: true,
+            ..options(100)
+        };
+        assert_eq!(reused(&deep, &frames), 0);
+        assert_eq!(reused(&deep, &[frames[0]; 2]), 8);
+    }
+}
@@ -0,0 +1,217 @@
+//! The command line run as a program, for the behavior living in the binary.
+
+mod common;
+
+use common::scratch_dir;
+use std::{fs, path::Path, process::Command};
+
+/// Runs the program with `args` in `dir`, panics unless it succeeds, returns its output
+fn mandelbrot(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("the program runs");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{:?}: {}", args, stderr);
+    assert!(!stdout.contains("Handled error"), "{:?}: {}", args, stdout);
+    stdout
+}
+
+/// Runs the program with `args` in `dir`, panics unless it stops at a handled error,
+/// returns the error
+fn handled_error(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("the program runs");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let error = stdout
+        .lines()
+        .find(|line| line.starts_with("Handled error"));
+    error
+        .unwrap_or_else(|| panic!("{:?}: {}", args, stdout))
+        .to_string()
+}
+
+/// Flags of a small and quick picture
+const SMALL: [&str; 6] = ["-w", "150", "-h", "100", "-i", "200"];
+
+#[test]
+fn tiled_pictures_are_the_same_files() {
+    let dir = scratch_dir("cli-tiled");
+    for ext in ["png", "bmp"] {
+        let whole = format!("whole.{}", ext);
+        let tiled = format!("tiled.{}", ext);
+        mandelbrot(&dir, &[&SMALL[..], &["--smooth", "-o", &whole]].concat());
+        for tile_height in ["1", "7", "64"] {
+            let args = ["--smooth", "--tile-height", tile_height, "-o", &tiled];
+            mandelbrot(&dir, &[&SMALL[..], &args].concat());
+            assert_eq!(
+                fs::read(dir.join(&whole)).unwrap(),
+                fs::read(dir.join(&tiled)).unwrap(),
+                "{} in bands of {} rows",
+                ext,
+                tile_height
+            );
+        }
+    }
+}
+
+/// Hash printed by --checksum for the small picture with `args`, the subcommand last
+fn checksum(dir: &Path, args: &[&str]) -> String {
+    let stdout = mandelbrot(dir, &[&["--checksum"], &SMALL[..], args].concat());
+    let line = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Checksum of the escape values: "))
+        .expect("--checksum prints the hash");
+    line.to_string()
+}
+
+#[test]
+fn checksum_is_the_same_for_every_number_of_threads() {
+    let dir = scratch_dir("cli-checksum");
+    for args in [&[][..], &["--smooth"], &["--samples", "2"]] {
+        let one = checksum(&dir, &[&["--threads", "1"], args].concat());
+        assert_eq!(one.len(), 16);
+        for threads in ["2", "5", "0"] {
+            let many = checksum(&dir, &[&["--threads", threads], args].concat());
+            assert_eq!(one, many, "{:?} with {} threads", args, threads);
+        }
+    }
+    // yet any change of the escape values shows
+    assert_ne!(checksum(&dir, &[]), checksum(&dir, &["--bailout", "3"]));
+}
+
+#[test]
+fn scalar_checksum_matches_the_simd_one() {
+    let dir = scratch_dir("cli-scalar");
+    // rows of 150 pixels end in a vector of 2 lanes
+    let views: [&[&str]; 4] = [
+        &[],
+        &["--samples", "2"],
+        &["--no-fast-interior"],
+        &["--center-re=-0.745", "--center-im", "0.11", "--zoom", "80"],
+    ];
+    for args in views {
+        let simd = checksum(&dir, args);
+        let scalar = checksum(&dir, &[&["--scalar"], args].concat());
+        assert_eq!(simd, scalar, "{:?}", args);
+    }
+}
+
+#[test]
+fn stdout_gets_the_ppm_picture_and_nothing_else() {
+    let dir = scratch_dir("cli-stdout");
+    for (palette, header, channels) in [
+        ("grayscale", "P5\n150 100\n255\n", 1),
+        ("fire", "P6\n150 100\n255\n", 3),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+            .current_dir(&dir)
+            .args([&SMALL[..], &["--stdout", "-p", palette]].concat())
+            .output()
+            .expect("the program runs");
+        assert!(output.status.success());
+
+        let samples = output
+            .stdout
+            .strip_prefix(header.as_bytes())
+            .expect("PNM header");
+        assert_eq!(samples.len(), 150 * 100 * channels, "{}", palette);
+        // the log went to stderr and no file was saved
+        assert!(!String::from_utf8_lossy(&output.stderr).contains("Handled error"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    }
+}
+
+#[test]
+fn views_beyond_f64_suggest_deep() {
+    let dir = scratch_dir("cli-deep");
+    let view = [
+        "--center-re=-0.743643887037151",
+        "--center-im",
+        "0.131825904205330",
+    ];
+    let args = |zoom| [&SMALL[..], &view, &["--zoom", zoom, "--no-save"]].concat();
+    assert!(mandelbrot(&dir, &args("3e15")).contains("pass --deep"));
+    assert!(!mandelbrot(&dir, &args("3e6")).contains("pass --deep"));
+    let deep = [&args("3e15")[..], &["--deep"]].concat();
+    assert!(!mandelbrot(&dir, &deep).contains("pass --deep"));
+}
+
+#[test]
+fn color_clamp_needs_a_low_below_the_high() {
+    let dir = scratch_dir("cli-color-clamp");
+    for (low, high) in [("5", "5"), ("60", "20"), ("0", "inf")] {
+        let args = [&SMALL[..], &["--no-save", "--color-clamp", low, high]].concat();
+        assert!(
+            handled_error(&dir, &args).contains("LOW below HIGH"),
+            "{} {}",
+            low,
+            high
+        );
+    }
+    mandelbrot(
+        &dir,
+        &[&SMALL[..], &["--no-save", "--color-clamp", "20", "60"]].concat(),
+    );
+}
+
+#[test]
+fn strict_precision_stops_at_views_beyond_f64() {
+    let dir = scratch_dir("cli-strict-precision");
+    let view = [
+        "--center-re=-0.743643887037151",
+        "--center-im",
+        "0.131825904205330",
+    ];
+    let args = |zoom| [&SMALL[..], &view, &["--zoom", zoom, "-o", "deep.png"]].concat();
+    let strict = [&args("3e15")[..], &["--strict-precision"]].concat();
+    assert!(handled_error(&dir, &strict).contains("--strict-precision"));
+    assert!(!dir.join("deep.png").exists());
+
+    // shallower views, and the deep precisions, calculate the picture
+    mandelbrot(&dir, &[&args("3e6")[..], &["--strict-precision"]].concat());
+    fs::remove_file(dir.join("deep.png")).unwrap();
+    for precision in [&["--deep"][..], &["--precision", "dd"]] {
+        mandelbrot(&dir, &[&strict[..], precision].concat());
+        assert!(dir.join("deep.png").exists(), "{:?}", precision);
+    }
+}
+
+#[test]
+fn histogram_recoloring_of_distances_is_a_handled_error() {
+    let dir = scratch_dir("cli-recolor-distances");
+    // few iterations leave distances past the last escape count
+    let small = ["-w", "150", "-h", "100", "-i", "10"];
+    let dump = ["--color-mode", "distance", "--dump-raw", "d.bin"];
+    mandelbrot(&dir, &[&small[..], &dump].concat());
+    let recolor = [
+        "--color-mode",
+        "histogram",
+        "-o",
+        "h.png",
+        "recolor",
+        "d.bin",
+    ];
+    assert!(handled_error(&dir, &recolor).contains("distances"));
+    assert!(!dir.join("h.png").exists());
+
+    // the counts of the same view recolor with the histogram
+    mandelbrot(&dir, &[&small[..], &["--dump-raw", "c.bin"]].concat());
+    mandelbrot(
+        &dir,
+        &[
+            "--color-mode",
+            "histogram",
+            "-o",
+            "h.png",
+            "recolor",
+            "c.bin",
+        ],
+    );
+    assert!(dir.join("h.png").exists());
+}
@@ -0,0 +1,575 @@
+//! The escape values mapped into the palettes and the colors of the pictures.
+
+mod common;
+
+use common::{calc_options, draw_options, full_view, view, THRESHOLD};
+use indicatif::ProgressBar;
+use mandelbrot::{
+    calc_escaped, calc_mandelbrot, color_image, dither, hsv, hue_wheel, mandelbrot_norm_at_point,
+    oklab_to_srgb, srgb_to_oklab, BitDepth, CalcOptions, ColorMode, Dither, DrawOptions, Escaped,
+    Gradient, MandelbrotError, Palette, SMOOTH_THRESHOLD,
+};
+
+#[test]
+fn smooth_counts_keep_the_points_of_the_set_and_normalize_the_others() {
+    let view = full_view(64, 36);
+    let smooth = CalcOptions {
+        smooth: true,
+        ..calc_options(200)
+    };
+    let Escaped::Smooth(values) = calc_escaped(&smooth, &view, &ProgressBar::hidden()) else {
+        panic!("smooth escape counts");
+    };
+    let counts = calc_mandelbrot(200, THRESHOLD, true, &view, &ProgressBar::hidden());
+    assert!(counts.contains(&200));
+
+    for (i, (&value, &count)) in values.iter().zip(&counts).enumerate() {
+        if count == 200 {
+            assert_eq!(value, 200.0, "pixel {}", i);
+            continue;
+        }
+        // n + 1 - log2(log(|z|)) of the orbit run up to the squared radius of 256, far
+        // enough for the log term, even though the escape counts stop at 4
+        let (cx, cy) = view.pixel_to_complex(i % 64, i / 64).unwrap();
+        let (n, norm_sqr) = mandelbrot_norm_at_point(cx, cy, 200, 2, SMOOTH_THRESHOLD);
+        assert!(norm_sqr > SMOOTH_THRESHOLD);
+        let nu = n as f64 + 1.0 - (0.5 * norm_sqr.ln()).log2();
+        assert!((value - nu.clamp(0.0, 199.0)).abs() < 1e-9, "pixel {}", i);
+    }
+}
+
+#[test]
+fn histogram_spans_the_whole_range_in_flat_and_detailed_regions() {
+    // a few counts far outside the set and hundreds of them along the boundary
+    for (bounds, distinct) in [
+        ((0.5, 1.5, 0.5, 1.2), 3..6),
+        ((-0.80, -0.70, 0.05, 0.15), 200..400),
+    ] {
+        let view = view(bounds, 96, 64);
+        let counts = calc_mandelbrot(500, THRESHOLD, true, &view, &ProgressBar::hidden());
+        let mut values = counts.clone();
+        values.sort();
+        values.dedup();
+        assert!(distinct.contains(&values.len()), "{:?}", bounds);
+
+        let options = DrawOptions {
+            color_mode: ColorMode::Histogram,
+            ..draw_options(500)
+        };
+        let img = color_image(&Escaped::Counts(counts.clone()), 96, 64, options).unwrap();
+        let levels: Vec<u8> = img
+            .to_luma8()
+            .pixels()
+            .zip(&counts)
+            .filter(|(_, count)| **count != 500)
+            .map(|(pixel, _)| pixel.0[0])
+            .collect();
+        assert_eq!(levels.iter().min(), Some(&0), "{:?}", bounds);
+        assert_eq!(levels.iter().max(), Some(&255), "{:?}", bounds);
+    }
+}
+
+/// Luma of the pixels of every count of 0..=100, the last one in the set
+fn levels(options: DrawOptions) -> Vec<u8> {
+    let counts: Vec<u64> = (0..=100).collect();
+    let img = color_image(&Escaped::Counts(counts), 101, 1, options).unwrap();
+    img.to_luma8().pixels().map(|pixel| pixel.0[0]).collect()
+}
+
+#[test]
+fn gamma_of_one_keeps_the_linear_levels_and_higher_gammas_brighten() {
+    let linear = levels(draw_options(100));
+    for (count, &level) in linear.iter().enumerate() {
+        let expected = count as f32 * 255.0 / 100.0;
+        assert!(
+            (f32::from(level) - expected).abs() <= 1.0,
+            "count {}",
+            count
+        );
+    }
+
+    for palette in [Palette::Grayscale, Palette::Fire] {
+        let mean = |gamma| {
+            let levels = levels(DrawOptions {
+                palette,
+                gamma,
+                ..draw_options(100)
+            });
+            levels.iter().map(|&level| f64::from(level)).sum::<f64>() / levels.len() as f64
+        };
+        let means = [0.5, 1.0, 1.8, 2.2, 3.0].map(mean);
+        assert!(
+            means.windows(2).all(|w| w[0] < w[1]),
+            "{:?}: {:?}",
+            palette,
+            means
+        );
+    }
+}
+
+#[test]
+fn inverting_the_inverted_picture_gives_the_picture_back() {
+    let view = full_view(48, 27);
+    let counts = Escaped::Counts(calc_mandelbrot(
+        100,
+        THRESHOLD,
+        true,
+        &view,
+        &ProgressBar::hidden(),
+    ));
+    for (palette, bit_depth) in [
+        (Palette::Grayscale, BitDepth::Eight),
+        (Palette::Grayscale, BitDepth::Sixteen),
+        (Palette::Fire, BitDepth::Eight),
+    ] {
+        let options = DrawOptions {
+            palette,
+            bit_depth,
+            ..draw_options(100)
+        };
+        let img = color_image(&counts, 48, 27, options).unwrap();
+        let invert = DrawOptions {
+            invert: true,
+            ..options
+        };
+        let mut inverted = color_image(&counts, 48, 27, invert).unwrap();
+        assert_ne!(inverted, img, "{:?}", palette);
+
+        inverted.invert();
+        assert_eq!(inverted, img, "{:?} of {:?}", palette, bit_depth);
+    }
+}
+
+#[test]
+fn dithering_changes_the_low_bit_only() {
+    let view = full_view(64, 36);
+    let smooth = CalcOptions {
+        smooth: true,
+        ..calc_options(200)
+    };
+    let values = calc_escaped(&smooth, &view, &ProgressBar::hidden());
+    for palette in [Palette::Grayscale, Palette::Ocean] {
+        let picture = |dither| {
+            let options = DrawOptions {
+                palette,
+                dither,
+                ..draw_options(200)
+            };
+            color_image(&values, 64, 36, options)
+                .unwrap()
+                .to_rgb8()
+                .into_raw()
+        };
+        let plain = picture(None);
+        // the error diffusion may carry a channel one level further
+        for (kind, spread) in [(Dither::Bayer, 1), (Dither::FloydSteinberg, 2)] {
+            let dithered = picture(Some(kind));
+            assert_eq!(dithered, picture(Some(kind)), "{:?}", kind);
+            assert_ne!(dithered, plain, "{:?}", kind);
+            for (i, (&d, &p)) in dithered.iter().zip(&plain).enumerate() {
+                assert!(
+                    d.abs_diff(p) <= spread,
+                    "{:?} of {:?} at {}",
+                    kind,
+                    palette,
+                    i
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn dithering_keeps_the_average_of_flat_regions() {
+    let flat = vec![100.25; 64 * 64];
+    for kind in [Dither::Bayer, Dither::FloydSteinberg] {
+        let dithered = dither(&flat, 64, 1, kind);
+        let mean = dithered.iter().map(|&v| f64::from(v)).sum::<f64>() / dithered.len() as f64;
+        assert!((mean - 100.25).abs() < 0.01, "{:?}: {}", kind, mean);
+    }
+    // integer values stay as they are
+    let levels: Vec<f32> = (0..=255).map(|v| v as f32).collect();
+    let expected: Vec<u8> = (0..=255).collect();
+    for kind in [Dither::Bayer, Dither::FloydSteinberg] {
+        assert_eq!(dither(&levels, 16, 1, kind), expected, "{:?}", kind);
+    }
+}
+
+#[test]
+fn opposite_phases_get_complementary_hues() {
+    // the phases of the right half of the wheel, then the opposite ones, and a point of
+    // the set
+    let half: Vec<f64> = (0..50).map(|i| i as f64 / 100.0).collect();
+    let phases: Vec<f64> = [
+        &half[..],
+        &half.iter().map(|t| t + 0.5).collect::<Vec<_>>(),
+        &[-1.0],
+    ]
+    .concat();
+    let options = DrawOptions {
+        color_mode: ColorMode::Phase,
+        ..draw_options(100)
+    };
+    let img = color_image(&Escaped::Stripe(phases), 101, 1, options)
+        .unwrap()
+        .to_rgb8();
+    let pixel = |i: u32| img.get_pixel(i, 0).0;
+
+    for i in 0..50 {
+        let (a, b) = (pixel(i), pixel(i + 50));
+        for c in 0..3 {
+            let sum = u16::from(a[c]) + u16::from(b[c]);
+            assert!(sum.abs_diff(255) <= 2, "{:?} and {:?} at {}", a, b, i);
+        }
+    }
+    assert_eq!(pixel(100), [0, 0, 0]);
+}
+
+/// Checks the points of the set among the values calculated with `calc` get the set
+/// color in `color_mode`, and some points of the view are in the set
+fn assert_set_color(calc: CalcOptions, color_mode: ColorMode) {
+    let (width, height) = (64, 40);
+    let values = calc_escaped(&calc, &full_view(width, height), &ProgressBar::hidden());
+    let red = image::Rgb([255, 0, 0]);
+    for palette in [Palette::Grayscale, Palette::Rainbow] {
+        let options = DrawOptions {
+            color_mode,
+            palette,
+            set_color: Some(red),
+            ..draw_options(calc.iters)
+        };
+        let img = color_image(&values, width as u32, height as u32, options)
+            .unwrap()
+            .to_rgb8();
+        let in_set = (0..values.len()).filter(|&i| values.get(i) == -1.0);
+        assert!(in_set.clone().count() > 0, "{:?}", color_mode);
+        for i in in_set {
+            let (x, y) = ((i % width) as u32, (i / width) as u32);
+            assert_eq!(
+                img.get_pixel(x, y),
+                &red,
+                "{:?} {:?} at ({}, {})",
+                color_mode,
+                palette,
+                x,
+                y
+            );
+        }
+    }
+}
+
+#[test]
+fn stripe_averages_of_the_set_get_the_set_color() {
+    let calc = CalcOptions {
+        stripe: Some(5.0),
+        ..calc_options(100)
+    };
+    assert_set_color(calc, ColorMode::Stripe);
+}
+
+#[test]
+fn triangle_inequality_averages_of_the_set_get_the_set_color() {
+    let calc = CalcOptions {
+        tia: true,
+        ..calc_options(100)
+    };
+    assert_set_color(calc, ColorMode::Tia);
+}
+
+#[test]
+fn hue_wheel_is_continuous_across_the_wrap() {
+    // steps of 1/360 turn move every channel by a few levels, the last one back to red
+    let steps: Vec<f32> = (0..=360).map(|i| i as f32 / 360.0).collect();
+    for pair in steps.windows(2) {
+        let (a, b) = (hue_wheel(pair[0]).0, hue_wheel(pair[1] % 1.0).0);
+        for c in 0..3 {
+            assert!(
+                a[c].abs_diff(b[c]) <= 5,
+                "{:?} and {:?} at {}",
+                a,
+                b,
+                pair[0]
+            );
+        }
+    }
+    assert_eq!(hue_wheel(0.0).0, [255, 0, 0]);
+}
+
+#[test]
+fn hsv_gives_the_known_colors() {
+    let half = 255.0 / 2.0;
+    for ((h, s, v), rgb) in [
+        ((0.0, 1.0, 1.0), [255.0, 0.0, 0.0]),
+        ((1.0 / 6.0, 1.0, 1.0), [255.0, 255.0, 0.0]),
+        ((1.0 / 3.0, 1.0, 1.0), [0.0, 255.0, 0.0]),
+        ((0.5, 1.0, 1.0), [0.0, 255.0, 255.0]),
+        ((2.0 / 3.0, 1.0, 1.0), [0.0, 0.0, 255.0]),
+        ((5.0 / 6.0, 1.0, 1.0), [255.0, 0.0, 255.0]),
+        // orange half way from red to yellow, pastel red and dark cyan
+        ((1.0 / 12.0, 1.0, 1.0), [255.0, half, 0.0]),
+        ((0.0, 0.5, 1.0), [255.0, half, half]),
+        ((0.5, 1.0, 0.5), [0.0, half, half]),
+        // no saturation is gray, no value black, whatever the hue
+        ((0.3, 0.0, 1.0), [255.0; 3]),
+        ((0.7, 0.0, 0.5), [half; 3]),
+        ((0.9, 1.0, 0.0), [0.0; 3]),
+        // the hue wraps around
+        ((1.25, 1.0, 1.0), [half, 255.0, 0.0]),
+        ((-1.0 / 3.0, 1.0, 1.0), [0.0, 0.0, 255.0]),
+    ] {
+        let got = hsv(h, s, v);
+        for c in 0..3 {
+            assert!(
+                (got[c] - rgb[c]).abs() < 0.01,
+                "{:?} of {:?}",
+                got,
+                (h, s, v)
+            );
+        }
+    }
+}
+
+/// Oklab coordinates of the 256 colors of the ramp of `seed`
+fn random_ramp(seed: u64) -> Vec<[f64; 3]> {
+    let gradient = Gradient::random(seed);
+    (0..256)
+        .map(|i| srgb_to_oklab(gradient.color(i as f32 / 255.0).0.map(|v| v as f64 / 255.0)))
+        .collect()
+}
+
+fn oklab_distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|c| (a[c] - b[c]).powi(2)).sum::<f64>().sqrt()
+}
+
+#[test]
+fn random_palettes_are_smooth_ramps_of_their_seeds() {
+    for seed in 0..50 {
+        let ramp = random_ramp(seed);
+        assert_eq!(ramp, random_ramp(seed));
+        // steps well below a just noticeable difference of about 0.02, from dark to light
+        for (i, pair) in ramp.windows(2).enumerate() {
+            let step = oklab_distance(pair[0], pair[1]);
+            assert!(step < 0.015, "step {} at {} of seed {}", step, i, seed);
+        }
+        assert!(ramp[255][0] - ramp[0][0] > 0.4, "seed {}", seed);
+    }
+
+    // the colors of seeds 0 and 1 are apart by far more than the steps
+    let (zero, one) = (random_ramp(0), random_ramp(1));
+    let apart = zero
+        .iter()
+        .zip(&one)
+        .map(|(&a, &b)| oklab_distance(a, b))
+        .sum::<f64>()
+        / 256.0;
+    assert!(apart > 0.05, "{}", apart);
+}
+
+/// Reference Oklab coordinates of sRGB colors, from the published conversion
+const OKLAB_REFERENCES: [([f64; 3], [f64; 3]); 5] = [
+    ([1.0, 1.0, 1.0], [1.0, 0.0, 0.0]),
+    ([0.0, 0.0, 0.0], [0.0, 0.0, 0.0]),
+    ([1.0, 0.0, 0.0], [0.627955, 0.224863, 0.125846]),
+    ([0.0, 1.0, 0.0], [0.866440, -0.233888, 0.179498]),
+    ([0.0, 0.0, 1.0], [0.452014, -0.032457, -0.311528]),
+];
+
+#[test]
+fn oklab_conversions_match_the_reference_values() {
+    for (rgb, lab) in OKLAB_REFERENCES {
+        let forward = srgb_to_oklab(rgb);
+        let inverse = oklab_to_srgb(lab);
+        for c in 0..3 {
+            assert!(
+                (forward[c] - lab[c]).abs() < 1e-4,
+                "{:?} of {:?}",
+                forward,
+                rgb
+            );
+            assert!(
+                (inverse[c] - rgb[c]).abs() < 1e-4,
+                "{:?} of {:?}",
+                inverse,
+                lab
+            );
+        }
+    }
+
+    // the inverse undoes the conversion of the colors of the gamut
+    for i in 0..=10 {
+        for j in 0..=10 {
+            let rgb = [i as f64 / 10.0, j as f64 / 10.0, (i * j % 11) as f64 / 10.0];
+            let back = oklab_to_srgb(srgb_to_oklab(rgb));
+            for c in 0..3 {
+                assert!((back[c] - rgb[c]).abs() < 1e-6, "{:?} of {:?}", back, rgb);
+            }
+        }
+    }
+}
+
+#[test]
+fn color_clamp_sends_the_counts_out_of_range_to_the_ends() {
+    let options = DrawOptions {
+        clamp: Some((20.0, 60.0)),
+        ..draw_options(100)
+    };
+    let levels = levels(options);
+    assert!(
+        levels[..=20].iter().all(|&level| level == 0),
+        "{:?}",
+        &levels[..=20]
+    );
+    assert!(
+        levels[60..100].iter().all(|&level| level == 255),
+        "{:?}",
+        &levels[60..100]
+    );
+    // a linear stretch in between, the point of the set keeps its color
+    assert!(levels[21..60].windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(levels[40].abs_diff(127) <= 1, "{}", levels[40]);
+    assert_eq!(levels[100], 255);
+}
+
+#[test]
+fn brightest_pixels_face_the_light() {
+    // far from the set the relief rises toward it from every side, away from the origin
+    // the normals lean outward
+    let view = view((-3.0, 3.0, -3.0, 3.0), 60, 60);
+    for degrees in [0.0f64, 45.0, 90.0, 180.0, 270.0] {
+        let azimuth = degrees.to_radians();
+        let options = CalcOptions {
+            lighting: Some((azimuth, 45f64.to_radians())),
+            ..calc_options(200)
+        };
+        let Escaped::Hsv(pixels) = calc_escaped(&options, &view, &ProgressBar::hidden()) else {
+            panic!("counts and shades");
+        };
+        // unit directions of the escaping points from the origin with their shades
+        let mut shades: Vec<(f64, (f64, f64))> = pixels
+            .iter()
+            .enumerate()
+            .filter(|(_, pixel)| pixel[0] < 200.0)
+            .map(|(i, &[_, shade])| {
+                let (cx, cy) = view.pixel_to_complex(i % 60, i / 60).unwrap();
+                (shade, (cx / cx.hypot(cy), cy / cx.hypot(cy)))
+            })
+            .collect();
+        shades.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let brightest = &shades[..shades.len() / 20];
+        let (x, y) = brightest
+            .iter()
+            .fold((0.0, 0.0), |(x, y), (_, (dx, dy))| (x + dx, y + dy));
+        let off = (y.atan2(x) - azimuth + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU)
+            - std::f64::consts::PI;
+        assert!(
+            off.abs() < 20f64.to_radians(),
+            "{} degrees off at {}",
+            off.to_degrees(),
+            degrees
+        );
+
+        // and the side of the light is the brighter one
+        let side = |lit: bool| {
+            let side: Vec<f64> = shades
+                .iter()
+                .filter(|(_, (dx, dy))| (dx * azimuth.cos() + dy * azimuth.sin() > 0.0) == lit)
+                .map(|&(shade, _)| shade)
+                .collect();
+            side.iter().sum::<f64>() / side.len() as f64
+        };
+        assert!(side(true) > side(false) + 0.3, "at {}", degrees);
+    }
+}
+
+#[test]
+fn orbits_escaping_at_the_last_iteration_reach_the_encoder_finite() {
+    // on the real axis past the cusp the orbits take longer to escape toward 0.25
+    let iters = 50;
+    let cx = (0..100_000)
+        .map(|i| 0.25 + i as f64 * 1e-5)
+        .find(|&cx| {
+            mandelbrot_norm_at_point(cx, 0.0, iters, 2, SMOOTH_THRESHOLD).0 == iters as u64 - 1
+        })
+        .expect("a point escaping at the last iteration");
+    let view = view((cx, cx + 1e-9, 0.0, 1e-9), 1, 1);
+    let smooth = CalcOptions {
+        smooth: true,
+        ..calc_options(iters)
+    };
+    let escaped = calc_escaped(&smooth, &view, &ProgressBar::hidden());
+    let Escaped::Smooth(values) = &escaped else {
+        panic!("smooth counts");
+    };
+    assert!(
+        values[0].is_finite() && values[0] <= (iters - 1) as f64,
+        "{}",
+        values[0]
+    );
+    assert!(values[0] > (iters - 2) as f64, "{}", values[0]);
+}
+
+#[test]
+fn degenerate_values_get_their_own_color() {
+    // a NaN and infinities of overflowing orbits among finite smooth counts
+    let values = vec![
+        f64::NAN,
+        10.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        100.0,
+        49.0,
+    ];
+    let red = image::Rgb([255, 0, 0]);
+    for palette in [Palette::Grayscale, Palette::Fire] {
+        let options = DrawOptions {
+            palette,
+            ..draw_options(100)
+        };
+        let img = color_image(&Escaped::Smooth(values.clone()), 6, 1, options).unwrap();
+        let plain = img.to_rgb8();
+        // the last escape count without a color of their own
+        let last = color_image(&Escaped::Smooth(vec![99.0]), 1, 1, options)
+            .unwrap()
+            .to_rgb8();
+        for x in [0, 2, 3] {
+            assert_eq!(
+                plain.get_pixel(x, 0),
+                last.get_pixel(0, 0),
+                "{:?} at {}",
+                palette,
+                x
+            );
+        }
+
+        let colored = DrawOptions {
+            degenerate_color: Some(red),
+            ..options
+        };
+        let img = color_image(&Escaped::Smooth(values.clone()), 6, 1, colored).unwrap();
+        let colored = img.to_rgb8();
+        for x in 0..6 {
+            let expected = if [0, 2, 3].contains(&x) {
+                &red
+            } else {
+                plain.get_pixel(x, 0)
+            };
+            assert_eq!(colored.get_pixel(x, 0), expected, "{:?} at {}", palette, x);
+        }
+    }
+}
+
+#[test]
+fn histogram_rejects_the_distances() {
+    let options = DrawOptions {
+        color_mode: ColorMode::Histogram,
+        ..draw_options(10)
+    };
+    let distances = Escaped::Distance(vec![0.0, 0.5, 12.0, 3e4]);
+    assert!(matches!(
+        color_image(&distances, 4, 1, options),
+        Err(MandelbrotError::UnsupportedColorMode(_))
+    ));
+    let counts = Escaped::Counts(vec![0, 3, 9, 10]);
+    assert!(color_image(&counts, 4, 1, options).is_ok());
+}
@@ -0,0 +1,96 @@
+//! Views, options and scratch directories shared by the tests of the library.
+
+#![allow(dead_code)]
+
+use mandelbrot::{
+    BitDepth, CalcOptions, ColorMode, DrawOptions, Offsets, Palette, Precision, SamplePattern,
+    Scale, SimdWidth, Variant, View, TILE,
+};
+use std::{fs, path::PathBuf};
+
+/// Squared bailout radius of the command line default
+pub const THRESHOLD: f64 = 4.0;
+
+/// Unrotated view of the bounds `(x_min, x_max, y_min, y_max)`
+pub fn view(
+    (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+) -> View {
+    View {
+        x_min,
+        x_max,
+        y_min,
+        y_max,
+        width,
+        height,
+        rows: (0, height),
+        columns: (0, width),
+        deadline: None,
+        offsets: Offsets::None,
+        rotation: 0.0,
+        rows_per_task: TILE,
+    }
+}
+
+/// View of the whole Mandelbrot set, symmetric about the real axis
+pub fn full_view(width: usize, height: usize) -> View {
+    let aspect = width as f64 / height as f64;
+    view(Variant::Mandelbrot.default_coords(aspect), width, height)
+}
+
+/// The options of the command line defaults with `iters` iterations
+pub fn calc_options(iters: usize) -> CalcOptions {
+    CalcOptions {
+        variant: Variant::Mandelbrot,
+        iters,
+        threshold: THRESHOLD,
+        smooth: false,
+        simd_width: SimdWidth::Four,
+        precision: Precision::F64,
+        fast_interior: true,
+        samples: 1,
+        pattern: SamplePattern::Grid,
+        seed: 0,
+        distance: false,
+        trap: None,
+        stripe: None,
+        tia: false,
+        phase: false,
+        hsv: false,
+        lighting: None,
+        deep: false,
+        adaptive: None,
+        scalar: false,
+    }
+}
+
+/// The coloring of the command line defaults with `iters` iterations
+pub fn draw_options(iters: usize) -> DrawOptions<'static> {
+    DrawOptions {
+        iters,
+        bit_depth: BitDepth::Eight,
+        palette: Palette::Grayscale,
+        color_mode: ColorMode::Linear,
+        mask: false,
+        gamma: 1.0,
+        scale: Scale::Linear,
+        palette_offset: 0.0,
+        set_color: None,
+        degenerate_color: None,
+        invert: false,
+        gradient: None,
+        dither: None,
+        hue_scale: 0.02,
+        clamp: None,
+    }
+}
+
+/// Empty directory of the test `name` in the temporary directory, left over files of
+/// an earlier run are removed
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mandelbrot-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("temporary directory is writable");
+    dir
+}
@@ -0,0 +1,198 @@
+//! The pictures saved in the formats of the output extensions, read back with the decoders
+//! of the image crate, and the escape values exported as CSV.
+
+mod common;
+
+use common::{draw_options, full_view, scratch_dir, THRESHOLD};
+use image::DynamicImage;
+use indicatif::ProgressBar;
+use mandelbrot::{
+    calc_mandelbrot, color_image, save_image, write_ppm, BitDepth, DrawOptions, Escaped,
+    MandelbrotError,
+};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Picture of the whole set in the colors of the default palette
+fn picture(width: usize, height: usize) -> DynamicImage {
+    let view = full_view(width, height);
+    let counts = calc_mandelbrot(100, THRESHOLD, true, &view, &ProgressBar::hidden());
+    let options = draw_options(100);
+    color_image(
+        &Escaped::Counts(counts),
+        width as u32,
+        height as u32,
+        options,
+    )
+    .unwrap()
+}
+
+#[test]
+fn webp_pictures_round_trip_lossless() {
+    let img = DynamicImage::ImageRgb8(picture(45, 26).to_rgb8());
+    let path = scratch_dir("formats-webp").join("set.webp");
+    save_image(img.clone(), &path, None).unwrap();
+
+    let decoded = image::open(&path).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (45, 26));
+    assert_eq!(decoded.to_rgb8(), img.to_rgb8());
+}
+
+#[test]
+fn webp_pictures_reject_a_quality() {
+    let path = scratch_dir("formats-webp-quality").join("set.webp");
+    let result = save_image(picture(8, 8), &path, Some(80));
+
+    assert!(matches!(
+        result,
+        Err(MandelbrotError::UnsupportedQuality(_))
+    ));
+    assert!(!path.exists());
+}
+
+#[test]
+fn every_format_round_trips_the_size() {
+    let img = picture(45, 26);
+    let dir = scratch_dir("formats-size");
+    for ext in ["png", "jpg", "jpeg", "bmp", "tiff", "ppm", "webp"] {
+        let path = dir.join(format!("set.{}", ext));
+        save_image(img.clone(), &path, None).unwrap();
+
+        let decoded = image::open(&path).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (45, 26), "{}", ext);
+        if ext != "jpg" && ext != "jpeg" {
+            assert_eq!(decoded.to_rgb8(), img.to_rgb8(), "{}", ext);
+        }
+    }
+}
+
+#[test]
+fn jpeg_quality_reaches_the_encoder() {
+    let img = picture(90, 52);
+    let dir = scratch_dir("formats-quality");
+    let size = |quality| {
+        let path = dir.join(format!("set_{}.jpg", quality));
+        save_image(img.clone(), &path, Some(quality)).unwrap();
+        std::fs::metadata(&path).unwrap().len()
+    };
+    assert!(size(10) < size(95));
+}
+
+#[test]
+fn unknown_extensions_are_rejected() {
+    let path = scratch_dir("formats-unknown").join("set.xyz");
+    let result = save_image(picture(8, 8), &path, None);
+
+    assert!(matches!(result, Err(MandelbrotError::UnsupportedFormat(ext)) if ext == "xyz"));
+    assert!(!path.exists());
+}
+
+/// Header of the PNG file at `path`
+fn png_info(path: &Path) -> png::Info<'static> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path).unwrap()));
+    decoder.read_info().unwrap().info().clone()
+}
+
+#[test]
+fn sixteen_bit_pictures_are_encoded_with_16_bits_per_channel() {
+    // every count of 0..=2000 once, the last one in the set
+    let counts: Vec<u64> = (0..=2000).collect();
+    let options = DrawOptions {
+        bit_depth: BitDepth::Sixteen,
+        ..draw_options(2000)
+    };
+    let img = color_image(&Escaped::Counts(counts), 2001, 1, options).unwrap();
+    let path = scratch_dir("formats-16-bit").join("set.png");
+    save_image(img, &path, None).unwrap();
+
+    let info = png_info(&path);
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+
+    let decoded = image::open(&path).unwrap().into_luma16();
+    let mut levels: Vec<u16> = decoded.pixels().map(|p| p.0[0]).collect();
+    assert_eq!(levels.last(), Some(&u16::MAX));
+    // far more levels than the 256 of the 8-bit pictures
+    levels.dedup();
+    assert!(levels.len() > 1000);
+}
+
+/// Magic number, width, height and maximum value of the PNM header of `bytes`, and the
+/// samples after it
+fn parse_pnm(bytes: &[u8]) -> ((String, u32, u32, u32), &[u8]) {
+    // the 4 fields are separated by single newlines and spaces
+    let mut fields = vec![];
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b' ' || b == b'\n' {
+            fields.push(std::str::from_utf8(&bytes[start..i]).unwrap().to_string());
+            start = i + 1;
+            if fields.len() == 4 {
+                break;
+            }
+        }
+    }
+    assert_eq!(fields.len(), 4, "header of 4 fields");
+    let number = |i: usize| fields[i].parse::<u32>().unwrap();
+    (
+        (fields[0].clone(), number(1), number(2), number(3)),
+        &bytes[start..],
+    )
+}
+
+#[test]
+fn ppm_headers_match_the_buffers() {
+    // the grayscale palette of the picture gives a gray one
+    let gray = picture(45, 26);
+    let img = DynamicImage::ImageRgb8(gray.to_rgb8());
+    let deep = DynamicImage::ImageLuma16(gray.to_luma16());
+    for (img, magic, max, bytes) in [
+        (&img, "P6", 255, 3),
+        (&gray, "P5", 255, 1),
+        (&deep, "P5", 65535, 2),
+    ] {
+        let mut out = vec![];
+        write_ppm(img, &mut out).unwrap();
+
+        let (header, samples) = parse_pnm(&out);
+        assert_eq!(header, (magic.to_string(), 45, 26, max));
+        assert_eq!(samples.len(), 45 * 26 * bytes, "{}", magic);
+        // the decoder of the image crate reads the same picture back
+        let decoded = image::load_from_memory(&out).unwrap();
+        assert_eq!(decoded.as_bytes(), img.as_bytes(), "{}", magic);
+    }
+}
+
+#[test]
+fn csv_round_trips_the_size_and_the_values() {
+    let view = full_view(23, 11);
+    let counts = calc_mandelbrot(100, THRESHOLD, true, &view, &ProgressBar::hidden());
+    let path = scratch_dir("formats-csv").join("counts.csv");
+    Escaped::Counts(counts.clone())
+        .save_csv(&path, &view, 100)
+        .unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let mut lines = text.lines();
+    let comment = lines.next().unwrap();
+    assert!(
+        comment.starts_with("# width=23 height=11 iters=100 x_min=-2 "),
+        "{}",
+        comment
+    );
+    let rows: Vec<Vec<u64>> = lines
+        .map(|line| line.split(',').map(|v| v.parse().unwrap()).collect())
+        .collect();
+    assert_eq!(rows.len(), 11);
+    assert!(rows.iter().all(|row| row.len() == 23));
+    assert_eq!(rows.concat(), counts);
+
+    // a buffer of another view is rejected
+    let result = Escaped::Counts(vec![0; 10]).save_csv(&path, &view, 100);
+    assert!(matches!(
+        result,
+        Err(MandelbrotError::InvalidBufferSize {
+            expected: 253,
+            got: 10
+        })
+    ));
+}
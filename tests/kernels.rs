@@ -0,0 +1,258 @@
+//! The kernels of single points of the library, checked at the points of known orbits.
+
+use mandelbrot::{
+    in_main_body, mandelbrot_at_point, mandelbrot_at_vec, mandelbrot_norm_at_point,
+    mandelbrot_norm_at_vec, mandelbrot_period_at_point, mandelbrot_trap_at_point, newton_at_point,
+    Complex4, Trap, NEWTON_ROOTS,
+};
+use num::Complex;
+
+/// Rows of 4 x `quads` points spread over the bounds, 4 adjacent points make up a vector
+fn grid(
+    (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+    quads: usize,
+    rows: usize,
+) -> Vec<([f64; 4], f64)> {
+    let dx = (x_max - x_min) / (4 * quads) as f64;
+    let dy = (y_max - y_min) / rows as f64;
+    (0..rows)
+        .flat_map(|j| {
+            (0..quads).map(move |i| {
+                let real = [0, 1, 2, 3].map(|k| x_min + (4 * i + k) as f64 * dx);
+                (real, y_min + j as f64 * dy)
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn period_is_one_in_the_main_cardioid() {
+    for (cx, cy) in [(-0.1, 0.0), (0.0, 0.0), (0.2, 0.3), (-0.4, -0.5)] {
+        assert_eq!(
+            mandelbrot_period_at_point(cx, cy, 1000),
+            Some(1),
+            "{cx}{cy:+}i"
+        );
+    }
+}
+
+#[test]
+fn period_is_two_in_the_main_bulb() {
+    for (cx, cy) in [(-1.0, 0.0), (-0.9, 0.15), (-1.1, -0.1)] {
+        assert_eq!(
+            mandelbrot_period_at_point(cx, cy, 1000),
+            Some(2),
+            "{cx}{cy:+}i"
+        );
+    }
+}
+
+#[test]
+fn period_is_none_near_the_boundary() {
+    // the cusp of the cardioid and the roots of the main bulb and of the upper period-3
+    // bulb, the orbits of these parabolic points converge too slowly to show a cycle
+    for (cx, cy) in [(0.25, 0.0), (-0.75, 0.0), (-0.125, 0.649519052838329)] {
+        assert_eq!(
+            mandelbrot_period_at_point(cx, cy, 1000),
+            None,
+            "{cx}{cy:+}i"
+        );
+    }
+    // outside of the set there is no cycle at all
+    assert_eq!(mandelbrot_period_at_point(1.0, 1.0, 1000), None);
+}
+
+#[test]
+fn simd_magnitudes_are_frozen_at_the_escape_of_every_lane() {
+    // lanes of the same vector escaping at different iterations, the ones escaping first
+    // keep iterating while the others still run
+    let mut spread = 0;
+    for j in 0..24 {
+        let cy = -1.2 + j as f64 * 0.1;
+        for i in 0..16 {
+            let real = [0, 1, 2, 3].map(|k| -2.2 + (4 * i + k) as f64 * 0.045);
+            let (counts, norms) =
+                mandelbrot_norm_at_vec(&Complex4::from_row(real, cy), 500, 256.0, false);
+
+            for k in 0..4 {
+                let (count, norm) = mandelbrot_norm_at_point(real[k], cy, 500, 2, 256.0);
+                assert_eq!(counts[k], count, "{}{:+}i", real[k], cy);
+                if count < 500 {
+                    assert!(norms[k] > 256.0 && norms[k].is_finite());
+                    assert!(
+                        (norms[k] - norm).abs() <= 1e-9 * norm,
+                        "{}{:+}i",
+                        real[k],
+                        cy
+                    );
+                }
+            }
+            if counts.iter().any(|&count| count != counts[0]) {
+                spread += 1;
+            }
+        }
+    }
+    assert!(spread > 100);
+}
+
+#[test]
+fn main_body_rejection_matches_the_brute_force() {
+    let mut inside = 0;
+    for (real, cy) in grid((-2.5, 1.0, -1.2, 1.2), 18, 48) {
+        let fast = mandelbrot_at_vec(&Complex4::from_row(real, cy), 300, 4.0, true);
+        let brute = mandelbrot_at_vec(&Complex4::from_row(real, cy), 300, 4.0, false);
+        assert_eq!(fast, brute, "{:?}{:+}i", real, cy);
+
+        for (k, &cx) in real.iter().enumerate() {
+            assert_eq!(mandelbrot_at_point(cx, cy, 300, 4.0, true), brute[k]);
+            if in_main_body(cx, cy) {
+                assert_eq!(brute[k], 300, "{}{:+}i", cx, cy);
+                inside += 1;
+            }
+        }
+    }
+    assert!(inside > 500);
+}
+
+#[test]
+fn periodicity_check_matches_the_brute_force() {
+    // the period-3 bulb on top of the cardioid and the filaments around it, away from the
+    // main body, so the periodicity check decides the points of the set
+    let mut periodic = 0;
+    for (real, cy) in grid((-0.3, 0.05, 0.6, 0.9), 10, 30) {
+        for cx in real {
+            let fast = mandelbrot_at_point(cx, cy, 1000, 4.0, true);
+            let brute = mandelbrot_at_point(cx, cy, 1000, 4.0, false);
+            assert_eq!(fast, brute, "{}{:+}i", cx, cy);
+            if brute == 1000 && !in_main_body(cx, cy) {
+                periodic += 1;
+            }
+        }
+    }
+    assert!(periodic > 100);
+}
+
+#[test]
+fn periodic_lanes_finish_apart_from_the_other_lanes() {
+    // vectors mixing the lanes found periodic with the ones escaping later, every lane
+    // has to end with the count of the brute force
+    let mut mixed = 0;
+    for (real, cy) in grid((-0.3, 0.05, 0.6, 0.9), 10, 30) {
+        let fast = mandelbrot_at_vec(&Complex4::from_row(real, cy), 1000, 4.0, true);
+        let brute = real.map(|cx| mandelbrot_at_point(cx, cy, 1000, 4.0, false));
+        assert_eq!(fast, brute, "{:?}{:+}i", real, cy);
+
+        let periodic = (0..4).any(|k| brute[k] == 1000 && !in_main_body(real[k], cy));
+        if periodic && brute.iter().any(|&count| count > 16 && count < 1000) {
+            mixed += 1;
+        }
+    }
+    assert!(mixed > 10);
+}
+
+#[test]
+fn simd_and_scalar_counts_are_identical() {
+    let mut spread = 0;
+    for fast_interior in [false, true] {
+        for (real, cy) in grid((-2.2, 0.8, -1.2, 1.2), 20, 40) {
+            let counts = mandelbrot_at_vec(&Complex4::from_row(real, cy), 500, 4.0, fast_interior);
+            let scalar = real.map(|cx| mandelbrot_at_point(cx, cy, 500, 4.0, fast_interior));
+            assert_eq!(counts, scalar, "{:?}{:+}i", real, cy);
+
+            // lanes escaping at different iterations, the count of a lane escaping
+            // before the others has to stop there
+            if counts.iter().any(|&count| count != counts[0]) {
+                spread += 1;
+            }
+        }
+    }
+    assert!(spread > 500);
+}
+
+#[test]
+fn point_trap_distance_shrinks_toward_the_origin() {
+    // along rays from the circle of radius 1/4 inside the main cardioid, farther out the
+    // orbits curl back closer to the origin than the orbits of points nearer to it
+    for ray in 0..32 {
+        let angle = ray as f64 * std::f64::consts::PI / 16.0;
+        let mut farther = f64::INFINITY;
+        for step in (0..=200).rev() {
+            let r = 0.25 * step as f64 / 200.0;
+            let (cx, cy) = (r * angle.cos(), r * angle.sin());
+            let (count, distance) = mandelbrot_trap_at_point(cx, cy, 500, 4.0, Trap::Point);
+
+            assert_eq!(count, 500);
+            // the orbit starts at c
+            assert!(distance <= r + 1e-15, "{cx}{cy:+}i");
+            assert!(distance <= farther, "{cx}{cy:+}i");
+            farther = distance;
+        }
+        assert_eq!(farther, 0.0);
+    }
+}
+
+#[test]
+fn newton_converges_to_the_root_of_its_sector() {
+    // the roots themselves, then points along the rays of the roots
+    for (i, root) in NEWTON_ROOTS.iter().enumerate() {
+        assert_eq!(newton_at_point(root.re, root.im, 50), Some((i, 0)));
+        for r in [0.5, 2.0, 10.0, 1e3] {
+            let (cx, cy) = (root.re * r, root.im * r);
+            let (found, steps) = newton_at_point(cx, cy, 50).expect("converges");
+            assert_eq!(found, i, "{cx}{cy:+}i");
+            assert!(steps > 0);
+        }
+    }
+    // far away every step goes about 2/3 of the way to the origin
+    let steps = |r: f64| newton_at_point(r, 0.0, 100).unwrap().1;
+    assert!(steps(10.0) < steps(1e3));
+
+    // the critical point, and too few steps to get there
+    assert_eq!(newton_at_point(0.0, 0.0, 50), None);
+    assert_eq!(newton_at_point(1e3, 0.0, 3), None);
+}
+
+#[test]
+fn newton_basins_are_mirrored_about_the_real_axis() {
+    // conjugate points converge to the conjugate roots, 1 being its own conjugate
+    let mirror = [0, 2, 1];
+    for j in 1..20 {
+        for i in -20..20 {
+            let (cx, cy) = (i as f64 * 0.11, j as f64 * 0.13);
+            let above = newton_at_point(cx, cy, 100);
+            let below = newton_at_point(cx, -cy, 100);
+            assert_eq!(
+                above.map(|(root, steps)| (mirror[root], steps)),
+                below,
+                "{cx}{cy:+}i"
+            );
+        }
+    }
+}
+
+/// Lanes of the real and imaginary parts as complex numbers
+fn lanes(z: Complex4) -> [Complex<f64>; 4] {
+    let (real, imag) = (z.real.to_array(), z.imag.to_array());
+    std::array::from_fn(|i| Complex::new(real[i], imag[i]))
+}
+
+#[test]
+fn complex4_arithmetic_matches_num_complex() {
+    let a = Complex4::from_parts([0.0, -1.5, 0.25, 3.0e-8], [1.0, -0.75, -2.0, 1.0e5]);
+    let b = Complex4::from_parts([-0.3, 2.0, 0.0, -1.0e-3], [0.1, -0.5, 4.0, -7.0]);
+    let (za, zb) = (lanes(a), lanes(b));
+
+    let norm_sqr = a.norm_sqr().to_array();
+    let (square, abs, sum) = (lanes(a.square()), lanes(a.abs()), lanes(a + b));
+    for i in 0..4 {
+        assert_eq!(norm_sqr[i], za[i].norm_sqr(), "lane {}", i);
+        assert_eq!(square[i], za[i] * za[i], "lane {}", i);
+        assert_eq!(
+            abs[i],
+            Complex::new(za[i].re.abs(), za[i].im.abs()),
+            "lane {}",
+            i
+        );
+        assert_eq!(sum[i], za[i] + zb[i], "lane {}", i);
+    }
+}
@@ -0,0 +1,365 @@
+//! The engine used as a dependency: the escape counts and the pictures of the library
+//! without the command line.
+
+mod common;
+
+use common::{calc_options, draw_options, full_view, scratch_dir, view, THRESHOLD};
+use indicatif::ProgressBar;
+use mandelbrot::{
+    burning_ship_at_point, calc_burning_ship, calc_escaped, calc_mandelbrot, calc_mandelbrot_f32,
+    calc_multibrot, calc_tricorn, color_image, draw_mandelbrot, mandelbrot_at_point,
+    tricorn_at_point, CalcOptions, Escaped, SamplePattern, Variant, View,
+};
+use num::Complex;
+
+#[test]
+fn calc_mandelbrot_counts_every_pixel_like_the_point_kernel() {
+    let view = full_view(61, 37);
+    let counts = calc_mandelbrot(200, THRESHOLD, false, &view, &ProgressBar::hidden());
+
+    assert_eq!(counts.len(), 61 * 37);
+    for (i, &count) in counts.iter().enumerate() {
+        let (cx, cy) = view.pixel_to_complex(i % 61, i / 61).unwrap();
+        assert_eq!(count, mandelbrot_at_point(cx, cy, 200, THRESHOLD, false));
+    }
+    // the middle of the main cardioid and the far corner of the view
+    let center = view.complex_to_pixel(-0.25, 0.0).unwrap();
+    assert_eq!(counts[center.1 * 61 + center.0], 200);
+    assert!(counts[0] < 5);
+}
+
+#[test]
+fn rows_of_any_width_end_like_the_point_kernel() {
+    // the last 1 to 3 pixels of the rows are in a vector of their own
+    for width in (1..=9).chain([3841]) {
+        let view = view((-1.8, 0.6, -0.7, 0.6), width, 3);
+        for fast in [false, true] {
+            let counts = calc_mandelbrot(200, THRESHOLD, fast, &view, &ProgressBar::hidden());
+
+            assert_eq!(counts.len(), width * 3);
+            for (i, &count) in counts.iter().enumerate() {
+                let (cx, cy) = view.pixel_to_complex(i % width, i / width).unwrap();
+                let expected = mandelbrot_at_point(cx, cy, 200, THRESHOLD, fast);
+                assert_eq!(count, expected, "pixel {} of rows {} wide", i, width);
+            }
+        }
+    }
+}
+
+#[test]
+fn color_image_fills_a_picture_of_the_view() {
+    let counts = calc_mandelbrot(
+        100,
+        THRESHOLD,
+        true,
+        &full_view(40, 30),
+        &ProgressBar::hidden(),
+    );
+    let img = color_image(&Escaped::Counts(counts), 40, 30, draw_options(100)).unwrap();
+
+    assert_eq!((img.width(), img.height()), (40, 30));
+    let gray = img.to_luma8();
+    // the points of the set are white in grayscale, the far escaping ones dark
+    assert!(gray.pixels().any(|p| p.0[0] == 255));
+    assert!(gray.pixels().any(|p| p.0[0] < 10));
+}
+
+#[test]
+fn draw_mandelbrot_saves_the_picture() {
+    let path = scratch_dir("library").join("set.png");
+    let counts = calc_mandelbrot(
+        50,
+        THRESHOLD,
+        true,
+        &full_view(32, 18),
+        &ProgressBar::hidden(),
+    );
+    draw_mandelbrot(
+        Escaped::Counts(counts),
+        32,
+        18,
+        draw_options(50),
+        &path,
+        Some(90),
+    )
+    .unwrap();
+
+    let img = image::open(&path).unwrap();
+    assert_eq!((img.width(), img.height()), (32, 18));
+}
+
+#[test]
+fn color_image_gives_the_pixels_draw_mandelbrot_saves() {
+    let view = full_view(32, 18);
+    let counts = calc_mandelbrot(50, THRESHOLD, true, &view, &ProgressBar::hidden());
+    let img = color_image(&Escaped::Counts(counts.clone()), 32, 18, draw_options(50)).unwrap();
+
+    // pixels are at hand without a file, the grayscale level of the escape ratio
+    let gray = img.to_luma8();
+    for (i, &count) in counts.iter().enumerate() {
+        let level = gray.get_pixel(i as u32 % 32, i as u32 / 32).0[0];
+        let expected = count as f32 * 255.0 / 50.0;
+        assert!((f32::from(level) - expected).abs() <= 1.0, "pixel {}", i);
+    }
+
+    let path = scratch_dir("library-pixels").join("set.png");
+    draw_mandelbrot(
+        Escaped::Counts(counts),
+        32,
+        18,
+        draw_options(50),
+        &path,
+        None,
+    )
+    .unwrap();
+    assert_eq!(image::open(&path).unwrap().to_luma8(), gray);
+}
+
+#[test]
+fn draw_mandelbrot_rejects_a_buffer_of_another_size() {
+    let path = scratch_dir("library-size").join("set.png");
+    let result = draw_mandelbrot(
+        Escaped::Counts(vec![0; 10]),
+        4,
+        4,
+        draw_options(50),
+        &path,
+        None,
+    );
+    assert!(result.is_err());
+    assert!(!path.exists());
+}
+
+#[test]
+fn calc_burning_ship_counts_every_pixel_like_the_point_kernel() {
+    // the default view of the ship and one centered on the real axis, which the ship
+    // isn't symmetric about
+    for bounds in [
+        Variant::BurningShip.default_coords(61.0 / 37.0),
+        (-2.0, 1.5, -1.2, 1.2),
+    ] {
+        let view = view(bounds, 61, 37);
+        let counts = calc_burning_ship(200, THRESHOLD, &view, &ProgressBar::hidden());
+
+        for (i, &count) in counts.iter().enumerate() {
+            let (cx, cy) = view.pixel_to_complex(i % 61, i / 61).unwrap();
+            assert_eq!(
+                count,
+                burning_ship_at_point(cx, cy, 200, THRESHOLD).0,
+                "pixel {}",
+                i
+            );
+        }
+        assert!(counts.contains(&200));
+    }
+}
+
+/// Escape count of z = z^power + c by `Complex::powu`, from z_1 = c like the kernels
+fn multibrot_at_point(c: Complex<f64>, iters: usize, power: u32) -> u64 {
+    let mut z = c;
+    for i in 0..iters {
+        if z.norm_sqr() > THRESHOLD {
+            return i as u64;
+        }
+        z = z.powu(power) + c;
+    }
+    iters as u64
+}
+
+#[test]
+fn calc_multibrot_counts_every_pixel_like_powu() {
+    for power in [3, 4] {
+        let view = view((-1.6, 1.6, -1.2, 1.2), 57, 43);
+        let counts = calc_multibrot(150, power, THRESHOLD, &view, &ProgressBar::hidden());
+
+        for (i, &count) in counts.iter().enumerate() {
+            let (cx, cy) = view.pixel_to_complex(i % 57, i / 57).unwrap();
+            let reference = multibrot_at_point(Complex::new(cx, cy), 150, power);
+            assert_eq!(count, reference, "power {}, pixel {}", power, i);
+        }
+        assert!(counts.contains(&150));
+    }
+}
+
+#[test]
+fn f32_counts_stay_close_to_the_f64_ones_on_shallow_views() {
+    for view in [full_view(96, 54), view((-0.80, -0.70, 0.05, 0.15), 96, 64)] {
+        let f64 = calc_mandelbrot(300, THRESHOLD, true, &view, &ProgressBar::hidden());
+        let f32 = calc_mandelbrot_f32(300, THRESHOLD, true, &view, &ProgressBar::hidden());
+
+        // the rounding of f32 moves the escape of a few slow points along the boundary
+        let same = f64.iter().zip(&f32).filter(|(a, b)| a == b).count();
+        let close = f64
+            .iter()
+            .zip(&f32)
+            .filter(|(a, b)| a.abs_diff(**b) <= 2)
+            .count();
+        assert!(
+            same as f64 >= 0.98 * f64.len() as f64,
+            "{} equal counts",
+            same
+        );
+        assert!(
+            close as f64 >= 0.99 * f64.len() as f64,
+            "{} close counts",
+            close
+        );
+    }
+}
+
+/// Smooth counts of the view with `samples` x `samples` samples per pixel
+fn supersampled(view: &View, samples: usize, pattern: SamplePattern, seed: u64) -> Vec<f64> {
+    let options = CalcOptions {
+        smooth: true,
+        samples,
+        pattern,
+        seed,
+        ..calc_options(200)
+    };
+    let Escaped::Smooth(values) = calc_escaped(&options, view, &ProgressBar::hidden()) else {
+        panic!("smooth counts");
+    };
+    values
+}
+
+/// Mean squared difference of the values from the reference
+fn variance(values: &[f64], reference: &[f64]) -> f64 {
+    let sum: f64 = values
+        .iter()
+        .zip(reference)
+        .map(|(v, r)| (v - r).powi(2))
+        .sum();
+    sum / values.len() as f64
+}
+
+#[test]
+fn supersampling_brings_the_edge_pixels_closer_to_their_averages() {
+    // the edge of the set and its filaments, 8 x 8 samples are close to the average of the
+    // smooth counts over every pixel
+    let view = view((-0.76, -0.72, 0.08, 0.12), 40, 40);
+    let reference = supersampled(&view, 8, SamplePattern::Grid, 0);
+    let single = variance(&supersampled(&view, 1, SamplePattern::Grid, 0), &reference);
+    for pattern in [
+        SamplePattern::Grid,
+        SamplePattern::RotatedGrid,
+        SamplePattern::Random,
+    ] {
+        let values = supersampled(&view, 3, pattern, 0);
+        let supersampled = variance(&values, &reference);
+        assert!(
+            supersampled < single / 4.0,
+            "{} with 3 x 3 samples of {:?}, {} with one",
+            supersampled,
+            pattern,
+            single
+        );
+    }
+}
+
+#[test]
+fn rotated_grid_keeps_the_straight_filaments_apart_from_the_samples() {
+    // along the antenna of the set on the real axis, a row of samples of the 3 x 3 grid
+    // lies on the axis and each of its pixels gets a third of the set, the turned rows
+    // only cross it
+    let view = view((-1.95, -1.75, -0.07, 0.05), 40, 40);
+    let reference = supersampled(&view, 8, SamplePattern::Grid, 0);
+    let grid = variance(&supersampled(&view, 3, SamplePattern::Grid, 0), &reference);
+    let rotated = variance(
+        &supersampled(&view, 3, SamplePattern::RotatedGrid, 0),
+        &reference,
+    );
+    assert!(
+        rotated < grid / 2.0,
+        "{} along the grid, {} turned",
+        grid,
+        rotated
+    );
+}
+
+#[test]
+fn random_samples_follow_the_seed() {
+    let view = view((-0.76, -0.72, 0.08, 0.12), 40, 40);
+    let random = |seed| supersampled(&view, 2, SamplePattern::Random, seed);
+    assert_eq!(random(7), random(7));
+    assert_ne!(random(7), random(8));
+}
+
+#[test]
+fn flipping_the_rows_twice_gives_the_buffer_back() {
+    // rows above and below the real axis, with and without a middle row
+    for height in [7, 8] {
+        let view = view((-2.0, 0.5, -0.3, 1.1), 13, height);
+        let counts = calc_mandelbrot(100, THRESHOLD, true, &view, &ProgressBar::hidden());
+        let mut escaped = Escaped::Counts(counts.clone());
+
+        escaped.flip_rows(13);
+        let Escaped::Counts(flipped) = &escaped else {
+            panic!("escape counts");
+        };
+        let rows: Vec<_> = counts.chunks(13).rev().flatten().copied().collect();
+        assert_eq!(flipped, &rows);
+        assert_ne!(flipped, &counts);
+
+        escaped.flip_rows(13);
+        let Escaped::Counts(twice) = escaped else {
+            panic!("escape counts");
+        };
+        assert_eq!(twice, counts);
+    }
+}
+
+#[test]
+fn tricorn_has_three_fold_symmetry() {
+    // the conjugate squaring commutes with the turns of a third about the origin
+    let view = view((-2.0, 2.0, -2.0, 2.0), 81, 81);
+    let counts = calc_tricorn(100, THRESHOLD, &view, &ProgressBar::hidden());
+    assert!(counts.contains(&100) && counts.iter().any(|&count| count < 3));
+
+    for turns in [1.0, 2.0] {
+        let angle = turns * std::f64::consts::TAU / 3.0;
+        let (sin, cos) = angle.sin_cos();
+        for (i, &count) in counts.iter().enumerate() {
+            let (cx, cy) = view.pixel_to_complex(i % 81, i / 81).unwrap();
+            let (turned, _) =
+                tricorn_at_point(cx * cos - cy * sin, cx * sin + cy * cos, 100, THRESHOLD);
+            assert_eq!(count, turned, "pixel {} turned {} thirds", i, turns);
+        }
+    }
+    // and the mirror symmetry about the real axis, row 0 at -2 mirrors the row at 2 just
+    // outside of the view
+    let rows: Vec<_> = counts.chunks(81).collect();
+    for y in 1..81 {
+        assert_eq!(rows[y], rows[81 - y], "row {}", y);
+    }
+}
+
+/// View of `width` x `height` pixels of the width `size` about the center
+fn centered((re, im): (f64, f64), size: f64, width: usize, height: usize) -> View {
+    let half = (size / 2.0, size * height as f64 / width as f64 / 2.0);
+    view(
+        (re - half.0, re + half.0, im - half.1, im + half.1),
+        width,
+        height,
+    )
+}
+
+#[test]
+fn views_too_small_for_f64_are_flagged() {
+    let seahorse = (-0.743643887037151, 0.131825904205330);
+    for size in [3.0, 1e-3, 1e-8, 1e-11] {
+        assert!(
+            !centered(seahorse, size, 1920, 1080).beyond_f64(),
+            "{}",
+            size
+        );
+    }
+    assert!(centered(seahorse, 1e-15, 1920, 1080).beyond_f64());
+    assert!(centered(seahorse, 1e-15, 48, 32).beyond_f64());
+
+    // the pixels are measured against the magnitude of the coordinates
+    assert!(!centered((0.0, 0.0), 1e-15, 1920, 1080).beyond_f64());
+    assert!(centered((-1.999, 0.0), 1e-13, 1920, 1080).beyond_f64());
+    // and along the shorter side of the pixels
+    let flat = view((-0.8, -0.7, 0.1, 0.1 + 1e-15), 100, 100);
+    assert!(flat.beyond_f64());
+}
@@ -0,0 +1,263 @@
+//! Ways of splitting up the calculation of a view that must not change its escape values.
+
+mod common;
+
+use common::{calc_options, full_view, view, THRESHOLD};
+use indicatif::ProgressBar;
+use mandelbrot::{
+    calc_escaped, calc_mandelbrot, mandelbrot_at_point, mandelbrot_stripe_at_point,
+    mandelbrot_z_at_point, CalcOptions, Escaped, Precision, View, SMOOTH_THRESHOLD,
+};
+use rayon::ThreadPoolBuilder;
+use std::time::{Duration, Instant};
+
+#[test]
+fn rows_past_the_deadline_are_skipped_whole() {
+    // slow rows along the boundary of the set, 3 tiles wide
+    let view = View {
+        rows_per_task: 1,
+        ..view((-0.80, -0.70, 0.05, 0.15), 160, 40)
+    };
+    let full = |rows: (usize, usize)| {
+        let view = View { rows, ..view };
+        calc_mandelbrot(50_000, THRESHOLD, false, &view, &ProgressBar::hidden())
+    };
+
+    let limited = View {
+        deadline: Some(Instant::now() + Duration::from_millis(20)),
+        ..view
+    };
+    let counts = calc_mandelbrot(50_000, THRESHOLD, false, &limited, &ProgressBar::hidden());
+
+    for (y, row) in counts.chunks_exact(160).enumerate() {
+        if row.iter().all(|&count| count == 0) {
+            continue;
+        }
+        assert_eq!(row, full((y, y + 1)), "row {} is calculated in part", y);
+    }
+}
+
+#[test]
+fn mirrored_rows_match_a_full_render() {
+    // views centered on the real axis, with an odd and an even number of rows
+    for view in [full_view(72, 41), view((-0.80, -0.70, -0.05, 0.05), 64, 40)] {
+        assert_eq!(view.y_min, -view.y_max);
+        let counts = calc_mandelbrot(300, THRESHOLD, true, &view, &ProgressBar::hidden());
+        let (width, height) = (view.width, view.height);
+
+        let mut mirrored = 0;
+        for y in 0..height {
+            for x in 0..width {
+                let (cx, cy) = view.pixel_to_complex(x, y).unwrap();
+                let count = mandelbrot_at_point(cx, cy, 300, THRESHOLD, true);
+                assert_eq!(counts[y * width + x], count, "pixel {}, {}", x, y);
+            }
+            if y >= 1 && 2 * y < height {
+                assert_eq!(
+                    counts[y * width..(y + 1) * width],
+                    counts[(height - y) * width..(height - y + 1) * width]
+                );
+                mirrored += 1;
+            }
+        }
+        assert_eq!(mirrored, (height - 1) / 2);
+    }
+}
+
+/// Panics unless the values of the view match `at_point` at every pixel
+fn assert_matches_the_points(values: &[f64], view: &View, at_point: impl Fn(f64, f64) -> f64) {
+    for (i, &value) in values.iter().enumerate() {
+        let (cx, cy) = view
+            .pixel_to_complex(i % view.width, i / view.width)
+            .unwrap();
+        let expected = at_point(cx, cy);
+        assert!(
+            value == expected,
+            "pixel {} at {}{:+}i: {} instead of {}",
+            i,
+            cx,
+            cy,
+            value,
+            expected
+        );
+    }
+}
+
+#[test]
+fn stripe_averages_of_symmetric_views_match_the_point_kernel() {
+    // the averages of conjugate points add up to 1, mirroring the rows would break them
+    let view = full_view(64, 40);
+    let options = CalcOptions {
+        stripe: Some(5.0),
+        ..calc_options(300)
+    };
+    let Escaped::Stripe(values) = calc_escaped(&options, &view, &ProgressBar::hidden()) else {
+        panic!("stripe averages");
+    };
+    assert_matches_the_points(&values, &view, |cx, cy| {
+        mandelbrot_stripe_at_point(cx, cy, 300, SMOOTH_THRESHOLD, 5.0, true)
+    });
+}
+
+#[test]
+fn phases_of_symmetric_views_match_the_point_kernel() {
+    let view = full_view(64, 40);
+    let options = CalcOptions {
+        phase: true,
+        ..calc_options(300)
+    };
+    let Escaped::Stripe(phases) = calc_escaped(&options, &view, &ProgressBar::hidden()) else {
+        panic!("phases");
+    };
+    assert_matches_the_points(&phases, &view, |cx, cy| {
+        let (count, z) = mandelbrot_z_at_point(cx, cy, 300, THRESHOLD, true);
+        if count == 300 {
+            -1.0
+        } else {
+            (z.arg() / std::f64::consts::TAU).rem_euclid(1.0)
+        }
+    });
+
+    // the phases of the rows mirrored about the real axis are opposite, but for the
+    // rounding of the points blown up along the boundary
+    let (width, height) = (view.width, view.height);
+    let pairs: Vec<(f64, f64)> = (1..height / 2)
+        .flat_map(|y| (0..width).map(move |x| (y * width + x, (height - y) * width + x)))
+        .map(|(i, j)| (phases[i], phases[j]))
+        .filter(|&(p, q)| p > 0.0 && q > 0.0)
+        .collect();
+    let opposite = pairs
+        .iter()
+        .filter(|(p, q)| (p + q - 1.0).abs() < 1e-3)
+        .count();
+    assert!(
+        opposite as f64 > 0.95 * pairs.len() as f64,
+        "{} of {}",
+        opposite,
+        pairs.len()
+    );
+}
+
+#[test]
+fn one_thread_gives_the_buffer_of_many() {
+    let view = View {
+        rows_per_task: 1,
+        ..view((-0.80, -0.70, 0.05, 0.15), 150, 90)
+    };
+    let smooth = CalcOptions {
+        smooth: true,
+        ..calc_options(500)
+    };
+    let calc = |threads: usize| {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            let counts = calc_mandelbrot(500, THRESHOLD, true, &view, &ProgressBar::hidden());
+            let Escaped::Smooth(smooth) = calc_escaped(&smooth, &view, &ProgressBar::hidden())
+            else {
+                panic!("smooth escape counts");
+            };
+            (counts, smooth)
+        })
+    };
+
+    let (counts, smooth) = calc(1);
+    for threads in [2, 4, 7] {
+        let (counts_of_many, smooth_of_many) = calc(threads);
+        assert!(counts == counts_of_many, "{} threads", threads);
+        assert!(smooth == smooth_of_many, "{} threads", threads);
+    }
+}
+
+/// View of `width` x `height` pixels of the width `size` about the center
+fn centered((re, im): (f64, f64), size: f64, width: usize, height: usize) -> View {
+    let half = (size / 2.0, size * height as f64 / width as f64 / 2.0);
+    view(
+        (re - half.0, re + half.0, im - half.1, im + half.1),
+        width,
+        height,
+    )
+}
+
+/// Share of the horizontally adjacent pixels of other escape counts
+fn detail(escaped: &Escaped, width: usize) -> f64 {
+    let Escaped::Counts(counts) = escaped else {
+        panic!("integer escape counts");
+    };
+    let rows = counts.chunks_exact(width);
+    let edges: usize = rows
+        .map(|row| row.windows(2).filter(|pair| pair[0] != pair[1]).count())
+        .sum();
+    edges as f64 / (counts.len() - counts.len() / width) as f64
+}
+
+#[test]
+fn double_double_reproduces_f64_on_shallow_views() {
+    let view = view((-0.80, -0.70, 0.05, 0.15), 64, 40);
+    let dd = CalcOptions {
+        precision: Precision::Dd,
+        ..calc_options(300)
+    };
+    let f64 = calc_escaped(&calc_options(300), &view, &ProgressBar::hidden());
+    let dd = calc_escaped(&dd, &view, &ProgressBar::hidden());
+    assert_eq!(f64.checksum(), dd.checksum());
+}
+
+#[test]
+fn double_double_stays_sharp_beyond_f64() {
+    // about 5 pixels a step of the f64 real parts, along a spiral of Seahorse Valley
+    let view = centered((-0.743643887037151, 0.131825904205330), 1e-15, 48, 32);
+    assert!(view.beyond_f64());
+    let dd = CalcOptions {
+        precision: Precision::Dd,
+        ..calc_options(10_000)
+    };
+    let f64 = calc_escaped(&calc_options(10_000), &view, &ProgressBar::hidden());
+    let dd = calc_escaped(&dd, &view, &ProgressBar::hidden());
+    let (f64, dd) = (detail(&f64, 48), detail(&dd, 48));
+    assert!(f64 < 0.3, "f64 pixels of other counts {}", f64);
+    assert!(dd > 0.75, "double-double pixels of other counts {}", dd);
+}
+
+/// Share of the pixels of the same escape counts
+fn agreement(a: &Escaped, b: &Escaped) -> f64 {
+    let (Escaped::Counts(a), Escaped::Counts(b)) = (a, b) else {
+        panic!("integer escape counts");
+    };
+    a.iter().zip(b).filter(|(a, b)| a == b).count() as f64 / a.len() as f64
+}
+
+#[test]
+fn perturbation_matches_the_direct_iterations() {
+    let deep = CalcOptions {
+        deep: true,
+        ..calc_options(5000)
+    };
+    let dd = CalcOptions {
+        precision: Precision::Dd,
+        ..calc_options(5000)
+    };
+    // a moderate zoom that f64 still resolves, then one beyond it
+    let moderate = centered((-0.743643887037151, 0.131825904205330), 1e-6, 48, 32);
+    assert!(!moderate.beyond_f64());
+    let f64 = calc_escaped(&calc_options(5000), &moderate, &ProgressBar::hidden());
+    let perturbed = calc_escaped(&deep, &moderate, &ProgressBar::hidden());
+    let agreed = agreement(&perturbed, &f64);
+    assert!(agreed > 0.97, "{} of the pixels of f64", agreed);
+    let agreed = agreement(
+        &perturbed,
+        &calc_escaped(&dd, &moderate, &ProgressBar::hidden()),
+    );
+    assert!(agreed > 0.99, "{} of the pixels of double-double", agreed);
+
+    let deep_view = centered((-0.743643887037151, 0.131825904205330), 1e-15, 48, 32);
+    assert!(deep_view.beyond_f64());
+    let perturbed = calc_escaped(&deep, &deep_view, &ProgressBar::hidden());
+    let agreed = agreement(
+        &perturbed,
+        &calc_escaped(&dd, &deep_view, &ProgressBar::hidden()),
+    );
+    assert!(agreed > 0.99, "{} of the pixels of double-double", agreed);
+}